@@ -1,3 +1,7 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
 use bugsyth_engine::prelude::*;
 
 #[derive(Clone, Copy)]
@@ -8,7 +12,12 @@ struct Vertex {
 implement_vertex!(Vertex, position, color);
 
 fn main() -> EngineResult {
-    let (event_loop, mut ctx) = init("3d", (960, 720))?;
+    // F11 toggles fullscreen
+    let (event_loop, mut ctx) = init_with_config(
+        WindowConfig::new("3d", (960, 720))
+            .with_multisampling(4)
+            .with_min_size((320, 240)),
+    )?;
     ctx.new_program(
         "3d",
         "
@@ -74,7 +83,7 @@ impl GameState for Game {
     fn update(&mut self, ctx: &mut Context) {
         bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 1.0, 1.0);
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color(0.0, 0.0, 0.0, 1.0);
         renderer
             .draw(
@@ -87,6 +96,21 @@ impl GameState for Game {
             )
             .unwrap();
     }
+    fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::F11),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } = event
+        {
+            ctx.toggle_fullscreen();
+        }
+    }
 }
 
 struct Triangle<'a> {
@@ -3,7 +3,11 @@ use bugsyth_engine::prelude::*;
 fn main() -> EngineResult {
     let (event_loop, mut ctx) = init("Audio", (960, 720))?;
     let game = Game {
-        imgui: bugsyth_engine_imgui_support::init(&ctx.window, &ctx.display, |_, _, _| {}),
+        imgui: bugsyth_engine_imgui_support::init(
+            ctx.window.as_ref().unwrap(),
+            ctx.display.get_window_display().unwrap(),
+            |_, _, _| {},
+        ),
         sound: Sound::new("resources/goron.wav")?,
         speed: audio_play_value::new_audio_play_value(1.0),
         volume: audio_play_value::new_audio_play_value(1.0),
@@ -32,11 +36,11 @@ impl GameState for Game {
     fn update(&mut self, ctx: &mut Context) {
         self.imgui.update_dt(ctx.dt);
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color(0.0, 0.0, 0.0, 1.0);
         self.imgui
             .platform
-            .prepare_frame(self.imgui.context.io_mut(), &ctx.window)
+            .prepare_frame(self.imgui.context.io_mut(), ctx.window.as_ref().unwrap())
             .unwrap();
         let ui = self.imgui.context.frame();
 
@@ -51,7 +55,9 @@ impl GameState for Game {
                 ui.slider("volume", 0.0, 2.0, &mut self.volume_float);
             });
 
-        self.imgui.platform.prepare_render(&ui, &ctx.window);
+        self.imgui
+            .platform
+            .prepare_render(&ui, ctx.window.as_ref().unwrap());
         let draw_data = self.imgui.context.render();
         self.imgui
             .renderer
@@ -61,6 +67,6 @@ impl GameState for Game {
         audio_play_value::atomic_f64_store(&self.volume, self.volume_float);
     }
     fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
-        self.imgui.event(&ctx.window, event);
+        self.imgui.event(ctx.window.as_ref().unwrap(), event);
     }
 }
@@ -7,7 +7,6 @@
 */
 
 use bugsyth_engine::prelude::*;
-use glium::{IndexBuffer, Surface};
 use rayon::iter::{
     IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
 };
@@ -29,10 +28,10 @@ struct Vertex {
 }
 implement_vertex!(Vertex, position, uv);
 #[derive(Clone, Copy)]
-struct Instance {
+struct BunnyInstance {
     instance: [f32; 2],
 }
-implement_vertex!(Instance, instance);
+implement_vertex!(BunnyInstance, instance);
 
 #[derive(Clone, Copy)]
 struct Bunny {
@@ -116,21 +115,18 @@ fn main() -> EngineResult {
     )
     .unwrap();
 
-    let ivbo_data: Vec<Instance> = (0..bunnies.len())
-        .map(|_| Instance {
-            instance: [0.0, 0.0],
-        })
-        .collect();
     let game = Game {
+        sprites: BunnySprites {
+            vbo,
+            ibo,
+            instances: InstanceBuffer::with_capacity(&ctx, bunnies.len())?,
+            params: DrawParameters {
+                blend: Blend::alpha_blending(),
+                ..Default::default()
+            },
+        },
         bunnies,
         tex: Texture::new(&ctx, "resources/wabbit_alpha.png").unwrap(),
-        vbo,
-        ivbo: VertexBuffer::dynamic(&ctx.display, &ivbo_data).unwrap(),
-        ibo,
-        params: DrawParameters {
-            blend: Blend::alpha_blending(),
-            ..Default::default()
-        },
 
         rng,
     };
@@ -138,13 +134,33 @@ fn main() -> EngineResult {
     Ok(())
 }
 
-struct Game {
-    bunnies: Vec<Bunny>,
-    tex: Texture,
+/// Every bunny in one draw call, one instance each
+struct BunnySprites {
     vbo: VertexBuffer<Vertex>,
-    ivbo: VertexBuffer<Instance>,
     ibo: IndexBuffer<u16>,
+    instances: InstanceBuffer<BunnyInstance>,
     params: DrawParameters<'static>,
+}
+
+impl Drawable for BunnySprites {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        (&self.vbo, self.instances.per_instance())
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        &self.ibo
+    }
+    fn get_program(&self) -> String {
+        "bunnymark".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        self.params.clone()
+    }
+}
+
+struct Game {
+    bunnies: Vec<Bunny>,
+    tex: Texture,
+    sprites: BunnySprites,
 
     rng: rng::Rng,
 }
@@ -184,40 +200,31 @@ impl GameState for Game {
                     }
                 }
             });
-        let ivbo_data: Vec<Instance> = self
+        let instances: Vec<BunnyInstance> = self
             .bunnies
             .par_iter()
-            .map(|bunny| Instance {
+            .map(|bunny| BunnyInstance {
                 instance: [bunny.position.x, bunny.position.y],
             })
             .collect();
-        if ivbo_data.len() > self.ivbo.len() {
-            self.ivbo = VertexBuffer::dynamic(&ctx.display, &ivbo_data).unwrap();
-        } else {
-            self.ivbo.write(&ivbo_data);
-        }
-        self.ivbo.write(&ivbo_data);
-        ctx.window.set_title(&format!(
+        self.sprites.instances.set(ctx, &instances).unwrap();
+        ctx.window.as_ref().unwrap().set_title(&format!(
             "Drawing {} bunnies at {:.0} fps",
             self.bunnies.len(),
-            1.0 / ctx.dt
+            ctx.get_frame_stats().get_fps()
         ));
     }
 
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color(0.0, 0.0, 0.0, 1.0);
-        let target = renderer.get_surface_mut();
         let tex = self.tex.get_texture_no_filtering();
-
-        target
+        renderer
             .draw(
-                (&self.vbo, self.ivbo.per_instance().unwrap()),
-                &self.ibo,
-                ctx.get_program("bunnymark").unwrap(),
+                ctx,
+                &self.sprites,
                 &uniform! {
                     tex: tex,
                 },
-                &self.params,
             )
             .unwrap();
     }
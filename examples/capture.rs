@@ -0,0 +1,64 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
+use bugsyth_engine::prelude::*;
+
+// F2 saves a screenshot, F3 records 120 frames at 60 fps into the capture folder
+fn main() -> EngineResult {
+    let (event_loop, ctx) = init("Capture", (960, 720))?;
+    let game = Game {
+        time: 0.0,
+        take_screenshot: false,
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    time: f32,
+    take_screenshot: bool,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        self.time += ctx.dt;
+    }
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color(
+            self.time.sin() * 0.5 + 0.5,
+            (self.time * 1.3).cos() * 0.5 + 0.5,
+            0.5,
+            1.0,
+        );
+        if self.take_screenshot {
+            self.take_screenshot = false;
+            save_png(&renderer.screenshot(ctx).unwrap(), "screenshot.png").unwrap();
+            println!("Saved screenshot.png");
+        }
+    }
+    fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(key_code),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key_code {
+                KeyCode::F2 => self.take_screenshot = true,
+                KeyCode::F3 if !ctx.is_capturing() => {
+                    ctx.start_capture(
+                        FrameCapture::new("capture", CaptureMode::FixedTimestep(1.0 / 60.0))
+                            .with_max_images(120),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+}
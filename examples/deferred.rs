@@ -0,0 +1,130 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
+use bugsyth_engine::{
+    prelude::*,
+    renderer::fxaa::{FXAA, FXAARenderer},
+};
+use std::{cell::RefCell, rc::Rc};
+
+const LIGHTS: usize = 64;
+
+// WASD and the mouse to fly around a field of monkeys lit by lots of small lights.
+// 1 to 6 switch between the lit image, albedo, normals, material, depth and the lights on their own
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("deferred", (960, 720))?;
+    ctx.camera.position = Vec3::new(0.0, 6.0, 14.0);
+    ctx.camera.look_at(Vec3::zero());
+    ctx.lights.ambient = Rgb::broadcast(0.02);
+    FXAA::send_program(&mut ctx)?;
+
+    let (width, height) = ctx.get_render_size();
+    // Both are kept the size of the window, the G-buffer is composited into the FXAA target
+    let gbuffer = Rc::new(RefCell::new(GBuffer::new(&mut ctx, width, height)?));
+    let fxaa = Rc::new(RefCell::new(FXAA::new(&ctx, width, height)?));
+    ctx.resize_with_window(&gbuffer, 1.0);
+    ctx.resize_with_window(&fxaa, 1.0);
+    let mut rng = rng::Rng::new();
+    let lights = (0..LIGHTS)
+        .map(|_| {
+            let color = Rgb::new(rng.f32(), rng.f32(), rng.f32());
+            ctx.lights
+                .add(Light::point(Vec3::zero(), color, 3.0).with_intensity(4.0))
+        })
+        .collect();
+    ctx.lights.add(
+        Light::spot(
+            Vec3::new(0.0, 8.0, 0.0),
+            -Vec3::unit_y(),
+            Rgb::new(1.0, 0.95, 0.8),
+            12.0,
+            0.4,
+        )
+        .with_intensity(30.0),
+    );
+
+    let game = Game {
+        gbuffer,
+        fxaa,
+        monkey: asset::load_wavefront(&ctx, &std::fs::read("resources/suzanne.obj").unwrap())?,
+        lights,
+        time: 0.0,
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    gbuffer: Rc<RefCell<GBuffer>>,
+    fxaa: Rc<RefCell<FXAA>>,
+    monkey: Model,
+    lights: Vec<LightHandle>,
+    time: f32,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 4.0, 1.0);
+        self.time += ctx.dt;
+        for (i, handle) in self.lights.iter().enumerate() {
+            let angle = self.time * 0.5 + i as f32 * 0.7;
+            let distance = 1.0 + (i % 8) as f32 * 1.2;
+            if let Some(Light::Point { position, .. }) = ctx.lights.get_mut(*handle) {
+                *position = Vec3::new(angle.cos() * distance, 0.8, angle.sin() * distance);
+            }
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        let gbuffer = self.gbuffer.borrow();
+        let fxaa = self.fxaa.borrow();
+        let mut gbuffer_renderer = GBufferRenderer::new(ctx, &gbuffer).unwrap();
+        let material =
+            PbrMaterial::new(Rgba::new(0.8, 0.8, 0.8, 1.0)).with_metallic_roughness(0.0, 0.4);
+        for x in -5..=5 {
+            for z in -5..=5 {
+                gbuffer_renderer
+                    .draw_pbr(
+                        ctx,
+                        &self.monkey,
+                        Mat4::translation_3d(Vec3::new(x as f32 * 2.0, 0.0, z as f32 * 2.0)),
+                        &material,
+                    )
+                    .unwrap();
+            }
+        }
+
+        let mut fxaa_renderer = FXAARenderer::new(ctx, &fxaa).unwrap();
+        fxaa_renderer.clear_color_and_depth((0.02, 0.02, 0.03, 1.0), 1.0);
+        gbuffer.composite(ctx, &mut fxaa_renderer).unwrap();
+        fxaa_renderer
+            .draw_frame_buffer(ctx, &fxaa, renderer)
+            .unwrap();
+    }
+
+    fn event(&mut self, _: &mut Context, event: &WindowEvent) {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(key),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return;
+        };
+        self.gbuffer.borrow_mut().view = match key {
+            KeyCode::Digit1 => GBufferView::Lit,
+            KeyCode::Digit2 => GBufferView::Albedo,
+            KeyCode::Digit3 => GBufferView::Normal,
+            KeyCode::Digit4 => GBufferView::Material,
+            KeyCode::Digit5 => GBufferView::Depth,
+            KeyCode::Digit6 => GBufferView::Light,
+            _ => return,
+        };
+    }
+}
@@ -0,0 +1,119 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
+use bugsyth_engine::prelude::*;
+use std::rc::Rc;
+
+// Hold space to spawn bunnies, P plays a sound from the monkey
+const GRAVITY: f32 = 0.6;
+
+struct Velocity(Vec2<f32>);
+struct Spin(f32);
+
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("ECS", (960, 720))?;
+    ctx.camera.position = Vec3::new(0.0, 0.0, 4.0);
+    ctx.camera.look_at(Vec3::zero());
+
+    let mut world = World::new();
+    let monkey = world.spawn((
+        Transform::new(Vec3::zero()),
+        MeshRenderer::new(Rc::new(asset::load_wavefront(
+            &ctx,
+            &std::fs::read("resources/suzanne.obj").unwrap(),
+        )?))
+        .with_color(Rgba::new(0.9, 0.5, 0.3, 1.0)),
+        AudioEmitter::new(),
+        Spin(1.0),
+    ));
+
+    let game = Game {
+        world,
+        monkey,
+        bunny: Rc::new(Texture::new(&ctx, "resources/wabbit_alpha.png")?),
+        sound: Sound::new("resources/goron.wav")?,
+        rng: rng::Rng::new(),
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    world: World,
+    monkey: Entity,
+    bunny: Rc<Texture>,
+    sound: Sound,
+    rng: rng::Rng,
+}
+
+impl Game {
+    fn spawn_bunny(&mut self) {
+        let velocity = Vec2::new(self.rng.f32() * 0.8, self.rng.f32() * 0.4);
+        self.world.spawn((
+            Transform::new(Vec3::new(-1.0, 1.0, self.rng.f32())),
+            Sprite::new(self.bunny.clone(), Vec2::new(0.05, 0.08)),
+            Velocity(velocity),
+        ));
+    }
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        if ctx.input.is_key_pressed(KeyCode::Space) {
+            for _ in 0..100 {
+                self.spawn_bunny();
+            }
+        }
+
+        let dt = ctx.dt;
+        // Runs on the rayon thread pool, the velocity is only read
+        self.world
+            .par_query::<Transform, &Velocity>(|_, transform, velocity| {
+                transform.position += Vec3::new(velocity.0.x, velocity.0.y, 0.0) * dt;
+            })
+            .unwrap();
+        self.world
+            .query::<(&Transform, &mut Velocity)>(|_, (transform, velocity)| {
+                velocity.0.y -= GRAVITY * dt;
+                if transform.position.x.abs() > 1.0 {
+                    velocity.0.x = -velocity.0.x;
+                }
+                if transform.position.y < -1.0 {
+                    velocity.0.y = velocity.0.y.abs() * 0.8;
+                }
+            });
+        self.world
+            .query::<(&mut Transform, &Spin)>(|_, (transform, spin)| {
+                transform.rotate(Quaternion::rotation_y(spin.0 * dt));
+            });
+
+        self.world.update(ctx);
+        ctx.window.as_ref().unwrap().set_title(&format!(
+            "{} entities at {:.0} fps",
+            self.world.len(),
+            ctx.get_frame_stats().get_fps()
+        ));
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.1, 0.1, 0.1, 1.0), 1.0);
+        self.world.draw(ctx, renderer).unwrap();
+    }
+    fn event(&mut self, _: &mut Context, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::KeyP),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } = event
+            && let Some(audio_emitter) = self.world.get_mut::<AudioEmitter>(self.monkey)
+        {
+            audio_emitter.play(&self.sound);
+        }
+    }
+}
@@ -2,6 +2,7 @@ use bugsyth_engine::{
     prelude::*,
     renderer::fxaa::{FXAA, FXAARenderer},
 };
+use std::{cell::RefCell, rc::Rc};
 
 fn main() -> EngineResult {
     let (event_loop, mut ctx) = init("fxaa", (960, 720))?;
@@ -56,7 +57,10 @@ fn main() -> EngineResult {
     )
     .unwrap();
     FXAA::send_program(&mut ctx)?;
-    let window_size = ctx.window.inner_size();
+    let (width, height) = ctx.get_render_size();
+    let fxaa = Rc::new(RefCell::new(FXAA::new(&ctx, width, height)?));
+    // Drawn at a quarter of the size for exaggerated effect, kept that way when the window is resized
+    ctx.resize_with_window(&fxaa, 0.25);
     let game = Game {
         obj: Obj {
             model: asset::load_wavefront(&ctx, &std::fs::read("resources/suzanne.obj").unwrap())?,
@@ -71,8 +75,7 @@ fn main() -> EngineResult {
                 ..Default::default()
             },
         },
-        // Divided by 4 for exaggerated effect
-        fxaa: FXAA::new(&ctx, window_size.width / 4, window_size.height / 4)?,
+        fxaa,
     };
     run(game, event_loop, ctx)?;
     Ok(())
@@ -80,16 +83,17 @@ fn main() -> EngineResult {
 
 struct Game {
     obj: Obj<'static>,
-    fxaa: FXAA,
+    fxaa: Rc<RefCell<FXAA>>,
 }
 
 impl GameState for Game {
     fn update(&mut self, ctx: &mut Context) {
         bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 1.0, 1.0);
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color(0.0, 0.0, 0.0, 1.0);
-        let mut fxaa_renderer = FXAARenderer::new(&ctx, &self.fxaa).unwrap();
+        let fxaa = self.fxaa.borrow();
+        let mut fxaa_renderer = FXAARenderer::new(&ctx, &fxaa).unwrap();
         fxaa_renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
         fxaa_renderer
             .draw(
@@ -103,7 +107,7 @@ impl GameState for Game {
             )
             .unwrap();
         fxaa_renderer
-            .draw_frame_buffer(ctx, &self.fxaa, renderer)
+            .draw_frame_buffer(ctx, &fxaa, renderer)
             .unwrap();
     }
 }
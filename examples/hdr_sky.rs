@@ -0,0 +1,120 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
+use bugsyth_engine::prelude::*;
+use std::rc::Rc;
+
+// WASD and the mouse to look around. Pass a .hdr panorama to use it as the sky, otherwise the sky is
+// made in a shader: up and down move the sun and G switches between the atmosphere and a gradient
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("hdr_sky", (960, 720))?;
+    ctx.camera.position = Vec3::new(0.0, 0.0, 4.0);
+    ctx.camera.look_at(Vec3::zero());
+    Skybox::send_program(&mut ctx)?;
+
+    let panorama = std::env::args().nth(1);
+    let mut game = Game {
+        skybox: match &panorama {
+            Some(path) => Skybox::from_equirectangular(&ctx, 512, path)?,
+            None => Skybox::procedural(&ctx, 128, ProceduralSky::atmosphere(-Vec3::unit_y()))?,
+        },
+        procedural: panorama.is_none(),
+        gradient: false,
+        sun_height: 0.5,
+        monkey: asset::load_wavefront(&ctx, &std::fs::read("resources/suzanne.obj").unwrap())?,
+    };
+    game.rebuild(&mut ctx)?;
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    skybox: Skybox,
+    procedural: bool,
+    gradient: bool,
+    sun_height: f32,
+    monkey: Model,
+}
+
+impl Game {
+    fn sun_direction(&self) -> Vec3<f32> {
+        Vec3::new(-0.6, -self.sun_height, -0.8)
+    }
+
+    /// Bakes the sky again and the lighting that comes from it
+    fn rebuild(&mut self, ctx: &mut Context) -> EngineResult {
+        if self.procedural {
+            let sky = if self.gradient {
+                ProceduralSky::gradient(
+                    Rgb::new(0.1, 0.3, 0.8),
+                    Rgb::new(0.8, 0.85, 0.9),
+                    Rgb::new(0.2, 0.18, 0.15),
+                )
+            } else {
+                ProceduralSky::atmosphere(self.sun_direction())
+            };
+            self.skybox = Skybox::procedural(ctx, 128, sky)?;
+        }
+        ctx.lights.environment = Some(Rc::new(Environment::from_skybox(ctx, &self.skybox)?));
+        ctx.lights.clear();
+        ctx.lights.add(
+            Light::directional(self.sun_direction(), Rgb::new(1.0, 0.9, 0.8)).with_intensity(2.0),
+        );
+        Ok(())
+    }
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 2.0, 1.0);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        renderer
+            .draw(
+                ctx,
+                &self.skybox,
+                &uniform! {
+                    persp: ctx.camera.get_perspective(),
+                    view: ctx.camera.get_view(),
+                    u_skybox: self.skybox.get_cubemap(),
+                },
+            )
+            .unwrap();
+        for (x, metallic) in [(-1.5, 0.0), (1.5, 1.0)] {
+            renderer
+                .draw_pbr(
+                    ctx,
+                    &self.monkey,
+                    Mat4::translation_3d(Vec3::new(x, 0.0, 0.0)),
+                    &PbrMaterial::new(Rgba::new(0.9, 0.9, 0.9, 1.0))
+                        .with_metallic_roughness(metallic, 0.25),
+                )
+                .unwrap();
+        }
+    }
+
+    fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(key),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return;
+        };
+        match key {
+            KeyCode::ArrowUp => self.sun_height = (self.sun_height + 0.05).min(1.5),
+            KeyCode::ArrowDown => self.sun_height = (self.sun_height - 0.05).max(-0.1),
+            KeyCode::KeyG => self.gradient = !self.gradient,
+            _ => return,
+        }
+        self.rebuild(ctx).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use bugsyth_engine::prelude::*;
+
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+implement_vertex!(Vertex, position, color);
+
+// Renders a frame without a window and checks it against a saved image,
+// the image is saved the first time this is ran
+fn main() -> EngineResult {
+    let mut ctx = init_headless((256, 256))?;
+    ctx.new_program(
+        "simple",
+        "
+    #version 140
+
+    in vec2 position;
+    in vec3 color;
+
+    out vec3 v_color;
+
+    void main() {
+        v_color = color;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+    ",
+        "
+    #version 140
+
+    in vec3 v_color;
+
+    out vec4 color;
+
+    void main() {
+        color = vec4(v_color, 1.0);
+    }
+    ",
+        None,
+    )?;
+    let mut game = Game {
+        tri: Triangle {
+            vbo: VertexBuffer::new(
+                &ctx.display,
+                &[
+                    Vertex {
+                        position: [-0.5, -0.5],
+                        color: [1.0, 0.0, 0.0],
+                    },
+                    Vertex {
+                        position: [0.5, 0.5],
+                        color: [0.0, 1.0, 0.0],
+                    },
+                    Vertex {
+                        position: [-0.5, 0.5],
+                        color: [0.0, 0.0, 1.0],
+                    },
+                ],
+            )?,
+        },
+    };
+    game.init(&mut ctx);
+    let image = run_headless_frame(&mut game, &mut ctx, 1.0 / 60.0)?;
+
+    let golden_path = "resources/headless_golden.png";
+    match image::open(golden_path) {
+        Ok(golden) => {
+            let golden = golden.to_rgba8();
+            // Small differences are allowed since drivers don't rasterize exactly the same
+            let max_difference = image
+                .pixels()
+                .zip(golden.pixels())
+                .flat_map(|(a, b)| a.0.iter().zip(b.0.iter()).map(|(a, b)| a.abs_diff(*b)))
+                .max()
+                .unwrap_or(0);
+            if image.dimensions() != golden.dimensions() || max_difference > 2 {
+                image.save("headless_failed.png")?;
+                return Err(EngineError::Error(format!(
+                    "Frame doesn't match {}, saved it to headless_failed.png",
+                    golden_path
+                )));
+            }
+            println!("Frame matches {}", golden_path);
+        }
+        Err(_) => {
+            image.save(golden_path)?;
+            println!("Saved {}", golden_path);
+        }
+    }
+    Ok(())
+}
+
+struct Game {
+    tri: Triangle,
+}
+
+impl GameState for Game {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color(0.0, 0.0, 0.0, 1.0);
+        renderer.draw(ctx, &self.tri, &uniform! {}).unwrap();
+    }
+}
+
+struct Triangle {
+    vbo: VertexBuffer<Vertex>,
+}
+
+impl Drawable for Triangle {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        &self.vbo
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TrianglesList)
+    }
+    fn get_program(&self) -> String {
+        "simple".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        DrawParameters::default()
+    }
+}
@@ -1,115 +1,114 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
 use bugsyth_engine::prelude::*;
 
+// WASD and the mouse to fly around, the red light circles the monkey and F toggles the flashlight
 fn main() -> EngineResult {
     let (event_loop, mut ctx) = init("lighting", (960, 720))?;
-    ctx.new_program(
-        "lighting",
-        "
-    #version 140
+    ctx.camera.position = Vec3::new(0.0, 0.5, 3.0);
+    ctx.camera.look_at(Vec3::zero());
 
-    in vec3 position;
-    in vec3 normal;
-    in vec2 tex_coords;
+    ctx.lights.ambient = Rgb::broadcast(0.05);
+    ctx.lights.add(
+        Light::directional(Vec3::new(-1.0, -0.4, -0.5), Rgb::new(1.0, 0.95, 0.8))
+            .with_intensity(0.6),
+    );
+    let orbit = ctx
+        .lights
+        .add(Light::point(Vec3::zero(), Rgb::new(1.0, 0.2, 0.1), 6.0).with_intensity(4.0));
+    let flashlight = ctx.lights.add(
+        Light::spot(Vec3::zero(), Vec3::unit_z(), Rgb::white(), 15.0, 0.35).with_intensity(8.0),
+    );
 
-    out vec3 v_position;
-    out vec3 v_normal;
-
-    uniform mat4 persp;
-    uniform mat4 view;
-
-    void main() {
-        v_position = position;
-        v_normal = normal;
-        gl_Position = persp * view * vec4(position, 1.0);
-    }
-    ",
-        "
-    #version 140
-    
-    in vec3 v_position;
-    in vec3 v_normal;
-
-    out vec4 color;
-
-    uniform vec3 u_light;
-
-    const vec3 ambient_color = vec3(0.2, 0.0, 0.0);
-    const vec3 diffuse_color = vec3(0.6, 0.0, 0.0);
-    const vec3 specular_color = vec3(1.0, 1.0, 1.0);
-
-    void main() {
-        float diffuse = max(dot(normalize(v_normal), normalize(u_light)), 0.0);
-
-        vec3 camera_dir = normalize(-v_position);
-        vec3 half_direction = normalize(normalize(u_light) + camera_dir);
-        float specular = pow(max(dot(half_direction, normalize(v_normal)), 0.0), 16.0);
-
-        color = vec4(ambient_color + diffuse * diffuse_color + specular * specular_color, 1.0);
-    }
-    ",
-        None,
-    )
-    .unwrap();
     let game = Game {
-        obj: Obj {
-            model: asset::load_wavefront(&ctx, &std::fs::read("resources/suzanne.obj").unwrap())?,
-            ibo: NoIndices(PrimitiveType::TrianglesList),
-            draw_params: DrawParameters {
-                depth: Depth {
-                    test: DepthTest::IfLess,
-                    write: true,
-                    ..Default::default()
-                },
-                backface_culling: BackfaceCullingMode::CullClockwise,
-                ..Default::default()
-            },
-        },
+        monkey: asset::load_wavefront(&ctx, &std::fs::read("resources/suzanne.obj").unwrap())?,
+        land: asset::load_wavefront(&ctx, &std::fs::read("resources/land.obj").unwrap())?,
+        monkey_material: Material::new(Rgba::new(0.8, 0.5, 0.3, 1.0))
+            .with_specular(Rgb::broadcast(0.8), 64.0),
+        land_material: Material::new(Rgba::new(0.3, 0.5, 0.3, 1.0))
+            .with_specular(Rgb::broadcast(0.1), 8.0)
+            .with_shading(Shading::Phong),
+        orbit,
+        flashlight,
+        flashlight_on: true,
+        time: 0.0,
     };
     run(game, event_loop, ctx)?;
     Ok(())
 }
 
 struct Game {
-    obj: Obj<'static>,
+    monkey: Model,
+    land: Model,
+    monkey_material: Material,
+    land_material: Material,
+    orbit: LightHandle,
+    flashlight: LightHandle,
+    flashlight_on: bool,
+    time: f32,
 }
 
 impl GameState for Game {
     fn update(&mut self, ctx: &mut Context) {
         bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 1.0, 1.0);
+        self.time += ctx.dt;
+
+        if let Some(Light::Point { position, .. }) = ctx.lights.get_mut(self.orbit) {
+            *position = Vec3::new(self.time.cos() * 2.0, 0.5, self.time.sin() * 2.0);
+        }
+        let forward = Vec3::new(
+            ctx.camera.yaw.cos() * ctx.camera.pitch.cos(),
+            ctx.camera.pitch.sin(),
+            ctx.camera.yaw.sin() * ctx.camera.pitch.cos(),
+        );
+        let camera_position = ctx.camera.position;
+        let flashlight_on = self.flashlight_on;
+        if let Some(Light::Spot {
+            position,
+            direction,
+            intensity,
+            ..
+        }) = ctx.lights.get_mut(self.flashlight)
+        {
+            *position = camera_position;
+            *direction = forward;
+            *intensity = if flashlight_on { 8.0 } else { 0.0 };
+        }
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
         renderer
-            .draw(
+            .draw_model(
                 ctx,
-                &self.obj,
-                &uniform! {
-                    persp: ctx.camera.get_perspective(),
-                    view: ctx.camera.get_view(),
-                    u_light: [100.0, 40.0, 50.0f32],
-                },
+                &self.monkey,
+                Mat4::rotation_y(self.time * 0.3),
+                &self.monkey_material,
+            )
+            .unwrap();
+        renderer
+            .draw_model(
+                ctx,
+                &self.land,
+                Mat4::translation_3d(Vec3::new(0.0, -2.0, 0.0)),
+                &self.land_material,
             )
             .unwrap();
     }
-}
-
-struct Obj<'a> {
-    model: Model,
-    ibo: NoIndices,
-    draw_params: DrawParameters<'a>,
-}
-
-impl<'a> Drawable for Obj<'a> {
-    fn get_vbo(&self) -> impl MultiVerticesSource {
-        self.model.get_vbo()
-    }
-    fn get_ibo(&self) -> impl Into<IndicesSource> {
-        &self.ibo
-    }
-    fn get_program(&self) -> String {
-        "lighting".to_string()
-    }
-    fn get_draw_params(&self) -> DrawParameters {
-        self.draw_params.clone()
+    fn event(&mut self, _: &mut Context, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::KeyF),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.flashlight_on = !self.flashlight_on;
+        }
     }
 }
@@ -0,0 +1,17 @@
+use bugsyth_engine::prelude::*;
+use std::time::Duration;
+
+// Renders the first two seconds of a sound to a file without needing an output device
+fn main() -> EngineResult {
+    let mut audio = Audio::new_offline(44_100, 2);
+    let sound = Sound::new("resources/goron.wav")?;
+    audio.play(
+        &sound,
+        &audio_play_value::new_audio_play_value(0.5),
+        &audio_play_value::new_audio_play_value(1.0),
+    )?;
+    let wav = audio.render_to_wav(Duration::from_secs(2))?;
+    std::fs::write("offline_audio.wav", wav)?;
+    println!("Sounds still playing: {}", audio.playing_count());
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use bugsyth_engine::prelude::*;
+use std::rc::Rc;
+
+// WASD and the mouse to fly around. Monkeys get rougher to the right and more metallic further up,
+// lit by the sky and the sun. Pass a .gltf or .glb path to show it in the middle
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("pbr", (960, 720))?;
+    ctx.camera.position = Vec3::new(0.0, 2.0, 9.0);
+    ctx.camera.look_at(Vec3::new(0.0, 2.0, 0.0));
+
+    Skybox::send_program(&mut ctx)?;
+    let skybox = Skybox::new(
+        &ctx,
+        128,
+        [
+            "resources/skybox/right.png",
+            "resources/skybox/left.png",
+            "resources/skybox/top.png",
+            "resources/skybox/bottom.png",
+            "resources/skybox/front.png",
+            "resources/skybox/back.png",
+        ],
+    )?;
+    ctx.lights.environment = Some(Rc::new(Environment::from_skybox(&mut ctx, &skybox)?));
+    ctx.lights.add(
+        Light::directional(Vec3::new(-0.5, -1.0, -0.4), Rgb::new(1.0, 0.95, 0.85))
+            .with_intensity(2.0),
+    );
+
+    let gltf = match std::env::args().nth(1) {
+        Some(path) => Some(asset::load_gltf(&ctx, path)?),
+        None => None,
+    };
+    let game = Game {
+        skybox,
+        monkey: asset::load_wavefront(&ctx, &std::fs::read("resources/suzanne.obj").unwrap())?,
+        gltf,
+        time: 0.0,
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    skybox: Skybox,
+    monkey: Model,
+    gltf: Option<GltfScene>,
+    time: f32,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 3.0, 1.0);
+        self.time += ctx.dt;
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        renderer
+            .draw(
+                ctx,
+                &self.skybox,
+                &uniform! {
+                    persp: ctx.camera.get_perspective(),
+                    view: ctx.camera.get_view(),
+                    u_skybox: self.skybox.get_cubemap(),
+                },
+            )
+            .unwrap();
+
+        let spin = Mat4::rotation_y(self.time * 0.3);
+        if let Some(gltf) = &self.gltf {
+            gltf.draw(
+                ctx,
+                renderer,
+                Mat4::<f32>::translation_3d(Vec3::new(0.0, 2.0, 2.0)) * spin,
+            )
+            .unwrap();
+        }
+        for row in 0..3 {
+            for column in 0..5 {
+                let material = PbrMaterial::new(Rgba::new(0.9, 0.6, 0.3, 1.0))
+                    .with_metallic_roughness(row as f32 / 2.0, (column as f32 + 0.5) / 5.0);
+                let position = Vec3::new(column as f32 * 2.5 - 5.0, row as f32 * 2.0, 0.0);
+                renderer
+                    .draw_pbr(
+                        ctx,
+                        &self.monkey,
+                        Mat4::<f32>::translation_3d(position) * spin,
+                        &material,
+                    )
+                    .unwrap();
+            }
+        }
+    }
+}
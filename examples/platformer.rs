@@ -0,0 +1,169 @@
+use bugsyth_engine::prelude::*;
+use std::rc::Rc;
+
+// A and D to move, space to jump, touch the yellow box to collect it
+// Physics is in meters and the screen is 20 meters wide
+const SCALE: f32 = 0.1;
+const PLAYER_LAYER: u32 = 0b10;
+
+fn main() -> EngineResult {
+    let (event_loop, ctx) = init("Platformer", (960, 960))?;
+    let white = Rc::new(Texture::from_rgba_bytes(&ctx.display, &[255; 4], (1, 1))?);
+    let mut game = Game {
+        physics: PhysicsWorld2d::default(),
+        world: World::new(),
+        bodies: Vec::new(),
+        player: None,
+        coin: None,
+        white,
+    };
+
+    let ground = Rgba::new(0.3, 0.3, 0.3, 1.0);
+    game.add(
+        RigidBody::new_static(Collider::rect(20.0, 1.0), Vec2::new(0.0, -9.5)),
+        Vec2::new(20.0, 1.0),
+        ground,
+    );
+    game.add(
+        RigidBody::new_static(Collider::rect(6.0, 0.5), Vec2::new(-5.0, -4.0)).with_rotation(0.2),
+        Vec2::new(6.0, 0.5),
+        ground,
+    );
+    game.add(
+        RigidBody::new_static(Collider::rect(6.0, 0.5), Vec2::new(5.0, 0.0)),
+        Vec2::new(6.0, 0.5),
+        ground,
+    );
+    for i in 0..6 {
+        game.add(
+            RigidBody::new_dynamic(
+                Collider::rect(1.0, 1.0).with_friction(0.6),
+                Vec2::new(4.0, 1.0 + i as f32 * 1.1),
+            ),
+            Vec2::broadcast(1.0),
+            Rgba::new(0.7, 0.4, 0.2, 1.0),
+        );
+    }
+    game.add(
+        RigidBody::new_dynamic(
+            Collider::circle(0.5).with_restitution(0.6),
+            Vec2::new(-5.0, 5.0),
+        ),
+        Vec2::broadcast(1.0),
+        Rgba::new(0.2, 0.4, 0.8, 1.0),
+    );
+
+    game.player = Some(
+        game.add(
+            RigidBody::new_dynamic(
+                Collider::capsule(1.8, 0.4)
+                    .with_friction(0.0)
+                    .with_layers(PLAYER_LAYER, u32::MAX),
+                Vec2::new(-8.0, -7.0),
+            )
+            .with_fixed_rotation(),
+            Vec2::new(0.8, 1.8),
+            Rgba::new(0.2, 0.8, 0.3, 1.0),
+        ),
+    );
+    game.coin = Some(game.add(
+        RigidBody::new_static(Collider::rect(0.6, 0.6).as_trigger(), Vec2::new(7.0, 1.5)),
+        Vec2::broadcast(0.6),
+        Rgba::new(1.0, 0.9, 0.1, 1.0),
+    ));
+
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    physics: PhysicsWorld2d,
+    world: World,
+    // Which entity shows which body
+    bodies: Vec<(BodyHandle, Entity)>,
+    player: Option<BodyHandle>,
+    coin: Option<BodyHandle>,
+    white: Rc<Texture>,
+}
+
+impl Game {
+    fn add(&mut self, body: RigidBody, size: Vec2<f32>, color: Rgba<f32>) -> BodyHandle {
+        let handle = self.physics.add(body);
+        let entity = self.world.spawn((
+            Transform::default(),
+            Sprite::new(self.white.clone(), size * SCALE).with_color(color),
+        ));
+        self.bodies.push((handle, entity));
+        handle
+    }
+
+    fn is_grounded(&self, player: BodyHandle) -> bool {
+        let Some(body) = self.physics.get(player) else {
+            return false;
+        };
+        // Starts inside the player so its own capsule is skipped by the mask
+        self.physics
+            .raycast(body.position, -Vec2::unit_y(), 1.0, !PLAYER_LAYER)
+            .is_some()
+    }
+}
+
+impl GameState for Game {
+    fn fixed_update(&mut self, ctx: &mut Context) {
+        if let Some(player) = self.player {
+            let grounded = self.is_grounded(player);
+            let input = &ctx.input;
+            if let Some(body) = self.physics.get_mut(player) {
+                let mut walk = 0.0;
+                if input.is_key_pressed(KeyCode::KeyA) {
+                    walk -= 6.0;
+                }
+                if input.is_key_pressed(KeyCode::KeyD) {
+                    walk += 6.0;
+                }
+                body.velocity.x = walk;
+                if grounded && input.is_key_pressed(KeyCode::Space) && body.velocity.y <= 0.1 {
+                    body.velocity.y = 8.0;
+                }
+            }
+        }
+
+        self.physics.fixed_update(ctx);
+
+        for event in self.physics.events().to_vec() {
+            if let CollisionEvent::Started {
+                a,
+                b,
+                trigger: true,
+            } = event
+                && self.coin.is_some_and(|coin| coin == a || coin == b)
+                && self.player.is_some_and(|player| player == a || player == b)
+            {
+                println!("Got the coin");
+                let coin = self.coin.take().unwrap();
+                self.physics.remove(coin);
+                if let Some(index) = self.bodies.iter().position(|(body, _)| *body == coin) {
+                    let (_, entity) = self.bodies.swap_remove(index);
+                    self.world.despawn(entity);
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context) {
+        for (handle, entity) in &self.bodies {
+            if let Some(body) = self.physics.get(*handle)
+                && let Some(transform) = self.world.get_mut::<Transform>(*entity)
+            {
+                transform.position = Vec3::new(body.position.x, body.position.y, 0.0) * SCALE;
+                transform.rotation = Quaternion::rotation_z(body.rotation);
+            }
+        }
+        self.world.update(ctx);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color(0.6, 0.8, 1.0, 1.0);
+        self.world.draw(ctx, renderer).unwrap();
+    }
+}
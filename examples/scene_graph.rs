@@ -0,0 +1,81 @@
+use bugsyth_engine::prelude::*;
+use std::rc::Rc;
+
+// Arrow keys drive the tank, A and D turn the turret, the barrel is attached to the turret
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("Scene Graph", (960, 720))?;
+    ctx.camera.position = Vec3::new(0.0, 4.0, 8.0);
+    ctx.camera.look_at(Vec3::zero());
+
+    let model = Rc::new(asset::load_wavefront(
+        &ctx,
+        &std::fs::read("resources/suzanne.obj").unwrap(),
+    )?);
+    let mut world = World::new();
+    // The hull is its own entity so its scale doesn't squash the turret
+    let tank = world.spawn((Transform::new(Vec3::zero()),));
+    let hull = world.spawn((
+        Transform::new(Vec3::zero()).with_scale(Vec3::new(1.5, 0.5, 2.0)),
+        MeshRenderer::new(model.clone()).with_color(Rgba::new(0.3, 0.5, 0.2, 1.0)),
+    ));
+    let turret = world.spawn((
+        Transform::new(Vec3::new(0.0, 0.6, 0.0)).with_scale(Vec3::broadcast(0.6)),
+        MeshRenderer::new(model.clone()).with_color(Rgba::new(0.4, 0.6, 0.3, 1.0)),
+    ));
+    let barrel = world.spawn((
+        Transform::new(Vec3::new(0.0, 0.0, -1.2)).with_scale(Vec3::new(0.2, 0.2, 1.0)),
+        MeshRenderer::new(model).with_color(Rgba::new(0.2, 0.2, 0.2, 1.0)),
+    ));
+    world.set_parent(hull, tank)?;
+    world.set_parent(turret, tank)?;
+    world.set_parent(barrel, turret)?;
+
+    let game = Game {
+        world,
+        tank,
+        turret,
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    world: World,
+    tank: Entity,
+    turret: Entity,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        let dt = ctx.dt;
+        let input = &ctx.input;
+        if let Some(tank) = self.world.get_mut::<Transform>(self.tank) {
+            if input.is_key_pressed(KeyCode::ArrowLeft) {
+                tank.rotate(Quaternion::rotation_y(dt));
+            }
+            if input.is_key_pressed(KeyCode::ArrowRight) {
+                tank.rotate(Quaternion::rotation_y(-dt));
+            }
+            if input.is_key_pressed(KeyCode::ArrowUp) {
+                tank.position += tank.forward() * 3.0 * dt;
+            }
+            if input.is_key_pressed(KeyCode::ArrowDown) {
+                tank.position -= tank.forward() * 3.0 * dt;
+            }
+        }
+        if let Some(turret) = self.world.get_mut::<Transform>(self.turret) {
+            if input.is_key_pressed(KeyCode::KeyA) {
+                turret.rotate(Quaternion::rotation_y(2.0 * dt));
+            }
+            if input.is_key_pressed(KeyCode::KeyD) {
+                turret.rotate(Quaternion::rotation_y(-2.0 * dt));
+            }
+        }
+        self.world.update(ctx);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.5, 0.7, 0.9, 1.0), 1.0);
+        self.world.draw(ctx, renderer).unwrap();
+    }
+}
@@ -4,6 +4,8 @@ use bugsyth_engine::prelude::*;
 
 fn main() -> EngineResult {
     let (event_loop, mut ctx) = init("scolling texture", (960, 720))?;
+    // Drawn at a low resolution and scaled up, resize the window to see the bars
+    ctx.set_viewport_mode(ViewportMode::PixelPerfect(320, 240))?;
     ctx.new_program(
         "texture",
         "
@@ -46,7 +48,11 @@ fn main() -> EngineResult {
     )
     .unwrap();
     let game = Game {
-        imgui: bugsyth_engine_imgui_support::init(&ctx.window, &ctx.display, |_, _, _| {}),
+        imgui: bugsyth_engine_imgui_support::init(
+            ctx.window.as_ref().unwrap(),
+            ctx.display.get_window_display().unwrap(),
+            |_, _, _| {},
+        ),
         obj: Plane {
             vbo: VertexBuffer::new(
                 &ctx.display,
@@ -115,7 +121,7 @@ impl GameState for Game {
         bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 1.0, 1.0);
         self.time += ctx.dt;
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
         renderer
             .draw(
@@ -135,7 +141,7 @@ impl GameState for Game {
 
         self.imgui
             .platform
-            .prepare_frame(self.imgui.context.io_mut(), &ctx.window)
+            .prepare_frame(self.imgui.context.io_mut(), ctx.window.as_ref().unwrap())
             .unwrap();
         let ui = self.imgui.context.frame();
 
@@ -154,7 +160,9 @@ impl GameState for Game {
                 ui.text(format!("FPS: {}", (1.0 / ctx.dt).floor()));
             });
 
-        self.imgui.platform.prepare_render(&ui, &ctx.window);
+        self.imgui
+            .platform
+            .prepare_render(&ui, ctx.window.as_ref().unwrap());
         let draw_data = self.imgui.context.render();
         self.imgui
             .renderer
@@ -162,7 +170,7 @@ impl GameState for Game {
             .unwrap();
     }
     fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
-        self.imgui.event(&ctx.window, event);
+        self.imgui.event(ctx.window.as_ref().unwrap(), event);
     }
 }
 
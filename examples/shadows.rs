@@ -0,0 +1,93 @@
+use bugsyth_engine::prelude::*;
+use std::{f32::consts::PI, rc::Rc};
+
+// WASD and the mouse to fly around, the sun slowly turns and the spot light sweeps over the monkeys
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("shadows", (960, 720))?;
+    ctx.camera.position = Vec3::new(0.0, 4.0, 10.0);
+    ctx.camera.look_at(Vec3::zero());
+
+    ctx.lights.ambient = Rgb::broadcast(0.08);
+    ctx.lights.shadows.resolution = 2048;
+    let sun = ctx.lights.add(
+        Light::directional(Vec3::new(-0.5, -1.0, -0.3), Rgb::new(1.0, 0.95, 0.85))
+            .with_intensity(0.8),
+    );
+    ctx.lights.set_casts_shadows(sun, true);
+    let spot = ctx.lights.add(
+        Light::spot(
+            Vec3::new(0.0, 6.0, 0.0),
+            -Vec3::unit_y(),
+            Rgb::new(0.4, 0.6, 1.0),
+            20.0,
+            0.5,
+        )
+        .with_intensity(30.0),
+    );
+    ctx.lights.set_casts_shadows(spot, true);
+
+    let mut world = World::new();
+    let land = Rc::new(asset::load_wavefront(
+        &ctx,
+        &std::fs::read("resources/land.obj").unwrap(),
+    )?);
+    world.spawn((
+        Transform::new(Vec3::new(0.0, -1.5, 0.0)).with_scale(Vec3::new(3.0, 1.0, 3.0)),
+        MeshRenderer::new(land).with_material(
+            Material::new(Rgba::new(0.4, 0.6, 0.35, 1.0)).with_specular(Rgb::broadcast(0.05), 4.0),
+        ),
+    ));
+    let monkey = Rc::new(asset::load_wavefront(
+        &ctx,
+        &std::fs::read("resources/suzanne.obj").unwrap(),
+    )?);
+    for i in 0..8 {
+        let angle = i as f32 / 8.0 * PI * 2.0;
+        world.spawn((
+            Transform::new(Vec3::new(angle.cos() * 4.0, 1.0, angle.sin() * 4.0)),
+            MeshRenderer::new(monkey.clone()).with_material(
+                Material::new(Rgba::new(0.9, 0.5, 0.3, 1.0)).with_specular(Rgb::white(), 64.0),
+            ),
+        ));
+    }
+
+    run(
+        Game {
+            world,
+            sun,
+            spot,
+            time: 0.0,
+        },
+        event_loop,
+        ctx,
+    )?;
+    Ok(())
+}
+
+struct Game {
+    world: World,
+    sun: LightHandle,
+    spot: LightHandle,
+    time: f32,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 4.0, 1.0);
+        self.time += ctx.dt;
+        if let Some(Light::Directional { direction, .. }) = ctx.lights.get_mut(self.sun) {
+            let angle = self.time * 0.1;
+            *direction = Vec3::new(angle.cos() * 0.5, -1.0, angle.sin() * 0.5);
+        }
+        if let Some(Light::Spot { direction, .. }) = ctx.lights.get_mut(self.spot) {
+            *direction = Vec3::new(self.time.cos() * 0.5, -1.0, self.time.sin() * 0.5);
+        }
+        self.world.update(ctx);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.5, 0.7, 0.9, 1.0), 1.0);
+        // Draws the shadow maps first since the sun and spot light cast shadows
+        self.world.draw(ctx, renderer).unwrap();
+    }
+}
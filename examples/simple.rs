@@ -69,7 +69,7 @@ struct Game {
 }
 
 impl GameState for Game {
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color(0.0, 0.0, 0.0, 1.0);
         renderer.draw(ctx, &self.tri, &uniform! {}).unwrap();
     }
@@ -75,7 +75,7 @@ impl GameState for Game {
     fn update(&mut self, ctx: &mut Context) {
         bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 1.0, 1.0);
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
         self.skybox
             .prepare_draw(ctx, glium::uniforms::MagnifySamplerFilter::Nearest)
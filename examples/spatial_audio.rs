@@ -0,0 +1,77 @@
+use bugsyth_engine::prelude::*;
+
+// Walk around with the free cam, the sound circles around the start position
+// Hold U to hear it underwater
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("Spatial Audio", (960, 720))?;
+    let game = Game {
+        emitter: Emitter::new(Vec3::new(5.0, 0.0, 0.0)).with_attenuation(Attenuation {
+            rolloff: Rolloff::Inverse,
+            reference_distance: 2.0,
+            max_distance: 50.0,
+            rolloff_factor: 1.0,
+        }),
+        angle: 0.0,
+        underwater: false,
+    };
+    // Stops clipping if lots of sounds end up playing at once
+    ctx.audio
+        .add_effect(MASTER_BUS, effects::Compressor::limiter(-1.0))?;
+    ctx.audio.add_bus("world");
+    let sound = Sound::new("resources/goron.wav")?;
+    ctx.audio.play_with(
+        &sound,
+        &audio_play_value::new_audio_play_value(1.0),
+        &audio_play_value::new_audio_play_value(1.0),
+        PlayOptions {
+            emitter: Some(game.emitter.clone()),
+            bus: Some("world".to_string()),
+            ..Default::default()
+        },
+    )?;
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    emitter: Emitter,
+    angle: f32,
+    underwater: bool,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 5.0, 1.0);
+
+        let radius = 5.0;
+        let angular_speed = 1.0;
+        self.angle += angular_speed * ctx.dt;
+        self.emitter.set_position(Vec3::new(
+            self.angle.cos() * radius,
+            0.0,
+            self.angle.sin() * radius,
+        ));
+        self.emitter.set_velocity(Vec3::new(
+            -self.angle.sin() * radius * angular_speed,
+            0.0,
+            self.angle.cos() * radius * angular_speed,
+        ));
+
+        let underwater = ctx.input.is_key_pressed(KeyCode::KeyU);
+        if underwater != self.underwater {
+            self.underwater = underwater;
+            ctx.audio.clear_effects("world").unwrap();
+            if underwater {
+                ctx.audio
+                    .add_effect("world", effects::Biquad::low_pass(600.0, 0.707))
+                    .unwrap();
+                ctx.audio
+                    .add_effect("world", effects::Reverb::new(0.8, 0.5, 0.3))
+                    .unwrap();
+            }
+        }
+    }
+    fn draw(&mut self, _: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color(0.0, 0.0, 0.0, 1.0);
+    }
+}
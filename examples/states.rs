@@ -0,0 +1,128 @@
+use bugsyth_engine::glium::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::PhysicalKey,
+};
+use bugsyth_engine::prelude::*;
+use std::fs;
+
+// Enter starts the game from the menu, Escape pauses, Backspace goes back
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("States", (960, 720))?;
+    // Escape is used for pausing instead
+    ctx.quit_on_escape = false;
+    ctx.add_font("dogica", &fs::read("resources/dogica.ttf")?, 72.0)?;
+    let menu = Menu {
+        text: Text::new(
+            &ctx,
+            Vec2::new(-0.4, 0.1),
+            0.001,
+            None,
+            "Press Enter",
+            "dogica",
+        )?,
+        key: None,
+    };
+    run(
+        StateStack::new(menu).with_fade(0.5, (0.0, 0.0, 0.0)),
+        event_loop,
+        ctx,
+    )?;
+    Ok(())
+}
+
+fn pressed_key(event: &WindowEvent) -> Option<KeyCode> {
+    match event {
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(key_code),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => Some(*key_code),
+        _ => None,
+    }
+}
+
+struct Menu {
+    text: Text,
+    key: Option<KeyCode>,
+}
+
+impl State for Menu {
+    fn update(&mut self, _: &mut Context) -> Transition {
+        match self.key.take() {
+            Some(KeyCode::Enter) => Transition::Push(Box::new(Play {
+                time: 0.0,
+                key: None,
+            })),
+            Some(KeyCode::Backspace) => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut DynRenderer, _: f32) {
+        renderer.clear_color(0.1, 0.1, 0.3, 1.0);
+        self.text.draw(ctx, renderer).unwrap();
+    }
+    fn on_scale_factor_changed(&mut self, ctx: &mut Context, _: f64) {
+        self.text.rebuild(ctx).unwrap();
+    }
+    fn event(&mut self, _: &mut Context, event: &WindowEvent) {
+        self.key = pressed_key(event).or(self.key);
+    }
+}
+
+struct Play {
+    time: f32,
+    key: Option<KeyCode>,
+}
+
+impl State for Play {
+    fn update(&mut self, ctx: &mut Context) -> Transition {
+        self.time += ctx.dt;
+        match self.key.take() {
+            Some(KeyCode::Escape) => Transition::Push(Box::new(Pause {
+                text: Text::new(ctx, Vec2::new(-0.3, 0.1), 0.001, None, "Paused", "dogica")
+                    .unwrap(),
+                key: None,
+            })),
+            Some(KeyCode::Backspace) => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+    fn draw(&mut self, _: &mut Context, renderer: &mut DynRenderer, _: f32) {
+        renderer.clear_color(0.5 + self.time.sin() * 0.5, 0.3, 0.2, 1.0);
+    }
+    fn event(&mut self, _: &mut Context, event: &WindowEvent) {
+        self.key = pressed_key(event).or(self.key);
+    }
+}
+
+/// Drawn on top of `Play`, which stops updating while paused
+struct Pause {
+    text: Text,
+    key: Option<KeyCode>,
+}
+
+impl State for Pause {
+    fn update(&mut self, _: &mut Context) -> Transition {
+        match self.key.take() {
+            Some(KeyCode::Escape) => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut DynRenderer, _: f32) {
+        self.text.draw(ctx, renderer).unwrap();
+    }
+    fn on_scale_factor_changed(&mut self, ctx: &mut Context, _: f64) {
+        self.text.rebuild(ctx).unwrap();
+    }
+    fn event(&mut self, _: &mut Context, event: &WindowEvent) {
+        self.key = pressed_key(event).or(self.key);
+    }
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
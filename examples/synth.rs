@@ -0,0 +1,55 @@
+use bugsyth_engine::prelude::*;
+
+// Press 1-7 to generate a new sound effect, the same seed always gives the same sounds
+fn main() -> EngineResult {
+    let (event_loop, ctx) = init("Synth", (960, 720))?;
+    let game = Game {
+        rng: rng::Rng::with_seed(1234),
+        last_keys: Vec::new(),
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+type Preset = fn(&mut rng::Rng) -> SynthParams;
+
+struct Game {
+    rng: rng::Rng,
+    last_keys: Vec<KeyCode>,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        let presets: [(KeyCode, Preset); 7] = [
+            (KeyCode::Digit1, SynthParams::pickup),
+            (KeyCode::Digit2, SynthParams::laser),
+            (KeyCode::Digit3, SynthParams::explosion),
+            (KeyCode::Digit4, SynthParams::powerup),
+            (KeyCode::Digit5, SynthParams::hit),
+            (KeyCode::Digit6, SynthParams::jump),
+            (KeyCode::Digit7, SynthParams::blip),
+        ];
+        let mut pressed = Vec::new();
+        for (key, preset) in presets {
+            if !ctx.input.is_key_pressed(key) {
+                continue;
+            }
+            pressed.push(key);
+            if self.last_keys.contains(&key) {
+                continue;
+            }
+            let sound = preset(&mut self.rng).generate(&mut self.rng);
+            ctx.audio
+                .play(
+                    &sound,
+                    &audio_play_value::new_audio_play_value(1.0),
+                    &audio_play_value::new_audio_play_value(1.0),
+                )
+                .unwrap();
+        }
+        self.last_keys = pressed;
+    }
+    fn draw(&mut self, _: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color(0.0, 0.0, 0.0, 1.0);
+    }
+}
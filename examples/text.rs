@@ -29,7 +29,7 @@ struct Game {
 
 impl GameState for Game {
     fn update(&mut self, _ctx: &mut Context) {}
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color(0.0, 0.0, 0.0, 1.0);
         self.text.draw(ctx, renderer).unwrap();
 
@@ -37,7 +37,11 @@ fn main() -> EngineResult {
         None,
     )
     .unwrap();
-    let imgui = bugsyth_engine_imgui_support::init(&ctx.window, &ctx.display, |_, _, _| {});
+    let imgui = bugsyth_engine_imgui_support::init(
+        ctx.window.as_ref().unwrap(),
+        ctx.display.get_window_display().unwrap(),
+        |_, _, _| {},
+    );
 
     let tri_positions = [[-0.5, -0.5, 0.0], [0.5, 0.5, 0.0], [-0.5, 0.5, 0.0]];
     let tri_colors = [
@@ -128,11 +132,11 @@ impl GameState for Game {
         self.imgui.update_dt(ctx.dt);
         bugsyth_engine::context::camera::CameraState::free_cam(ctx.dt, ctx, 1.0, 1.0);
     }
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
         renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
         self.imgui
             .platform
-            .prepare_frame(self.imgui.context.io_mut(), &ctx.window)
+            .prepare_frame(self.imgui.context.io_mut(), ctx.window.as_ref().unwrap())
             .unwrap();
         let ui = self.imgui.context.frame();
 
@@ -171,33 +175,32 @@ impl GameState for Game {
         )
         .unwrap();
 
-        /*
-            Must draw the opaque objects first, then
-            draw the transparent objects in order of
-            farthest to closest to the view
-        */
-        renderer
-            .draw(
-                ctx,
-                &self.tri,
-                &uniform! {
-                    persp: ctx.camera.get_perspective(),
-                    view: ctx.camera.get_view(),
-                },
-            )
-            .unwrap();
-        renderer
-            .draw(
-                ctx,
-                &self.tansparent_tri,
-                &uniform! {
-                    persp: ctx.camera.get_perspective(),
-                    view: ctx.camera.get_view(),
-                },
-            )
-            .unwrap();
+        // Queued in the wrong order on purpose, the queue draws the opaque triangle first
+        // and the transparent ones from farthest to closest to the view
+        let mut queue = RenderQueue::new();
+        queue.push(
+            RenderLayer::Transparent,
+            centroid(&self.tansparent_tri.positions),
+            &self.tansparent_tri,
+            uniform! {
+                persp: ctx.camera.get_perspective(),
+                view: ctx.camera.get_view(),
+            },
+        );
+        queue.push(
+            RenderLayer::Opaque,
+            centroid(&self.tri.positions),
+            &self.tri,
+            uniform! {
+                persp: ctx.camera.get_perspective(),
+                view: ctx.camera.get_view(),
+            },
+        );
+        queue.flush(ctx, renderer).unwrap();
 
-        self.imgui.platform.prepare_render(&ui, &ctx.window);
+        self.imgui
+            .platform
+            .prepare_render(&ui, ctx.window.as_ref().unwrap());
         let draw_data = self.imgui.context.render();
         self.imgui
             .renderer
@@ -205,10 +208,14 @@ impl GameState for Game {
             .unwrap();
     }
     fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
-        self.imgui.event(&ctx.window, event);
+        self.imgui.event(ctx.window.as_ref().unwrap(), event);
     }
 }
 
+fn centroid(positions: &[[f32; 3]; 3]) -> Vec3<f32> {
+    (Vec3::from(positions[0]) + Vec3::from(positions[1]) + Vec3::from(positions[2])) / 3.0
+}
+
 struct Triangle<'a> {
     vbo: VertexBuffer<Vertex>,
     positions: [[f32; 3]; 3],
@@ -0,0 +1,123 @@
+use bugsyth_engine::prelude::*;
+
+// WASD to walk, space to jump, mouse or arrow keys to look around, hold E to free the mouse
+// land.obj is scaled up so the hills are big enough to walk on
+const LAND_SCALE: f32 = 10.0;
+
+fn main() -> EngineResult {
+    let (event_loop, mut ctx) = init("walk", (960, 720))?;
+    ctx.new_program(
+        "land",
+        "
+    in vec3 position;
+    in vec3 normal;
+    in vec2 tex_coords;
+
+    out vec3 v_normal;
+
+    uniform mat4 persp;
+    uniform mat4 view;
+    uniform mat4 model;
+
+    void main() {
+        v_normal = normal;
+        gl_Position = persp * view * model * vec4(position, 1.0);
+    }
+    ",
+        "
+    in vec3 v_normal;
+
+    out vec4 color;
+
+    void main() {
+        float light = max(dot(normalize(v_normal), normalize(vec3(0.3, 1.0, 0.2))), 0.2);
+        color = vec4(vec3(0.3, 0.6, 0.25) * light, 1.0);
+    }
+    ",
+        None,
+    )
+    .unwrap();
+
+    let model = asset::load_wavefront(&ctx, &std::fs::read("resources/land.obj").unwrap())?;
+    let transform = Mat4::scaling_3d(LAND_SCALE);
+    let mut collision = CollisionWorld3d::new();
+    collision.add(Collider3d::Mesh(TriangleMesh::from_model(
+        &model, transform,
+    )));
+
+    // Dropped in from above the highest hill
+    let player = CharacterController::new(Vec3::new(0.0, 20.0, 0.0));
+    ctx.camera.position = player.eye_position();
+
+    let game = Game {
+        land: Land {
+            model,
+            transform,
+            ibo: NoIndices(PrimitiveType::TrianglesList),
+            draw_params: DrawParameters {
+                depth: Depth {
+                    test: DepthTest::IfLess,
+                    write: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        },
+        collision,
+        player,
+    };
+    run(game, event_loop, ctx)?;
+    Ok(())
+}
+
+struct Game {
+    land: Land<'static>,
+    collision: CollisionWorld3d,
+    player: CharacterController,
+}
+
+impl GameState for Game {
+    fn update(&mut self, ctx: &mut Context) {
+        self.player.first_person(ctx, &self.collision, 4.0, 1.0);
+        // Fell off the edge
+        if self.player.position.y < -20.0 {
+            self.player = CharacterController::new(Vec3::new(0.0, 20.0, 0.0));
+        }
+    }
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, _: f32) {
+        renderer.clear_color_and_depth((0.6, 0.8, 1.0, 1.0), 1.0);
+        renderer
+            .draw(
+                ctx,
+                &self.land,
+                &uniform! {
+                    persp: ctx.camera.get_perspective(),
+                    view: ctx.camera.get_view(),
+                    model: self.land.transform.into_col_arrays(),
+                },
+            )
+            .unwrap();
+    }
+}
+
+struct Land<'a> {
+    model: Model,
+    transform: Mat4<f32>,
+    ibo: NoIndices,
+    draw_params: DrawParameters<'a>,
+}
+
+impl<'a> Drawable for Land<'a> {
+    fn get_vbo(&self) -> impl MultiVerticesSource {
+        self.model.get_vbo()
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource> {
+        &self.ibo
+    }
+    fn get_program(&self) -> String {
+        "land".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters {
+        self.draw_params.clone()
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    asset::model::Model,
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::{Renderer, pbr::PbrMaterial, shadow::ShadowCaster, texture::Texture},
+};
+use glium::{VertexBuffer, implement_vertex};
+use std::{path::Path, rc::Rc};
+use vek::{Mat4, Rgb, Rgba, Vec3};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    tex_coords: [f32; 2],
+}
+implement_vertex!(Vertex, position, normal, tex_coords);
+
+/// One primitive of a mesh in a glTF file, placed where its node is
+pub struct GltfMesh {
+    pub name: Option<String>,
+    /// Shared between every node that uses the same mesh
+    pub model: Rc<Model>,
+    /// Index into `GltfScene::materials`, `None` uses `PbrMaterial::default`
+    pub material: Option<usize>,
+    /// Where the node is in the file's scene, with its parents' transforms applied
+    pub transform: Mat4<f32>,
+}
+
+/// Everything in a glTF file's scene, drawn with `Renderer::draw_pbr`
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<PbrMaterial>,
+    default_material: PbrMaterial,
+}
+
+impl GltfScene {
+    pub fn get_material(&self, mesh: &GltfMesh) -> &PbrMaterial {
+        mesh.material
+            .and_then(|index| self.materials.get(index))
+            .unwrap_or(&self.default_material)
+    }
+
+    /// Draws every mesh, `transform` moves the whole scene
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        renderer: &mut impl Renderer,
+        transform: Mat4<f32>,
+    ) -> EngineResult {
+        for mesh in &self.meshes {
+            renderer.draw_pbr(
+                ctx,
+                &mesh.model,
+                transform * mesh.transform,
+                self.get_material(mesh),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draws every mesh into a shadow map, call it from the closure passed to `draw_shadows`
+    pub fn draw_shadows(
+        &self,
+        caster: &mut ShadowCaster<'_, '_>,
+        transform: Mat4<f32>,
+    ) -> EngineResult {
+        for mesh in &self.meshes {
+            caster.draw_model(&mesh.model, transform * mesh.transform)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loads a .gltf or .glb file with its buffers and images, the default scene is used or else the first one.
+/// Only triangles and the first set of texture coordinates are loaded, normals are made flat when missing.
+/// The vertices are the same as `load_wavefront`'s
+pub fn load_gltf(ctx: &Context, path: impl AsRef<Path>) -> EngineResult<GltfScene> {
+    let (document, buffers, images) = gltf::import(path)?;
+
+    let images = images
+        .iter()
+        .map(|image| {
+            Texture::from_rgba_bytes(&ctx.display, &to_rgba(image)?, (image.width, image.height))
+                .map(Rc::new)
+        })
+        .collect::<EngineResult<Vec<_>>>()?;
+    let texture = |texture: gltf::Texture| images[texture.source().index()].clone();
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            PbrMaterial {
+                base_color: Rgba::from(pbr.base_color_factor()),
+                base_color_texture: pbr.base_color_texture().map(|info| texture(info.texture())),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                metallic_roughness_texture: pbr
+                    .metallic_roughness_texture()
+                    .map(|info| texture(info.texture())),
+                normal_texture: material
+                    .normal_texture()
+                    .map(|normal| texture(normal.texture())),
+                normal_scale: material
+                    .normal_texture()
+                    .map_or(1.0, |normal| normal.scale()),
+                occlusion_texture: material
+                    .occlusion_texture()
+                    .map(|occlusion| texture(occlusion.texture())),
+                occlusion_strength: material
+                    .occlusion_texture()
+                    .map_or(1.0, |occlusion| occlusion.strength()),
+                emissive: Rgb::from(material.emissive_factor()),
+                emissive_texture: material
+                    .emissive_texture()
+                    .map(|info| texture(info.texture())),
+                double_sided: material.double_sided(),
+            }
+        })
+        .collect();
+
+    let mut models = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader =
+                primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| {
+                    EngineError::GltfError(format!(
+                        "A primitive in mesh {} has no positions",
+                        mesh.index()
+                    ))
+                })?
+                .collect();
+            let normals: Option<Vec<[f32; 3]>> =
+                reader.read_normals().map(|normals| normals.collect());
+            let tex_coords: Option<Vec<[f32; 2]>> = reader
+                .read_tex_coords(0)
+                .map(|tex_coords| tex_coords.into_f32().collect());
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let mut vertex_data = Vec::with_capacity(indices.len());
+            let mut triangles = Vec::with_capacity(indices.len() / 3);
+            for triangle in indices.chunks_exact(3) {
+                let corners = [0, 1, 2].map(|i| Vec3::from(positions[triangle[i] as usize]));
+                triangles.push(corners);
+                let flat_normal = (corners[1] - corners[0])
+                    .cross(corners[2] - corners[0])
+                    .normalized();
+                for index in triangle.iter().map(|index| *index as usize) {
+                    let normal = normals
+                        .as_ref()
+                        .map_or(flat_normal.into_array(), |normals| normals[index]);
+                    // glTF's v goes down from the top, textures here are loaded the other way up
+                    let [u, v] = tex_coords
+                        .as_ref()
+                        .map_or([0.0, 0.0], |tex_coords| tex_coords[index]);
+                    vertex_data.push(Vertex {
+                        position: positions[index],
+                        normal,
+                        tex_coords: [u, 1.0 - v],
+                    });
+                }
+            }
+            primitives.push((
+                Rc::new(Model::new(
+                    VertexBuffer::new(&ctx.display, &vertex_data)?.into(),
+                    triangles,
+                )),
+                primitive.material().index(),
+            ));
+        }
+        models.push((mesh.name().map(String::from), primitives));
+    }
+
+    let mut meshes = Vec::new();
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            add_node(&node, Mat4::identity(), &models, &mut meshes);
+        }
+    }
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        default_material: PbrMaterial::default(),
+    })
+}
+
+type LoadedMesh = (Option<String>, Vec<(Rc<Model>, Option<usize>)>);
+
+fn add_node(
+    node: &gltf::Node,
+    parent: Mat4<f32>,
+    models: &[LoadedMesh],
+    meshes: &mut Vec<GltfMesh>,
+) {
+    let transform = parent * Mat4::from_col_arrays(node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        let (name, primitives) = &models[mesh.index()];
+        for (model, material) in primitives {
+            meshes.push(GltfMesh {
+                name: name.clone(),
+                model: model.clone(),
+                material: *material,
+                transform,
+            });
+        }
+    }
+    for child in node.children() {
+        add_node(&child, transform, models, meshes);
+    }
+}
+
+/// 16 bit channels are cut down to 8, float images aren't supported
+fn to_rgba(image: &gltf::image::Data) -> EngineResult<Vec<u8>> {
+    use gltf::image::Format;
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        format => {
+            return Err(EngineError::GltfError(format!(
+                "Unsupported glTF image format: {:?}",
+                format
+            )));
+        }
+    };
+    let mut rgba = Vec::with_capacity(image.width as usize * image.height as usize * 4);
+    for pixel in image.pixels.chunks_exact(channels * bytes_per_channel) {
+        // The high byte of each little endian channel
+        let channel = |i: usize| pixel[i * bytes_per_channel + bytes_per_channel - 1];
+        let pixel = match channels {
+            1 => [channel(0), channel(0), channel(0), 255],
+            2 => [channel(0), channel(1), 0, 255],
+            3 => [channel(0), channel(1), channel(2), 255],
+            _ => [channel(0), channel(1), channel(2), channel(3)],
+        };
+        rgba.extend_from_slice(&pixel);
+    }
+    Ok(rgba)
+}
@@ -1,5 +1,6 @@
 use crate::{asset::model::Model, context::Context, error::EngineResult};
 use glium::{VertexBuffer, implement_vertex};
+use vek::Vec3;
 
 /// Vertex structure:
 /// struct Vertex {
@@ -22,11 +23,17 @@ pub fn load_wavefront(ctx: &Context, data: &[u8]) -> EngineResult<Model> {
     let data = obj::ObjData::load_buf(&mut data)?;
 
     let mut vertex_data = Vec::new();
+    let mut triangles = Vec::new();
 
     for object in data.objects.iter() {
         for polygon in object.groups.iter().flat_map(|g| g.polys.iter()) {
             match polygon {
                 obj::SimplePolygon(indices) => {
+                    // Fanned out so faces with more than 3 sides still collide
+                    let corner = |i: usize| Vec3::from(data.position[indices[i].0]);
+                    for i in 2..indices.len() {
+                        triangles.push([corner(0), corner(i - 1), corner(i)]);
+                    }
                     for v in indices.iter() {
                         let position = data.position[v.0];
                         let texture = v.1.map(|index| data.texture[index]);
@@ -48,5 +55,6 @@ pub fn load_wavefront(ctx: &Context, data: &[u8]) -> EngineResult<Model> {
 
     Ok(Model::new(
         VertexBuffer::new(&ctx.display, &vertex_data)?.into(),
+        triangles,
     ))
 }
@@ -1,17 +1,23 @@
 use glium::vertex::VertexBufferAny;
+use vek::Vec3;
 
 #[derive(Debug)]
 pub struct Model {
     vbo: VertexBufferAny,
+    triangles: Vec<[Vec3<f32>; 3]>,
 }
 
 impl Model {
-    pub(crate) fn new(vbo: VertexBufferAny) -> Self
+    pub(crate) fn new(vbo: VertexBufferAny, triangles: Vec<[Vec3<f32>; 3]>) -> Self
 where {
-        Self { vbo }
+        Self { vbo, triangles }
     }
 
     pub fn get_vbo(&self) -> &VertexBufferAny {
         &self.vbo
     }
+    /// Positions kept on the CPU, used to build a `TriangleMesh` for collisions
+    pub fn get_triangles(&self) -> &[[Vec3<f32>; 3]] {
+        &self.triangles
+    }
 }
@@ -0,0 +1,276 @@
+use geometry::Primitive;
+use mesh::TriangleMesh;
+use vek::{Aabb, Vec3};
+
+pub mod controller;
+mod geometry;
+pub mod mesh;
+
+/// Id of a collider in a `CollisionWorld3d`, old ids never point at new colliders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColliderHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// Segment grown by a radius, `start` and `end` are the middles of the round ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capsule {
+    pub start: Vec3<f32>,
+    pub end: Vec3<f32>,
+    pub radius: f32,
+}
+
+impl Capsule {
+    pub fn new(start: Vec3<f32>, end: Vec3<f32>, radius: f32) -> Self {
+        Self { start, end, radius }
+    }
+    /// Standing up with the bottom of it at `feet`, `height` is from the bottom to the top
+    pub fn upright(feet: Vec3<f32>, height: f32, radius: f32) -> Self {
+        let half = (height / 2.0 - radius).max(0.0);
+        let center = feet + Vec3::unit_y() * height / 2.0;
+        Self {
+            start: center - Vec3::unit_y() * half,
+            end: center + Vec3::unit_y() * half,
+            radius,
+        }
+    }
+    pub fn translated(self, offset: Vec3<f32>) -> Self {
+        Self {
+            start: self.start + offset,
+            end: self.end + offset,
+            radius: self.radius,
+        }
+    }
+    pub fn aabb(&self) -> Aabb<f32> {
+        Primitive::Segment(self.start, self.end, self.radius).aabb()
+    }
+}
+
+/// Something to collide with, everything is in world space and doesn't move on its own
+#[derive(Debug, Clone)]
+pub enum Collider3d {
+    Aabb(Aabb<f32>),
+    Sphere { center: Vec3<f32>, radius: f32 },
+    Capsule(Capsule),
+    Mesh(TriangleMesh),
+}
+
+impl Collider3d {
+    pub fn aabb(&self) -> Option<Aabb<f32>> {
+        match self {
+            Self::Mesh(mesh) => mesh.aabb(),
+            _ => self.primitive().map(|primitive| primitive.aabb()),
+        }
+    }
+
+    /// `None` for meshes, they're made of many
+    fn primitive(&self) -> Option<Primitive> {
+        match self {
+            Self::Aabb(aabb) => Some(Primitive::Box(*aabb)),
+            Self::Sphere { center, radius } => Some(Primitive::Point(*center, *radius)),
+            Self::Capsule(capsule) => Some(Primitive::Segment(
+                capsule.start,
+                capsule.end,
+                capsule.radius,
+            )),
+            Self::Mesh(_) => None,
+        }
+    }
+
+    /// Runs `f` on every piece of the collider that could touch `aabb`
+    fn for_each_near(&self, aabb: &Aabb<f32>, mut f: impl FnMut(Primitive)) {
+        match self {
+            Self::Mesh(mesh) => mesh.for_each_near(aabb, f),
+            _ => {
+                if let Some(primitive) = self.primitive()
+                    && geometry::overlaps(&primitive.aabb(), aabb)
+                {
+                    f(primitive);
+                }
+            }
+        }
+    }
+}
+
+/// Where a ray hit a collider
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit3d {
+    pub collider: ColliderHandle,
+    pub point: Vec3<f32>,
+    pub normal: Vec3<f32>,
+    pub distance: f32,
+}
+
+/// Where a moving capsule or sphere first touched a collider
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepHit {
+    pub collider: ColliderHandle,
+    /// 0.0 to 1.0, how much of the movement happened before the hit
+    pub fraction: f32,
+    /// Points away from the collider, toward the capsule
+    pub normal: Vec3<f32>,
+    pub point: Vec3<f32>,
+}
+
+/// How far a capsule is stuck in a collider, move it by `normal * depth` to get it out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Penetration {
+    pub collider: ColliderHandle,
+    pub normal: Vec3<f32>,
+    pub depth: f32,
+}
+
+/// Static colliders for raycasts, sweeps and the `CharacterController`
+#[derive(Debug, Default)]
+pub struct CollisionWorld3d {
+    colliders: Vec<Option<Collider3d>>,
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
+impl CollisionWorld3d {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, collider: Collider3d) -> ColliderHandle {
+        match self.free.pop() {
+            Some(index) => {
+                self.colliders[index as usize] = Some(collider);
+                ColliderHandle {
+                    index,
+                    generation: self.generations[index as usize],
+                }
+            }
+            None => {
+                self.colliders.push(Some(collider));
+                self.generations.push(0);
+                ColliderHandle {
+                    index: self.colliders.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, handle: ColliderHandle) -> Option<Collider3d> {
+        self.get(handle)?;
+        let index = handle.index as usize;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(handle.index);
+        self.colliders[index].take()
+    }
+
+    pub fn get(&self, handle: ColliderHandle) -> Option<&Collider3d> {
+        if *self.generations.get(handle.index as usize)? != handle.generation {
+            return None;
+        }
+        self.colliders[handle.index as usize].as_ref()
+    }
+    pub fn get_mut(&mut self, handle: ColliderHandle) -> Option<&mut Collider3d> {
+        if *self.generations.get(handle.index as usize)? != handle.generation {
+            return None;
+        }
+        self.colliders[handle.index as usize].as_mut()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ColliderHandle, &Collider3d)> {
+        self.colliders
+            .iter()
+            .enumerate()
+            .filter_map(|(index, collider)| Some((self.handle(index), collider.as_ref()?)))
+    }
+
+    /// Closest collider along the ray
+    pub fn raycast(
+        &self,
+        origin: Vec3<f32>,
+        direction: Vec3<f32>,
+        max_distance: f32,
+    ) -> Option<RayHit3d> {
+        if direction.magnitude_squared() == 0.0 {
+            return None;
+        }
+        let direction = direction.normalized();
+        self.iter()
+            .filter_map(|(handle, collider)| {
+                let (distance, normal) = match collider {
+                    Collider3d::Mesh(mesh) => mesh.raycast(origin, direction, max_distance),
+                    _ => collider
+                        .primitive()?
+                        .raycast(origin, direction, max_distance),
+                }?;
+                Some(RayHit3d {
+                    collider: handle,
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// First thing the capsule hits while moving by `displacement`.
+    /// Colliders it's already in are skipped if it's moving out of them
+    pub fn sweep_capsule(&self, capsule: Capsule, displacement: Vec3<f32>) -> Option<SweepHit> {
+        let start = capsule.aabb();
+        let end = capsule.translated(displacement).aabb();
+        let swept = geometry::union(&start, &end);
+
+        let mut best: Option<SweepHit> = None;
+        for (handle, collider) in self.iter() {
+            collider.for_each_near(&swept, |primitive| {
+                let Some((fraction, normal, point)) =
+                    primitive.sweep(capsule.start, capsule.end, capsule.radius, displacement)
+                else {
+                    return;
+                };
+                if best.is_none_or(|best| fraction < best.fraction) {
+                    best = Some(SweepHit {
+                        collider: handle,
+                        fraction,
+                        normal,
+                        point,
+                    });
+                }
+            });
+        }
+        best
+    }
+    pub fn sweep_sphere(
+        &self,
+        center: Vec3<f32>,
+        radius: f32,
+        displacement: Vec3<f32>,
+    ) -> Option<SweepHit> {
+        self.sweep_capsule(Capsule::new(center, center, radius), displacement)
+    }
+
+    /// Every collider the capsule is inside of, one for each triangle of a mesh
+    pub fn penetrations(&self, capsule: Capsule) -> Vec<Penetration> {
+        let aabb = capsule.aabb();
+        let mut penetrations = Vec::new();
+        for (handle, collider) in self.iter() {
+            collider.for_each_near(&aabb, |primitive| {
+                if let Some((normal, depth)) =
+                    primitive.penetration(capsule.start, capsule.end, capsule.radius)
+                {
+                    penetrations.push(Penetration {
+                        collider: handle,
+                        normal,
+                        depth,
+                    });
+                }
+            });
+        }
+        penetrations
+    }
+
+    fn handle(&self, index: usize) -> ColliderHandle {
+        ColliderHandle {
+            index: index as u32,
+            generation: self.generations[index],
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    collision3d::{Capsule, CollisionWorld3d},
+    context::{Context, camera::CameraState},
+};
+use glium::winit::keyboard::KeyCode;
+use std::f32::consts::PI;
+use vek::Vec3;
+
+// Gap kept between the capsule and whatever it's touching so the next sweep doesn't start inside
+const SKIN: f32 = 0.01;
+const MAX_SLIDES: usize = 4;
+const MAX_DEPENETRATIONS: usize = 4;
+
+/// Upright capsule that walks on a `CollisionWorld3d` without being pushed around by physics.
+/// Walks up slopes less steep than `max_slope`, climbs ledges up to `step_height` and falls with gravity
+#[derive(Debug, Clone)]
+pub struct CharacterController {
+    /// Bottom of the capsule
+    pub position: Vec3<f32>,
+    pub velocity: Vec3<f32>,
+    pub height: f32,
+    pub radius: f32,
+    /// How far above `position` the camera goes
+    pub eye_height: f32,
+    pub step_height: f32,
+    /// Steepest slope that can be walked on, in radians
+    pub max_slope: f32,
+    pub gravity: f32,
+    pub jump_speed: f32,
+    grounded: bool,
+    ground_normal: Vec3<f32>,
+}
+
+impl CharacterController {
+    /// About the size of a person, in meters
+    pub fn new(position: Vec3<f32>) -> Self {
+        Self {
+            position,
+            velocity: Vec3::zero(),
+            height: 1.8,
+            radius: 0.35,
+            eye_height: 1.65,
+            step_height: 0.35,
+            max_slope: PI / 4.0,
+            gravity: 9.81,
+            jump_speed: 5.0,
+            grounded: false,
+            ground_normal: Vec3::unit_y(),
+        }
+    }
+
+    pub fn with_size(mut self, height: f32, radius: f32) -> Self {
+        self.height = height;
+        self.radius = radius;
+        self
+    }
+    pub fn with_eye_height(mut self, eye_height: f32) -> Self {
+        self.eye_height = eye_height;
+        self
+    }
+    pub fn with_step_height(mut self, step_height: f32) -> Self {
+        self.step_height = step_height;
+        self
+    }
+    pub fn with_max_slope(mut self, max_slope: f32) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+    pub fn with_jump_speed(mut self, jump_speed: f32) -> Self {
+        self.jump_speed = jump_speed;
+        self
+    }
+
+    pub fn capsule(&self) -> Capsule {
+        Capsule::upright(self.position, self.height, self.radius)
+    }
+    pub fn eye_position(&self) -> Vec3<f32> {
+        self.position + Vec3::unit_y() * self.eye_height
+    }
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+    /// Normal of what it's standing on, straight up if it's in the air
+    pub fn ground_normal(&self) -> Vec3<f32> {
+        self.ground_normal
+    }
+
+    /// Does nothing in the air
+    pub fn jump(&mut self) {
+        if self.grounded {
+            self.velocity.y = self.jump_speed;
+            self.grounded = false;
+        }
+    }
+
+    /// Walks with `walk_velocity`, only the x and z are used, and falls with gravity
+    pub fn move_and_slide(&mut self, world: &CollisionWorld3d, walk_velocity: Vec3<f32>, dt: f32) {
+        self.depenetrate(world);
+
+        if self.grounded && self.velocity.y <= 0.0 {
+            self.velocity.y = 0.0;
+        } else {
+            self.velocity.y -= self.gravity * dt;
+        }
+        self.velocity.x = walk_velocity.x;
+        self.velocity.z = walk_velocity.z;
+
+        let walk = Vec3::new(walk_velocity.x, 0.0, walk_velocity.z) * dt;
+        if walk.magnitude_squared() > 0.0 {
+            self.walk(world, walk);
+        }
+
+        let was_grounded = self.grounded;
+        self.grounded = false;
+        self.ground_normal = Vec3::unit_y();
+        let fall = Vec3::unit_y() * self.velocity.y * dt;
+        if fall.y != 0.0 {
+            for normal in self.slide(world, fall, false) {
+                if fall.y < 0.0 && self.is_walkable(normal) {
+                    self.grounded = true;
+                    self.ground_normal = normal;
+                } else if fall.y > 0.0 && normal.y < 0.0 {
+                    // Hit the ceiling
+                    self.velocity.y = 0.0;
+                }
+            }
+        }
+
+        // Keeps it on the ground walking down slopes and steps instead of flying off them
+        if was_grounded && !self.grounded && self.velocity.y <= 0.0 {
+            self.snap_to_ground(world, self.step_height + SKIN);
+        }
+        if self.grounded {
+            self.velocity.y = 0.0;
+        }
+    }
+
+    /// WASD to walk where the camera is looking, space to jump, arrow keys or the mouse to look around.
+    /// Moves the camera to the eyes, so use this instead of `CameraState::free_cam`
+    pub fn first_person(
+        &mut self,
+        ctx: &mut Context,
+        world: &CollisionWorld3d,
+        speed: f32,
+        rot_speed: f32,
+    ) {
+        let dt = ctx.dt;
+        CameraState::free_look(dt, ctx, rot_speed);
+
+        let (f, s, _) = ctx.camera.get_directions();
+        let forward = Vec3::new(f.x, 0.0, f.z)
+            .try_normalized()
+            .unwrap_or_default();
+        let left = Vec3::new(s.x, 0.0, s.z)
+            .try_normalized()
+            .unwrap_or_default();
+        let mut walk = Vec3::zero();
+        if ctx.input.is_key_pressed(KeyCode::KeyW) {
+            walk += forward;
+        }
+        if ctx.input.is_key_pressed(KeyCode::KeyS) {
+            walk -= forward;
+        }
+        if ctx.input.is_key_pressed(KeyCode::KeyA) {
+            walk += left;
+        }
+        if ctx.input.is_key_pressed(KeyCode::KeyD) {
+            walk -= left;
+        }
+        if ctx.input.is_key_pressed(KeyCode::Space) {
+            self.jump();
+        }
+
+        let walk = walk.try_normalized().unwrap_or_default() * speed;
+        self.move_and_slide(world, walk, dt);
+        ctx.camera.position = self.eye_position();
+    }
+
+    fn is_walkable(&self, normal: Vec3<f32>) -> bool {
+        normal.y >= self.max_slope.cos()
+    }
+
+    /// Slides along walls, and if one is in the way tries stepping up onto it
+    fn walk(&mut self, world: &CollisionWorld3d, walk: Vec3<f32>) {
+        let start = self.position;
+        let blocked = self
+            .slide(world, walk, true)
+            .into_iter()
+            .any(|normal| !self.is_walkable(normal));
+        if !blocked || !self.grounded || self.step_height <= 0.0 {
+            return;
+        }
+
+        // Restored together if the step doesn't work out, the snap below sets the ground too
+        let slid = (self.position, self.grounded, self.ground_normal);
+        self.position = start;
+        let up = Vec3::unit_y() * self.step_height;
+        let raised = match world.sweep_capsule(self.capsule(), up) {
+            Some(hit) => self.step_height * hit.fraction - SKIN,
+            None => self.step_height,
+        };
+        if raised <= SKIN {
+            self.position = slid.0;
+            return;
+        }
+        self.position.y += raised;
+        self.slide(world, walk, true);
+
+        // Only keep the step if it lands on something and gets further than sliding did
+        let flat_distance =
+            |point: Vec3<f32>| Vec3::new(point.x - start.x, 0.0, point.z - start.z).magnitude();
+        if !self.snap_to_ground(world, raised + SKIN)
+            || flat_distance(self.position) <= flat_distance(slid.0)
+        {
+            (self.position, self.grounded, self.ground_normal) = slid;
+        }
+    }
+
+    /// Moves along `displacement` and slides along whatever it hits, returns the normals it hit.
+    /// Walking doesn't slide up steep slopes, it treats them like walls
+    fn slide(
+        &mut self,
+        world: &CollisionWorld3d,
+        displacement: Vec3<f32>,
+        walking: bool,
+    ) -> Vec<Vec3<f32>> {
+        let mut normals = Vec::new();
+        let mut remaining = displacement;
+        for _ in 0..MAX_SLIDES {
+            let length = remaining.magnitude();
+            if length <= f32::EPSILON {
+                break;
+            }
+            let Some(hit) = world.sweep_capsule(self.capsule(), remaining) else {
+                self.position += remaining;
+                break;
+            };
+            let fraction = (hit.fraction - SKIN / length).max(0.0);
+            self.position += remaining * fraction;
+            normals.push(hit.normal);
+
+            let mut normal = hit.normal;
+            if walking && !self.is_walkable(normal) {
+                normal.y = 0.0;
+                normal = normal.try_normalized().unwrap_or(hit.normal);
+            } else if !walking && self.is_walkable(normal) && remaining.y < 0.0 {
+                // Landed, don't slide down the slope
+                break;
+            }
+            remaining *= 1.0 - fraction;
+            remaining -= normal * remaining.dot(normal);
+        }
+        normals
+    }
+
+    /// Moves down up to `distance` if there's walkable ground there
+    fn snap_to_ground(&mut self, world: &CollisionWorld3d, distance: f32) -> bool {
+        let down = -Vec3::unit_y() * distance;
+        match world.sweep_capsule(self.capsule(), down) {
+            Some(hit) if self.is_walkable(hit.normal) => {
+                self.position.y -= (distance * hit.fraction - SKIN).max(0.0);
+                self.grounded = true;
+                self.ground_normal = hit.normal;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pushes it out of anything it ended up inside of, like a collider added on top of it
+    fn depenetrate(&mut self, world: &CollisionWorld3d) {
+        for _ in 0..MAX_DEPENETRATIONS {
+            let Some(deepest) = world
+                .penetrations(self.capsule())
+                .into_iter()
+                .max_by(|a, b| a.depth.total_cmp(&b.depth))
+            else {
+                break;
+            };
+            self.position += deepest.normal * (deepest.depth + SKIN);
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use vek::{Aabb, Vec3};
+
+// Sweeps stop once they're this close to touching
+const SWEEP_TOLERANCE: f32 = 0.0005;
+const SWEEP_ITERATIONS: usize = 64;
+
+/// A convex piece of a collider, grown by a radius for spheres and capsules
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Primitive {
+    Point(Vec3<f32>, f32),
+    Segment(Vec3<f32>, Vec3<f32>, f32),
+    Triangle([Vec3<f32>; 3]),
+    Box(Aabb<f32>),
+}
+
+impl Primitive {
+    fn radius(&self) -> f32 {
+        match self {
+            Self::Point(_, radius) | Self::Segment(_, _, radius) => *radius,
+            Self::Triangle(_) | Self::Box(_) => 0.0,
+        }
+    }
+
+    /// Closest points between the segment and the primitive's core, in that order
+    fn closest_to_segment(&self, p: Vec3<f32>, q: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+        match self {
+            Self::Point(center, _) => (closest_on_segment(*center, p, q), *center),
+            Self::Segment(a, b, _) => closest_segment_segment(p, q, *a, *b),
+            Self::Triangle(triangle) => closest_segment_triangle(p, q, triangle),
+            Self::Box(aabb) => {
+                for point in [p, q] {
+                    if contains(aabb, point) {
+                        return (point, point);
+                    }
+                }
+                box_triangles(aabb)
+                    .iter()
+                    .map(|triangle| closest_segment_triangle(p, q, triangle))
+                    .min_by(|(a0, a1), (b0, b1)| {
+                        a0.distance_squared(*a1)
+                            .total_cmp(&b0.distance_squared(*b1))
+                    })
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Pushes a capsule out of the primitive, the normal points toward the capsule
+    pub(crate) fn penetration(
+        &self,
+        p: Vec3<f32>,
+        q: Vec3<f32>,
+        radius: f32,
+    ) -> Option<(Vec3<f32>, f32)> {
+        let (on_segment, on_primitive) = self.closest_to_segment(p, q);
+        let radii = radius + self.radius();
+        let distance = on_segment.distance(on_primitive);
+        if distance >= radii {
+            return None;
+        }
+        let normal = if distance > f32::EPSILON {
+            (on_segment - on_primitive) / distance
+        } else {
+            self.fallback_normal((p + q) / 2.0)
+        };
+        Some((normal, radii - distance))
+    }
+
+    /// Direction out of the primitive when the capsule's core is right on it
+    fn fallback_normal(&self, point: Vec3<f32>) -> Vec3<f32> {
+        match self {
+            Self::Triangle(triangle) => triangle_normal(triangle),
+            Self::Box(aabb) => {
+                // Out the nearest face
+                let center = (aabb.min + aabb.max) / 2.0;
+                let half = (aabb.max - aabb.min) / 2.0;
+                let offset = point - center;
+                let gaps = half - offset.map(f32::abs);
+                let mut normal = Vec3::zero();
+                if gaps.x <= gaps.y && gaps.x <= gaps.z {
+                    normal.x = offset.x.signum();
+                } else if gaps.y <= gaps.z {
+                    normal.y = offset.y.signum();
+                } else {
+                    normal.z = offset.z.signum();
+                }
+                normal
+            }
+            Self::Point(..) | Self::Segment(..) => Vec3::unit_y(),
+        }
+    }
+
+    /// How far along `displacement` a capsule gets before touching, as a fraction, and the normal there.
+    /// Conservative advancement, the capsule moves forward by its distance to the primitive until it's touching
+    pub(crate) fn sweep(
+        &self,
+        p: Vec3<f32>,
+        q: Vec3<f32>,
+        radius: f32,
+        displacement: Vec3<f32>,
+    ) -> Option<(f32, Vec3<f32>, Vec3<f32>)> {
+        let length = displacement.magnitude();
+        if length <= f32::EPSILON {
+            return None;
+        }
+        let radii = radius + self.radius();
+        let mut fraction = 0.0;
+        for _ in 0..SWEEP_ITERATIONS {
+            let offset = displacement * fraction;
+            let (on_segment, on_primitive) = self.closest_to_segment(p + offset, q + offset);
+            let distance = on_segment.distance(on_primitive);
+            let gap = distance - radii;
+            if gap < SWEEP_TOLERANCE {
+                let normal = if distance > f32::EPSILON {
+                    (on_segment - on_primitive) / distance
+                } else {
+                    self.fallback_normal((p + q) / 2.0 + offset)
+                };
+                // Already touching and moving away
+                if normal.dot(displacement) >= 0.0 {
+                    return None;
+                }
+                let point = on_primitive + normal * self.radius();
+                return Some((fraction, normal, point));
+            }
+            fraction += gap / length;
+            if fraction > 1.0 {
+                return None;
+            }
+        }
+        None
+    }
+
+    pub(crate) fn raycast(
+        &self,
+        origin: Vec3<f32>,
+        direction: Vec3<f32>,
+        max_distance: f32,
+    ) -> Option<(f32, Vec3<f32>)> {
+        match self {
+            Self::Point(center, radius) => {
+                raycast_sphere(origin, direction, max_distance, *center, *radius)
+            }
+            Self::Segment(a, b, radius) => {
+                raycast_capsule(origin, direction, max_distance, *a, *b, *radius)
+            }
+            Self::Triangle(triangle) => raycast_triangle(origin, direction, max_distance, triangle),
+            Self::Box(aabb) => raycast_aabb(origin, direction, max_distance, aabb),
+        }
+    }
+
+    pub(crate) fn aabb(&self) -> Aabb<f32> {
+        match self {
+            Self::Point(center, radius) => Aabb {
+                min: *center - *radius,
+                max: *center + *radius,
+            },
+            Self::Segment(a, b, radius) => Aabb {
+                min: Vec3::partial_min(*a, *b) - *radius,
+                max: Vec3::partial_max(*a, *b) + *radius,
+            },
+            Self::Triangle([a, b, c]) => Aabb {
+                min: Vec3::partial_min(Vec3::partial_min(*a, *b), *c),
+                max: Vec3::partial_max(Vec3::partial_max(*a, *b), *c),
+            },
+            Self::Box(aabb) => *aabb,
+        }
+    }
+}
+
+pub(crate) fn contains(aabb: &Aabb<f32>, point: Vec3<f32>) -> bool {
+    point.x >= aabb.min.x
+        && point.y >= aabb.min.y
+        && point.z >= aabb.min.z
+        && point.x <= aabb.max.x
+        && point.y <= aabb.max.y
+        && point.z <= aabb.max.z
+}
+
+pub(crate) fn overlaps(a: &Aabb<f32>, b: &Aabb<f32>) -> bool {
+    a.min.x <= b.max.x
+        && a.max.x >= b.min.x
+        && a.min.y <= b.max.y
+        && a.max.y >= b.min.y
+        && a.min.z <= b.max.z
+        && a.max.z >= b.min.z
+}
+
+pub(crate) fn union(a: &Aabb<f32>, b: &Aabb<f32>) -> Aabb<f32> {
+    Aabb {
+        min: Vec3::partial_min(a.min, b.min),
+        max: Vec3::partial_max(a.max, b.max),
+    }
+}
+
+pub(crate) fn triangle_normal([a, b, c]: &[Vec3<f32>; 3]) -> Vec3<f32> {
+    (*b - *a).cross(*c - *a).normalized()
+}
+
+fn box_triangles(aabb: &Aabb<f32>) -> [[Vec3<f32>; 3]; 12] {
+    let corner = |x: bool, y: bool, z: bool| {
+        Vec3::new(
+            if x { aabb.max.x } else { aabb.min.x },
+            if y { aabb.max.y } else { aabb.min.y },
+            if z { aabb.max.z } else { aabb.min.z },
+        )
+    };
+    let face = |a: Vec3<f32>, b: Vec3<f32>, c: Vec3<f32>, d: Vec3<f32>| [[a, b, c], [a, c, d]];
+    let faces = [
+        face(
+            corner(false, false, false),
+            corner(false, true, false),
+            corner(true, true, false),
+            corner(true, false, false),
+        ),
+        face(
+            corner(false, false, true),
+            corner(true, false, true),
+            corner(true, true, true),
+            corner(false, true, true),
+        ),
+        face(
+            corner(false, false, false),
+            corner(false, false, true),
+            corner(false, true, true),
+            corner(false, true, false),
+        ),
+        face(
+            corner(true, false, false),
+            corner(true, true, false),
+            corner(true, true, true),
+            corner(true, false, true),
+        ),
+        face(
+            corner(false, false, false),
+            corner(true, false, false),
+            corner(true, false, true),
+            corner(false, false, true),
+        ),
+        face(
+            corner(false, true, false),
+            corner(false, true, true),
+            corner(true, true, true),
+            corner(true, true, false),
+        ),
+    ];
+    let mut triangles = [[Vec3::zero(); 3]; 12];
+    for (i, triangle) in faces.iter().flatten().enumerate() {
+        triangles[i] = *triangle;
+    }
+    triangles
+}
+
+pub(crate) fn closest_on_segment(point: Vec3<f32>, a: Vec3<f32>, b: Vec3<f32>) -> Vec3<f32> {
+    let ab = b - a;
+    let length_squared = ab.magnitude_squared();
+    if length_squared <= f32::EPSILON {
+        return a;
+    }
+    a + ab * ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0)
+}
+
+/// Closest points on segments pq and ab, from Real-Time Collision Detection
+fn closest_segment_segment(
+    p: Vec3<f32>,
+    q: Vec3<f32>,
+    a: Vec3<f32>,
+    b: Vec3<f32>,
+) -> (Vec3<f32>, Vec3<f32>) {
+    let d1 = q - p;
+    let d2 = b - a;
+    let r = p - a;
+    let e = d2.magnitude_squared();
+    let f = d2.dot(r);
+    let l = d1.magnitude_squared();
+    if l <= f32::EPSILON && e <= f32::EPSILON {
+        return (p, a);
+    }
+    let (s, t) = if l <= f32::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / l).clamp(0.0, 1.0), 0.0)
+        } else {
+            let bb = d1.dot(d2);
+            let denominator = l * e - bb * bb;
+            let mut s = if denominator > f32::EPSILON {
+                ((bb * f - c * e) / denominator).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let mut t = (bb * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / l).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((bb - c) / l).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (p + d1 * s, a + d2 * t)
+}
+
+/// Closest point on the triangle to `point`, from Real-Time Collision Detection
+pub(crate) fn closest_on_triangle(point: Vec3<f32>, [a, b, c]: &[Vec3<f32>; 3]) -> Vec3<f32> {
+    let (a, b, c) = (*a, *b, *c);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = point - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = point - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = point - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+fn closest_segment_triangle(
+    p: Vec3<f32>,
+    q: Vec3<f32>,
+    triangle: &[Vec3<f32>; 3],
+) -> (Vec3<f32>, Vec3<f32>) {
+    // Going through the triangle
+    let direction = q - p;
+    let length = direction.magnitude();
+    if length > f32::EPSILON
+        && let Some((distance, _)) = raycast_triangle(p, direction / length, length, triangle)
+    {
+        let point = p + direction / length * distance;
+        return (point, point);
+    }
+
+    let mut best = (p, closest_on_triangle(p, triangle));
+    let mut check = |pair: (Vec3<f32>, Vec3<f32>)| {
+        if pair.0.distance_squared(pair.1) < best.0.distance_squared(best.1) {
+            best = pair;
+        }
+    };
+    check((q, closest_on_triangle(q, triangle)));
+    for i in 0..3 {
+        check(closest_segment_segment(
+            p,
+            q,
+            triangle[i],
+            triangle[(i + 1) % 3],
+        ));
+    }
+    best
+}
+
+fn raycast_sphere(
+    origin: Vec3<f32>,
+    direction: Vec3<f32>,
+    max_distance: f32,
+    center: Vec3<f32>,
+    radius: f32,
+) -> Option<(f32, Vec3<f32>)> {
+    let to_origin = origin - center;
+    let b = to_origin.dot(direction);
+    let c = to_origin.magnitude_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some((0.0, -direction));
+    }
+    let discriminant = b * b - c;
+    if b > 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    (distance <= max_distance).then(|| {
+        let normal = (origin + direction * distance - center) / radius;
+        (distance, normal)
+    })
+}
+
+fn raycast_capsule(
+    origin: Vec3<f32>,
+    direction: Vec3<f32>,
+    max_distance: f32,
+    a: Vec3<f32>,
+    b: Vec3<f32>,
+    radius: f32,
+) -> Option<(f32, Vec3<f32>)> {
+    let ends = [
+        raycast_sphere(origin, direction, max_distance, a, radius),
+        raycast_sphere(origin, direction, max_distance, b, radius),
+    ];
+    let axis = b - a;
+    let length_squared = axis.magnitude_squared();
+    let mut side = None;
+    if length_squared > f32::EPSILON {
+        // Infinite cylinder, then only keep hits between the ends
+        let to_origin = origin - a;
+        let flat_direction = direction - axis * (direction.dot(axis) / length_squared);
+        let flat_origin = to_origin - axis * (to_origin.dot(axis) / length_squared);
+        let qa = flat_direction.magnitude_squared();
+        let qb = flat_direction.dot(flat_origin);
+        let qc = flat_origin.magnitude_squared() - radius * radius;
+        let discriminant = qb * qb - qa * qc;
+        if qa > f32::EPSILON && discriminant >= 0.0 {
+            let distance = (-qb - discriminant.sqrt()) / qa;
+            let along = (to_origin + direction * distance).dot(axis) / length_squared;
+            if (0.0..=max_distance).contains(&distance) && (0.0..=1.0).contains(&along) {
+                let point = origin + direction * distance;
+                let normal = (point - (a + axis * along)).normalized();
+                side = Some((distance, normal));
+            }
+        }
+    }
+    ends.into_iter()
+        .chain([side])
+        .flatten()
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+}
+
+/// Möller-Trumbore, hits from both sides
+fn raycast_triangle(
+    origin: Vec3<f32>,
+    direction: Vec3<f32>,
+    max_distance: f32,
+    triangle: &[Vec3<f32>; 3],
+) -> Option<(f32, Vec3<f32>)> {
+    let [a, b, c] = *triangle;
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+    if determinant.abs() <= f32::EPSILON {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let to_origin = origin - a;
+    let u = to_origin.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(ab);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = ac.dot(q) * inverse;
+    if !(0.0..=max_distance).contains(&distance) {
+        return None;
+    }
+    let normal = triangle_normal(triangle);
+    let normal = if normal.dot(direction) > 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    Some((distance, normal))
+}
+
+pub(crate) fn raycast_aabb(
+    origin: Vec3<f32>,
+    direction: Vec3<f32>,
+    max_distance: f32,
+    aabb: &Aabb<f32>,
+) -> Option<(f32, Vec3<f32>)> {
+    let (mut enter, mut exit) = (0.0, max_distance);
+    let mut normal = -direction;
+    for axis in 0..3 {
+        let (min, max) = (aabb.min[axis], aabb.max[axis]);
+        if direction[axis].abs() <= f32::EPSILON {
+            if origin[axis] < min || origin[axis] > max {
+                return None;
+            }
+            continue;
+        }
+        let inverse = 1.0 / direction[axis];
+        let (mut near, mut far) = (
+            (min - origin[axis]) * inverse,
+            (max - origin[axis]) * inverse,
+        );
+        if near > far {
+            std::mem::swap(&mut near, &mut far);
+        }
+        if near > enter {
+            enter = near;
+            normal = Vec3::zero();
+            normal[axis] = -direction[axis].signum();
+        }
+        exit = exit.min(far);
+        if enter > exit {
+            return None;
+        }
+    }
+    Some((enter, normal))
+}
@@ -0,0 +1,154 @@
+use crate::{
+    asset::model::Model,
+    collision3d::geometry::{self, Primitive},
+};
+use vek::{Aabb, Mat4, Vec3, Vec4};
+
+// Most triangles in one leaf of the tree
+const LEAF_SIZE: usize = 4;
+
+/// Triangles to collide with, usually the level. Kept in a bounding volume tree so
+/// only the triangles near a query get checked
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    triangles: Vec<[Vec3<f32>; 3]>,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    aabb: Aabb<f32>,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NodeKind {
+    Leaf { start: usize, end: usize },
+    Branch { left: usize, right: usize },
+}
+
+impl TriangleMesh {
+    /// Triangles with no area are left out
+    pub fn new(triangles: Vec<[Vec3<f32>; 3]>) -> Self {
+        let mut triangles: Vec<[Vec3<f32>; 3]> = triangles
+            .into_iter()
+            .filter(|[a, b, c]| (*b - *a).cross(*c - *a).magnitude_squared() > f32::EPSILON)
+            .collect();
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            build(&mut triangles, 0, len, &mut nodes);
+        }
+        Self { triangles, nodes }
+    }
+
+    /// Uses the triangles kept by `load_wavefront`, moved by `transform` like the `model` uniform would
+    pub fn from_model(model: &Model, transform: Mat4<f32>) -> Self {
+        let to_world = |point: Vec3<f32>| (transform * Vec4::from_point(point)).xyz();
+        Self::new(
+            model
+                .get_triangles()
+                .iter()
+                .map(|[a, b, c]| [to_world(*a), to_world(*b), to_world(*c)])
+                .collect(),
+        )
+    }
+
+    pub fn get_triangles(&self) -> &[[Vec3<f32>; 3]] {
+        &self.triangles
+    }
+
+    /// `None` if there are no triangles
+    pub fn aabb(&self) -> Option<Aabb<f32>> {
+        self.nodes.first().map(|node| node.aabb)
+    }
+
+    /// Runs `f` on every triangle whose bounding box overlaps `aabb`
+    pub(crate) fn for_each_near(&self, aabb: &Aabb<f32>, mut f: impl FnMut(Primitive)) {
+        self.walk(
+            |node_aabb| geometry::overlaps(node_aabb, aabb),
+            |triangle| f(Primitive::Triangle(*triangle)),
+        );
+    }
+
+    pub(crate) fn raycast(
+        &self,
+        origin: Vec3<f32>,
+        direction: Vec3<f32>,
+        max_distance: f32,
+    ) -> Option<(f32, Vec3<f32>)> {
+        let mut best: Option<(f32, Vec3<f32>)> = None;
+        let mut closest = max_distance;
+        self.walk(
+            |node_aabb| {
+                geometry::raycast_aabb(origin, direction, max_distance, node_aabb).is_some()
+            },
+            |triangle| {
+                if let Some(hit) =
+                    Primitive::Triangle(*triangle).raycast(origin, direction, closest)
+                {
+                    closest = hit.0;
+                    best = Some(hit);
+                }
+            },
+        );
+        best
+    }
+
+    fn walk(&self, mut visit: impl FnMut(&Aabb<f32>) -> bool, mut f: impl FnMut(&[Vec3<f32>; 3])) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !visit(&node.aabb) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => self.triangles[start..end].iter().for_each(&mut f),
+                NodeKind::Branch { left, right } => stack.extend([left, right]),
+            }
+        }
+    }
+}
+
+/// Splits the triangles in half along the longest side of their box until the leaves are small
+fn build(
+    triangles: &mut [[Vec3<f32>; 3]],
+    start: usize,
+    end: usize,
+    nodes: &mut Vec<Node>,
+) -> usize {
+    let aabb = triangles[start..end]
+        .iter()
+        .map(|triangle| Primitive::Triangle(*triangle).aabb())
+        .reduce(|a, b| geometry::union(&a, &b))
+        .unwrap();
+    let index = nodes.len();
+    nodes.push(Node {
+        aabb,
+        kind: NodeKind::Leaf { start, end },
+    });
+    if end - start <= LEAF_SIZE {
+        return index;
+    }
+
+    let size = aabb.max - aabb.min;
+    let axis = if size.x >= size.y && size.x >= size.z {
+        0
+    } else if size.y >= size.z {
+        1
+    } else {
+        2
+    };
+    let middle = (start + end) / 2;
+    let centroid = |[a, b, c]: &[Vec3<f32>; 3]| (a[axis] + b[axis] + c[axis]) / 3.0;
+    triangles[start..end]
+        .select_nth_unstable_by(middle - start, |a, b| centroid(a).total_cmp(&centroid(b)));
+
+    let left = build(triangles, start, middle, nodes);
+    let right = build(triangles, middle, end, nodes);
+    nodes[index].kind = NodeKind::Branch { left, right };
+    index
+}
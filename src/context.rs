@@ -1,37 +1,118 @@
 use crate::{
     error::EngineResult,
-    shaders::{TEXT_FS, TEXT_VS},
+    renderer::{
+        capture::FrameCapture,
+        lighting::Lights,
+        render_queue::RenderQueue,
+        render_target::{RenderTarget, Resizable},
+        shadow::ShadowMap,
+    },
+    shaders::{self, TEXT_FS, TEXT_VS},
 };
 use audio::Audio;
 use camera::CameraState;
+use display::DisplayWrapper;
 use font::Font;
-use glium::{Display, Program, glutin::surface::WindowSurface, winit::window::Window};
+use frame_pacing::{FramePacing, FrameStats, Vsync};
+use glium::{
+    Display, Program, Rect,
+    glutin::surface::WindowSurface,
+    program::ProgramCreationInput,
+    winit::window::{CursorIcon, Window},
+};
 use input::Input;
-use std::{collections::HashMap, f32::consts::PI};
-use vek::Vec3;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    f32::consts::PI,
+    rc::{Rc, Weak},
+};
+use vek::{Vec2, Vec3};
+use viewport::{Viewport, ViewportMode};
+use window::{FullscreenMode, WindowConfig};
 
 pub mod audio;
 pub mod camera;
+pub mod display;
 pub(crate) mod font;
+pub mod frame_pacing;
 mod input;
+pub mod viewport;
+pub mod window;
 
 /// Holds everything that the user can use for event handling, audio, and basic boilerplate
 pub struct Context {
-    pub window: Window,
-    pub display: Display<WindowSurface>,
+    /// `None` when made with `init_headless`
+    pub window: Option<Window>,
+    pub display: DisplayWrapper,
     pub input: Input,
     pub audio: Audio,
     pub camera: CameraState,
+    /// Used by `MeshRenderer`s with a `Material` and `Renderer::draw_model`
+    pub lights: Lights,
+    /// Drawn to the renderer given to `GameState::draw` once it returns, sorted by layer and distance
+    pub render_queue: RenderQueue<'static>,
     pub dt: f32,
     pub fixed_update: FixedUpdate,
+    pub frame_pacing: FramePacing,
+    /// Pressing Escape asks to quit, same as closing the window. Defaults to true
+    pub quit_on_escape: bool,
+    frame_stats: FrameStats,
     programs: HashMap<String, Program>,
     fonts: HashMap<String, Font>,
+    // Where frames are drawn when there is no window
+    pub(crate) headless_target: Option<RenderTarget>,
+    pub(crate) capture: Option<FrameCapture>,
+    // `None` when the viewport mode is `Fill`
+    pub(crate) viewport: Option<Viewport>,
+    // Drawn by `draw_shadows`, `None` until the first time it's called
+    pub(crate) shadow_map: Option<ShadowMap>,
+    // Kept the same size as the render size, see `resize_with_window`
+    resizables: Vec<(Weak<RefCell<dyn Resizable>>, f32)>,
+    window_size: (u32, u32),
+    scale_factor: f64,
+    quit_requested: bool,
 }
 
 impl Context {
-    pub(crate) fn new(window: Window, display: Display<WindowSurface>) -> EngineResult<Self> {
+    pub(crate) fn new(
+        window: Window,
+        display: Display<WindowSurface>,
+        vsync: Vsync,
+        config: &WindowConfig,
+    ) -> EngineResult<Self> {
         let window_size = window.inner_size();
+        let scale_factor = window.scale_factor();
+        let refresh_rate = monitor_refresh_rate(&window);
+        let mut ctx = Self::build(
+            Some(window),
+            DisplayWrapper::Window(display),
+            (window_size.width, window_size.height),
+            if config.null_audio {
+                Audio::new_null()
+            } else {
+                Audio::new()?
+            },
+        )?;
+        ctx.frame_pacing = FramePacing::new(vsync, refresh_rate);
+        ctx.scale_factor = scale_factor;
+        Ok(ctx)
+    }
 
+    /// Uses the null audio backend so tests don't need a sound card
+    pub(crate) fn new_headless(size: (u32, u32)) -> EngineResult<Self> {
+        let display = DisplayWrapper::Headless(display::headless::create_context(size)?);
+        let mut ctx = Self::build(None, display, size, Audio::new_null())?;
+        ctx.headless_target = Some(RenderTarget::new(&ctx.display, size.0, size.1)?);
+        Ok(ctx)
+    }
+
+    fn build(
+        window: Option<Window>,
+        display: DisplayWrapper,
+        size: (u32, u32),
+        audio: Audio,
+    ) -> EngineResult<Self> {
         let mut programs = HashMap::new();
         programs.insert(
             "text".to_string(),
@@ -42,27 +123,204 @@ impl Context {
             window,
             display,
             input: Input::default(),
-            audio: Audio::new()?,
+            audio,
             camera: CameraState::new(
                 Vec3::new(0.0, 0.0, 0.0),
                 Vec3::new(0.0, 0.0, 0.0),
                 Vec3::new(0.0, 1.0, 0.0),
                 PI / 2.0,
-                window_size.width as f32 / window_size.height as f32,
+                size.0 as f32 / size.1.max(1) as f32,
                 0.001,
                 1000.0,
             ),
+            lights: Lights::default(),
+            render_queue: RenderQueue::new(),
             dt: 0.0,
             fixed_update: FixedUpdate {
                 accumulator: 0.0,
                 tick_rate: 0.0166,
+                mode: LoopMode::Fixed,
+                max_steps: 5,
+                alpha: 1.0,
             },
+            frame_pacing: FramePacing::new(Vsync::Off, None),
+            quit_on_escape: true,
+            frame_stats: FrameStats::new(),
             programs,
             fonts: HashMap::new(),
+            headless_target: None,
+            capture: None,
+            viewport: None,
+            shadow_map: None,
+            resizables: Vec::new(),
+            window_size: size,
+            scale_factor: 1.0,
+            quit_requested: false,
         })
     }
 
-    /// Same as `add_program` but creates the program for you
+    pub fn is_headless(&self) -> bool {
+        self.display.is_headless()
+    }
+
+    /// Size of the window in pixels, or the size given to `init_headless`
+    pub fn get_window_size(&self) -> (u32, u32) {
+        self.window_size
+    }
+    /// Size the game is drawn at, the virtual resolution if the viewport mode has one
+    pub fn get_render_size(&self) -> (u32, u32) {
+        self.get_viewport_mode()
+            .get_resolution()
+            .unwrap_or(self.window_size)
+    }
+
+    pub fn get_viewport_mode(&self) -> ViewportMode {
+        self.viewport
+            .as_ref()
+            .map_or(ViewportMode::Fill, |viewport| viewport.mode)
+    }
+    /// The renderer given to `GameState::draw` is the virtual resolution in size
+    /// for modes that have one, it gets scaled onto the window after drawing
+    pub fn set_viewport_mode(&mut self, mode: ViewportMode) -> EngineResult {
+        let window_size = self.window_size;
+        self.viewport = Viewport::new(self, mode, window_size)?;
+        self.update_aspect_ratio();
+        self.resize_registered();
+        Ok(())
+    }
+    /// Where the game is drawn in the window, from the bottom left
+    pub fn get_viewport_rect(&self) -> Rect {
+        self.get_viewport_mode().get_rect(self.window_size)
+    }
+    /// Turns a position in the window, like `input.mouse_position()`, into a position in the render size
+    pub fn window_to_viewport(&self, position: Vec2<f32>) -> Option<Vec2<f32>> {
+        self.get_viewport_mode()
+            .window_to_viewport(self.window_size, position)
+    }
+
+    /// Called when the window is resized, before `GameState::resized`
+    pub(crate) fn resize(&mut self, size: (u32, u32)) {
+        self.window_size = size;
+        self.display.resize(size);
+        if let Some(viewport) = &mut self.viewport {
+            viewport.resize(size);
+        }
+        self.update_aspect_ratio();
+        self.resize_registered();
+    }
+
+    /// Keeps a `RenderTarget`, `FXAA`, `GBuffer` or anything else `Resizable` at the render size times
+    /// `scale`, so it doesn't have to be done in `GameState::resized`. It's resized right away and
+    /// forgotten once every `Rc` to it is dropped
+    pub fn resize_with_window<R: Resizable + 'static>(
+        &mut self,
+        resizable: &Rc<RefCell<R>>,
+        scale: f32,
+    ) {
+        let weak: Weak<RefCell<R>> = Rc::downgrade(resizable);
+        let weak: Weak<RefCell<dyn Resizable>> = weak;
+        self.resizables.push((weak, scale));
+        self.resize_registered();
+    }
+
+    fn resize_registered(&mut self) {
+        let (width, height) = self.get_render_size();
+        let mut resizables = std::mem::take(&mut self.resizables);
+        resizables.retain(|(resizable, scale)| {
+            let Some(resizable) = resizable.upgrade() else {
+                return false;
+            };
+            let scaled = |size: u32| ((size as f32 * scale) as u32).max(1);
+            if let Err(err) = resizable
+                .borrow_mut()
+                .resize(self, scaled(width), scaled(height))
+            {
+                eprintln!("Unable to resize a render target: {}", err);
+            }
+            true
+        });
+        // Resizing could have registered more
+        resizables.append(&mut self.resizables);
+        self.resizables = resizables;
+    }
+
+    /// How many pixels make up a point on the window's monitor, 1.0 when headless
+    pub fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+    /// Fonts are rasterized again for the new DPI
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) -> EngineResult {
+        self.scale_factor = scale_factor;
+        for font in self.fonts.values_mut() {
+            font.set_scale_factor(&self.display, scale_factor as f32)?;
+        }
+        Ok(())
+    }
+
+    fn update_aspect_ratio(&mut self) {
+        let (width, height) = self.get_render_size();
+        self.camera.aspect_ratio = width as f32 / height.max(1) as f32;
+    }
+
+    /// Exits once the current event or frame is done, skips `GameState::on_quit_requested`
+    pub fn quit(&mut self) {
+        self.quit_requested = true;
+    }
+    pub fn is_quit_requested(&self) -> bool {
+        self.quit_requested
+    }
+
+    /// Does nothing when headless, same for the other window functions
+    pub fn set_title(&self, title: &str) {
+        if let Some(window) = &self.window {
+            window.set_title(title);
+        }
+    }
+    pub fn set_fullscreen(&self, mode: FullscreenMode) {
+        if let Some(window) = &self.window {
+            window::set_fullscreen(window, mode);
+        }
+    }
+    pub fn get_fullscreen(&self) -> FullscreenMode {
+        self.window
+            .as_ref()
+            .map_or(FullscreenMode::Windowed, window::get_fullscreen)
+    }
+    /// Switches between windowed and borderless fullscreen
+    pub fn toggle_fullscreen(&self) {
+        match self.get_fullscreen() {
+            FullscreenMode::Windowed => self.set_fullscreen(FullscreenMode::Borderless),
+            FullscreenMode::Borderless | FullscreenMode::Exclusive => {
+                self.set_fullscreen(FullscreenMode::Windowed)
+            }
+        }
+    }
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        if let Some(window) = &self.window {
+            window.set_cursor(icon);
+        }
+    }
+
+    pub fn get_frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+    pub(crate) fn get_frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+
+    /// Saves frames to PNGs after `GameState::draw` until `stop_capture` is called
+    pub fn start_capture(&mut self, capture: FrameCapture) {
+        self.capture = Some(capture);
+    }
+    pub fn stop_capture(&mut self) -> Option<FrameCapture> {
+        self.capture.take()
+    }
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Same as `add_program` but creates the program for you.
+    /// `#include <shadows>` lines are replaced with `shaders::SHADOWS_GLSL`
     pub fn new_program(
         &mut self,
         name: impl Into<String>,
@@ -70,9 +328,44 @@ impl Context {
         frag: &str,
         geom: Option<&str>,
     ) -> EngineResult {
+        let geom = geom.map(shaders::resolve_includes);
+        self.programs.insert(
+            name.into(),
+            Program::from_source(
+                &self.display,
+                &shaders::resolve_includes(vert),
+                &shaders::resolve_includes(frag),
+                geom.as_deref(),
+            )?,
+        );
+        Ok(())
+    }
+
+    /// Same as `new_program` for shaders that convert their output to sRGB themselves, like the
+    /// built-in "pbr" one, so glium doesn't convert it again
+    pub fn new_srgb_program(
+        &mut self,
+        name: impl Into<String>,
+        vert: &str,
+        frag: &str,
+    ) -> EngineResult {
+        let vert = shaders::resolve_includes(vert);
+        let frag = shaders::resolve_includes(frag);
         self.programs.insert(
             name.into(),
-            Program::from_source(&self.display, vert, frag, geom)?,
+            Program::new(
+                &self.display,
+                ProgramCreationInput::SourceCode {
+                    vertex_shader: &vert,
+                    tessellation_control_shader: None,
+                    tessellation_evaluation_shader: None,
+                    geometry_shader: None,
+                    fragment_shader: &frag,
+                    transform_feedback_varyings: None,
+                    outputs_srgb: true,
+                    uses_point_size: false,
+                },
+            )?,
         );
         Ok(())
     }
@@ -98,8 +391,10 @@ impl Context {
         font: &[u8],
         font_size: f32,
     ) -> EngineResult {
-        self.fonts
-            .insert(name.into(), Font::new(&self.display, font, font_size)?);
+        self.fonts.insert(
+            name.into(),
+            Font::new(&self.display, font, font_size, self.scale_factor as f32)?,
+        );
         Ok(())
     }
     pub fn remove_font(&mut self, name: impl Into<String>) {
@@ -114,9 +409,48 @@ impl Context {
     // }
 }
 
+/// How `GameState::fixed_update` is ran each frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+    /// `fixed_update` runs once every frame right after `update`, ignoring `tick_rate`
+    Variable,
+    /// `fixed_update` runs every `tick_rate` seconds, possibly more than once a frame
+    Fixed,
+    /// Same as `Fixed` but the alpha passed to `GameState::draw` says how far between the last two
+    /// fixed updates the frame is, use it to interpolate positions in `draw` so movement is
+    /// smooth on monitors faster than the tick rate
+    FixedInterpolated,
+}
+
 /// Change tick_rate to what you want it to be in your game
 /// Default is 0.0166 or 60 fps
 pub struct FixedUpdate {
     pub(crate) accumulator: f32,
     pub tick_rate: f32,
+    pub mode: LoopMode,
+    /// Most fixed updates ran in one frame, after a long hitch the rest of the time is dropped
+    /// instead of the game trying to catch up forever. Default is 5
+    pub max_steps: u32,
+    pub(crate) alpha: f32,
+}
+
+impl FixedUpdate {
+    /// 0.0 to 1.0, only changes with `LoopMode::FixedInterpolated` and is 1.0 otherwise.
+    /// Draw at `previous.lerp(current, alpha)` where `previous` is from the fixed update before last
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Time that hasn't been used by a fixed update yet
+    pub fn get_accumulator(&self) -> f32 {
+        self.accumulator
+    }
+}
+
+/// Refresh rate of the monitor the window is on, in hertz
+pub(crate) fn monitor_refresh_rate(window: &Window) -> Option<f32> {
+    window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+        .map(|millihertz| millihertz as f32 / 1000.0)
 }
@@ -1,12 +1,51 @@
-use crate::error::{EngineError, EngineResult};
+use crate::{
+    context::camera::CameraState,
+    error::{EngineError, EngineResult},
+};
 use cpal::{
-    Device, FromSample, Host, OutputCallbackInfo, Sample, SupportedStreamConfig,
+    Device, Host, OutputCallbackInfo, Stream, StreamError, SupportedStreamConfig,
     traits::{DeviceTrait, HostTrait, StreamTrait},
 };
+use effects::Effect;
+use mixer::{Bus, Mixer, Voice};
+use resampler::Resampler;
 use sound::Sound;
-use std::{collections::HashMap, sync::Arc, thread, time::Duration};
+use spatial::{Emitter, Listener};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use wav_io::header::{SampleFormat, WavHeader};
 
+pub mod effects;
+mod mixer;
+pub mod resampler;
 pub mod sound;
+pub mod spatial;
+pub mod synth;
+
+/// Sample rate used by `Audio::new_null` and when there is no output device
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+/// Name of the bus every other bus and sound ends up in
+pub const MASTER_BUS: &str = "master";
+// Longest wait between tries at reopening a device that keeps failing
+const MAX_DEVICE_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Extra settings for `Audio::play_with`
+#[derive(Default)]
+pub struct PlayOptions {
+    /// Plays the sound from a point in the world, see `Audio::play_spatial`
+    pub emitter: Option<Emitter>,
+    /// Name of the bus to play on, the master bus is used if it's `None` or the bus doesn't exist
+    pub bus: Option<String>,
+    /// Effects only used on this sound, they are run before the bus effects.
+    /// The sound keeps playing until the effects' tails have rung out, so long ones are better on a bus
+    pub effects: Vec<Box<dyn Effect>>,
+}
 
 pub mod audio_play_value {
     use std::sync::{
@@ -31,33 +70,156 @@ pub mod audio_play_value {
     }
 }
 
+/// Where the mixed audio ends up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioBackend {
+    /// Plays through the active output device
+    Device,
+    /// No output device, sounds are still mixed as time passes but nothing is heard.
+    /// Picked automatically when there is no output device or it fails to open
+    Null,
+    /// Nothing is mixed until `render` or `render_to_wav` is called, useful for testing audio
+    Offline,
+}
+
 pub struct Audio {
     host: Host,
     output_devices: HashMap<String, (Device, SupportedStreamConfig)>,
     active_output_device: String,
+    mixer: Arc<Mutex<Mixer>>,
+    backend: AudioBackend,
+    // Kept alive for as long as the device backend is used
+    stream: Option<Stream>,
+    /// Leftover fraction of a frame when the null backend is advanced
+    null_frame_accumulator: f64,
+    /// False when the null or offline backend was asked for, so devices are never opened
+    use_devices: bool,
+    /// Set by the stream when its device is unplugged
+    device_lost: Arc<AtomicBool>,
+    last_device_check: Instant,
+    // Doubles every time a device fails to open, zero once one does
+    device_retry_delay: Duration,
+    /// When true the listener is moved to `ctx.camera` every frame
+    pub listener_follows_camera: bool,
+    /// When true the audio moves to the default output device whenever it changes,
+    /// turned off by `set_output_device` and back on by `set_output_device_as_default_device`
+    pub follow_default_device: bool,
+    /// How often to check if the output devices changed, default is 1 second
+    pub device_check_interval: Duration,
+    /// When true the audio is paused while the window isn't focused, default is false
+    pub pause_on_focus_lost: bool,
+    // So gaining focus doesn't resume audio the game paused itself
+    paused_by_focus: bool,
 }
 
 impl Audio {
+    /// Opens the default output device, falls back to the null backend if that doesn't work
     pub(crate) fn new() -> EngineResult<Self> {
-        // Might need to add host to Audio later
-        let host = cpal::default_host();
-        let mut output_devices = HashMap::new();
-        for device in host.output_devices()? {
-            let config = device.default_output_config()?;
-            output_devices.insert(device.name()?, (device, config));
+        let mut audio = Self::with_backend(AudioBackend::Null, DEFAULT_SAMPLE_RATE, 2);
+        audio.use_devices = true;
+        if let Err(err) = audio.refresh_output_devices() {
+            eprintln!("Unable to get audio output devices: {}", err);
+        }
+        // A device without a name is treated like no device at all
+        if let Some(name) = audio
+            .host
+            .default_output_device()
+            .and_then(|device| device.name().ok())
+        {
+            audio.active_output_device = name;
         }
-        let mut active_output_device = String::new();
-        if let Some(device) = host.default_output_device() {
-            active_output_device = device.name()?;
+        if audio
+            .output_devices
+            .contains_key(&audio.active_output_device)
+            && let Err(err) = audio.start_stream()
+        {
+            eprintln!(
+                "Unable to open audio output device, using null audio: {}",
+                err
+            );
         }
-        Ok(Self {
-            host,
-            output_devices,
-            active_output_device,
-        })
+        Ok(audio)
     }
 
-    /// There is noise when the file is done but the thread is still active will try to fix sometime. Volume and speed can be changed using values of type
+    /// Audio that never opens an output device, for machines without one like CI or servers
+    pub fn new_null() -> Self {
+        Self::with_backend(AudioBackend::Null, DEFAULT_SAMPLE_RATE, 2)
+    }
+
+    /// Audio that only mixes when `render` is called so the output is always the same
+    pub fn new_offline(sample_rate: u32, channels: u16) -> Self {
+        Self::with_backend(AudioBackend::Offline, sample_rate, channels)
+    }
+
+    fn with_backend(backend: AudioBackend, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            host: cpal::default_host(),
+            output_devices: HashMap::new(),
+            active_output_device: String::new(),
+            mixer: Arc::new(Mutex::new(Mixer::new(sample_rate, channels))),
+            backend,
+            stream: None,
+            null_frame_accumulator: 0.0,
+            use_devices: false,
+            device_lost: Arc::new(AtomicBool::new(false)),
+            last_device_check: Instant::now(),
+            device_retry_delay: Duration::ZERO,
+            listener_follows_camera: true,
+            follow_default_device: true,
+            device_check_interval: Duration::from_secs(1),
+            pause_on_focus_lost: false,
+            paused_by_focus: false,
+        }
+    }
+
+    /// Opens a stream on the active output device that pulls from the mixer, sounds that are
+    /// already playing carry on through the new stream
+    fn start_stream(&mut self) -> EngineResult {
+        let (device, config) = match self.output_devices.get(&self.active_output_device) {
+            Some(device) => device,
+            None => {
+                return Err(EngineError::AudioError(format!(
+                    "Can't find device: {}",
+                    self.active_output_device
+                )));
+            }
+        };
+        // Stop the old stream first so two streams aren't pulling from the mixer
+        self.stream = None;
+        self.backend = AudioBackend::Null;
+        {
+            let mut mixer = self.mixer.lock().unwrap();
+            mixer.sample_rate = config.sample_rate().0;
+            mixer.channels = config.channels();
+            // Nothing is pulling from the mixer yet so this is off the audio thread
+            mixer.prepare_effects();
+        }
+
+        let mixer = Arc::clone(&self.mixer);
+        let device_lost = Arc::clone(&self.device_lost);
+        let err_fn = move |err| {
+            if let StreamError::DeviceNotAvailable = err {
+                device_lost.store(true, Ordering::Relaxed);
+            }
+            eprintln!("An error occurred on stream: {}", err);
+        };
+        let stream = device.build_output_stream(
+            &SupportedStreamConfig::into(config.clone()),
+            move |data: &mut [f32], _: &OutputCallbackInfo| {
+                mixer.lock().unwrap().mix(data);
+            },
+            err_fn,
+            None,
+        )?;
+        stream.play()?;
+
+        self.device_lost.store(false, Ordering::Relaxed);
+        self.stream = Some(stream);
+        self.backend = AudioBackend::Device;
+        Ok(())
+    }
+
+    /// Volume and speed can be changed using values of type
     /// `Arc<AtomicU64>` that are wrapped in `AudioPlayValue` you can use `atomic_f64_store` and `atomic_f64_load` to manage that data.
     pub fn play(
         &mut self,
@@ -65,82 +227,292 @@ impl Audio {
         volume: &audio_play_value::AudioPlayValue,
         speed: &audio_play_value::AudioPlayValue,
     ) -> EngineResult {
-        let (device, config) = self.output_devices.get(&self.active_output_device).unwrap();
-        let (device, config) = (device.clone(), config.clone());
-        let samples = Arc::clone(&sound.samples);
-        let sound_sample_rate = sound.header.sample_rate as f64;
-        let sound_channels = sound.header.channels;
-        let volume = volume.clone();
-        let speed = speed.clone();
-
-        rayon::spawn(move || {
-            let sample_rate = config.sample_rate().0 as f64;
-            let sample_rate_ratio = sound_sample_rate / sample_rate;
-            let duration = samples.len() as f64 / sample_rate_ratio;
-            let channels = config.channels() as usize;
-
-            let mut sample_clock = 0.0f64;
-
-            let mut next_value = move || {
-                let volume = audio_play_value::atomic_f64_load(&volume);
-                let speed = audio_play_value::atomic_f64_load(&speed);
-                let index = (sample_clock.floor() as usize) * sound_channels as usize;
-                let next_index = ((sample_clock.floor() as usize) + 1)
-                    .min(samples.len() / sound_channels as usize - 1)
-                    * sound_channels as usize;
-
-                let frac = sample_clock - sample_clock.floor();
-                let mut output_sample = 0.0;
-
-                for ch in 0..sound_channels as usize {
-                    let sample1 = samples.get(index + ch).copied().unwrap_or(0.0) as f64;
-                    let sample2 = samples.get(next_index + ch).copied().unwrap_or(0.0) as f64;
-                    output_sample += (1.0 - frac) * sample1 + frac * sample2; // Linear interpolation per channel
-                }
-
-                sample_clock += sample_rate_ratio * speed;
-                (output_sample * volume / sound_channels as f64) as f32 // Normalize volume across channels
-            };
-
-            let err_fn = |err| eprintln!("An error occurred on stream: {}", err);
-
-            let stream = device
-                .build_output_stream(
-                    &SupportedStreamConfig::into(config.clone()),
-                    move |data: &mut [f32], _: &OutputCallbackInfo| {
-                        write_data(data, channels, &mut next_value);
-                    },
-                    err_fn,
-                    None,
-                )
-                .unwrap();
-
-            stream.play().unwrap();
-            // Let the whole clip play out
-            thread::sleep(Duration::from_secs_f64(duration));
-        });
+        self.play_with(sound, volume, speed, PlayOptions::default())
+    }
+
+    /// Same as `play` but the sound comes from the `Emitter`, it gets quieter with distance,
+    /// is panned depending on where the listener is facing, and is pitched by the Doppler effect.
+    /// Moving the emitter while the sound is playing will move the sound
+    pub fn play_spatial(
+        &mut self,
+        sound: &Sound,
+        emitter: &Emitter,
+        volume: &audio_play_value::AudioPlayValue,
+        speed: &audio_play_value::AudioPlayValue,
+    ) -> EngineResult {
+        self.play_with(
+            sound,
+            volume,
+            speed,
+            PlayOptions {
+                emitter: Some(emitter.clone()),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as `play` with a choice of bus, emitter, and effects
+    pub fn play_with(
+        &mut self,
+        sound: &Sound,
+        volume: &audio_play_value::AudioPlayValue,
+        speed: &audio_play_value::AudioPlayValue,
+        mut options: PlayOptions,
+    ) -> EngineResult {
+        for effect in options.effects.iter_mut() {
+            self.prepare_effect(effect.as_mut());
+        }
+        self.mixer
+            .lock()
+            .unwrap()
+            .add_voice(Voice::new(sound, volume, speed, options));
+        Ok(())
+    }
+
+    /// Adds a bus that sounds can be played on with `PlayOptions`, does nothing if it already exists
+    pub fn add_bus(&mut self, name: impl Into<String>) {
+        self.mixer
+            .lock()
+            .unwrap()
+            .buses
+            .entry(name.into())
+            .or_insert_with(Bus::new);
+    }
+    /// Sounds still playing on the bus will move to the master bus
+    pub fn remove_bus(&mut self, name: impl Into<String>) {
+        self.mixer.lock().unwrap().buses.remove(&name.into());
+    }
+    pub fn get_bus_names(&self) -> Vec<String> {
+        self.mixer.lock().unwrap().buses.keys().cloned().collect()
+    }
+
+    pub fn set_bus_volume(&mut self, bus: impl Into<String>, volume: f32) -> EngineResult {
+        self.with_bus(bus, |bus| bus.volume = volume)
+    }
+    /// Effects are run in the order they are added
+    pub fn add_effect(
+        &mut self,
+        bus: impl Into<String>,
+        effect: impl Effect + 'static,
+    ) -> EngineResult {
+        let mut effect = Box::new(effect);
+        self.prepare_effect(effect.as_mut());
+        self.with_bus(bus, |bus| bus.effects.push(effect))
+    }
+    pub fn clear_effects(&mut self, bus: impl Into<String>) -> EngineResult {
+        self.with_bus(bus, |bus| bus.effects.clear())
+    }
+
+    /// Prepared without holding the lock so the audio thread isn't kept waiting on the allocations
+    fn prepare_effect(&self, effect: &mut dyn Effect) {
+        let (channels, sample_rate) = {
+            let mixer = self.mixer.lock().unwrap();
+            (mixer.channels as usize, mixer.sample_rate)
+        };
+        effect.prepare(channels, sample_rate);
+    }
+
+    fn with_bus(&mut self, name: impl Into<String>, f: impl FnOnce(&mut Bus)) -> EngineResult {
+        let name = name.into();
+        match self.mixer.lock().unwrap().get_bus_mut(&name) {
+            Some(bus) => {
+                f(bus);
+                Ok(())
+            }
+            None => Err(EngineError::AudioError(format!("Can't find bus: {}", name))),
+        }
+    }
+
+    /// Default is `Resampler::Cubic`
+    pub fn set_resampler(&mut self, resampler: Resampler) {
+        self.mixer.lock().unwrap().resampler = resampler;
+    }
+    pub fn get_resampler(&self) -> Resampler {
+        self.mixer.lock().unwrap().resampler
+    }
+
+    /// How many sounds are still playing
+    pub fn playing_count(&self) -> usize {
+        self.mixer.lock().unwrap().voice_count()
+    }
+
+    /// Mixes the next `duration` of audio, samples are interleaved by channel.
+    /// Doesn't work while an output device is being used since the device pulls the audio itself
+    pub fn render(&mut self, duration: Duration) -> EngineResult<Vec<f32>> {
+        if self.backend == AudioBackend::Device {
+            return Err(EngineError::AudioError(
+                "Can't render audio while it is playing through an output device".to_string(),
+            ));
+        }
+        let mut mixer = self.mixer.lock().unwrap();
+        let frames = (duration.as_secs_f64() * mixer.sample_rate as f64).round() as usize;
+        let mut samples = vec![0.0; frames * mixer.channels as usize];
+        mixer.mix(&mut samples);
+        Ok(samples)
+    }
+
+    /// Same as `render` but encoded as a 32 bit float .wav file
+    pub fn render_to_wav(&mut self, duration: Duration) -> EngineResult<Vec<u8>> {
+        let samples = self.render(duration)?;
+        Ok(wav_io::write_to_bytes(&self.wav_header(), &samples)?)
+    }
+
+    /// Header that matches the output of `render`
+    pub fn wav_header(&self) -> WavHeader {
+        WavHeader {
+            sample_format: SampleFormat::Float,
+            channels: self.channels(),
+            sample_rate: self.sample_rate(),
+            bits_per_sample: 32,
+            list_chunk: None,
+        }
+    }
+
+    pub fn backend(&self) -> AudioBackend {
+        self.backend
+    }
+    pub fn sample_rate(&self) -> u32 {
+        self.mixer.lock().unwrap().sample_rate
+    }
+    pub fn channels(&self) -> u16 {
+        self.mixer.lock().unwrap().channels
+    }
+
+    pub fn get_listener(&self) -> Listener {
+        self.mixer.lock().unwrap().listener
+    }
+    /// Set `listener_follows_camera` to false first or this will be overwritten next frame
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.mixer.lock().unwrap().listener = listener;
+    }
+
+    /// Stops every sound where it is, new sounds can still be played but won't be heard until `resume`
+    pub fn pause(&mut self) {
+        self.mixer.lock().unwrap().paused = true;
+    }
+    pub fn resume(&mut self) {
+        self.mixer.lock().unwrap().paused = false;
+        self.paused_by_focus = false;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.mixer.lock().unwrap().paused
+    }
+
+    pub(crate) fn focus_changed(&mut self, focused: bool) {
+        if !focused && self.pause_on_focus_lost && !self.is_paused() {
+            self.pause();
+            self.paused_by_focus = true;
+        } else if focused && self.paused_by_focus {
+            self.resume();
+        }
+    }
+
+    /// Called every frame after the camera is updated, the null backend is moved forward by `dt`
+    pub(crate) fn update(&mut self, camera: &CameraState, dt: f32) {
+        if self.use_devices
+            && (self.device_lost.load(Ordering::Relaxed)
+                || self.last_device_check.elapsed()
+                    >= self.device_check_interval.max(self.device_retry_delay))
+        {
+            self.last_device_check = Instant::now();
+            self.check_devices();
+        }
+
+        let mut mixer = self.mixer.lock().unwrap();
+        if self.listener_follows_camera {
+            mixer.listener.follow_camera(camera, dt);
+        }
+        if self.backend == AudioBackend::Null {
+            self.null_frame_accumulator += dt as f64 * mixer.sample_rate as f64;
+            let frames = self.null_frame_accumulator.floor();
+            self.null_frame_accumulator -= frames;
+            let mut samples = vec![0.0; frames as usize * mixer.channels as usize];
+            mixer.mix(&mut samples);
+        }
+    }
+
+    /// Moves the mixer to another device if the active one was unplugged or the default device changed.
+    /// If no device is left the null backend is used until one comes back
+    fn check_devices(&mut self) {
+        let default_device = self
+            .host
+            .default_output_device()
+            .and_then(|device| device.name().ok());
+        let lost = self.device_lost.load(Ordering::Relaxed);
+        let default_changed = self.follow_default_device
+            && default_device.is_some()
+            && default_device.as_ref() != Some(&self.active_output_device);
+        if !lost && !default_changed && self.backend == AudioBackend::Device {
+            return;
+        }
+        if self.backend != AudioBackend::Device && default_device.is_none() {
+            // Still no device to switch to
+            return;
+        }
+
+        if let Err(err) = self.refresh_output_devices() {
+            eprintln!("Unable to get audio output devices: {}", err);
+        }
+        let target = match default_device {
+            Some(name)
+                if self.follow_default_device
+                    || !self.output_devices.contains_key(&self.active_output_device) =>
+            {
+                name
+            }
+            _ => self.active_output_device.clone(),
+        };
+        if !self.output_devices.contains_key(&target) {
+            // Nothing to play on, sounds carry on silently until a device shows up
+            self.stream = None;
+            self.backend = AudioBackend::Null;
+            self.device_lost.store(false, Ordering::Relaxed);
+            return;
+        }
+        if target == self.active_output_device && !lost && self.backend == AudioBackend::Device {
+            return;
+        }
+        self.active_output_device = target;
+        match self.start_stream() {
+            Ok(()) => self.device_retry_delay = Duration::ZERO,
+            Err(err) => {
+                // Waits longer each time instead of trying again every frame
+                self.device_lost.store(false, Ordering::Relaxed);
+                self.device_retry_delay = (self.device_retry_delay * 2)
+                    .max(self.device_check_interval)
+                    .min(MAX_DEVICE_RETRY_DELAY);
+                eprintln!("Unable to switch audio output device: {}", err);
+            }
+        }
+    }
+
+    /// Enumerates the output devices again, use this to find devices that were plugged in
+    pub fn refresh_output_devices(&mut self) -> EngineResult {
+        self.output_devices = enumerate_output_devices(&self.host)?;
         Ok(())
     }
 
     pub fn get_output_device_names(&self) -> Vec<String> {
         self.output_devices.keys().cloned().collect()
     }
+    /// Name of the device being played on, empty if there isn't one
+    pub fn get_active_output_device(&self) -> &str {
+        &self.active_output_device
+    }
 
+    /// Moves all audio over to the device, including sounds that are already playing.
+    /// Turns off `follow_default_device`
     pub fn set_output_device(&mut self, name: String) -> EngineResult {
-        if self.output_devices.contains_key(&name) {
-            self.active_output_device = name;
-        } else {
-            return Err(EngineError::AudioError(format!(
-                "Can't find device: {}",
-                name
-            )));
-        }
+        self.switch_output_device(name)?;
+        self.follow_default_device = false;
         Ok(())
     }
+    /// Also turns `follow_default_device` back on
     pub fn set_output_device_as_default_device(&mut self) -> EngineResult {
         match self.host.default_output_device() {
             Some(device) => {
-                self.active_output_device = device.name()?;
+                self.switch_output_device(device.name()?)?;
+                self.follow_default_device = true;
             }
             _ => {
                 return Err(EngineError::AudioError("No default device".to_string()));
@@ -148,16 +520,37 @@ impl Audio {
         }
         Ok(())
     }
+
+    fn switch_output_device(&mut self, name: String) -> EngineResult {
+        if !self.output_devices.contains_key(&name) {
+            // The device might have been plugged in since the last check
+            self.refresh_output_devices()?;
+        }
+        if self.output_devices.contains_key(&name) {
+            self.active_output_device = name;
+        } else {
+            return Err(EngineError::AudioError(format!(
+                "Can't find device: {}",
+                name
+            )));
+        }
+        if self.backend != AudioBackend::Offline {
+            self.use_devices = true;
+            self.start_stream()?;
+        }
+        Ok(())
+    }
 }
 
-fn write_data<T>(output: &mut [T], channels: usize, next_sample: &mut dyn FnMut() -> f32)
-where
-    T: Sample + FromSample<f32>,
-{
-    for frame in output.chunks_mut(channels) {
-        let value: T = T::from_sample(next_sample());
-        for sample in frame.iter_mut() {
-            *sample = value;
+/// Devices that fail to give a config are skipped since they might be getting unplugged
+fn enumerate_output_devices(
+    host: &Host,
+) -> EngineResult<HashMap<String, (Device, SupportedStreamConfig)>> {
+    let mut output_devices = HashMap::new();
+    for device in host.output_devices()? {
+        if let (Ok(name), Ok(config)) = (device.name(), device.default_output_config()) {
+            output_devices.insert(name, (device, config));
         }
     }
+    Ok(output_devices)
 }
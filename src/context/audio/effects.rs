@@ -0,0 +1,418 @@
+use std::f32::consts::PI;
+
+/// Anything that can process audio, effects can be put on a single sound with `PlayOptions`
+/// or on a bus with `Audio::add_effect`.
+/// Effects run on the audio thread so they need to be `Send`
+#[allow(unused_variables)]
+pub trait Effect: Send {
+    /// Called before the effect is first used and again if the output's channels or sample rate change.
+    /// It's never called from the audio thread, so buffers should be allocated here and not in `process`
+    fn prepare(&mut self, channels: usize, sample_rate: u32) {}
+    /// `buffer` is interleaved by channel, the effect should change it in place
+    fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32);
+    /// Clears anything the effect remembers such as delay lines or filter history
+    fn reset(&mut self) {}
+    /// Seconds the effect keeps making sound after its input goes silent. A sound with the effect
+    /// keeps playing for this long after it ends so echoes and reverb aren't cut off
+    fn tail(&self) -> f32 {
+        0.0
+    }
+}
+
+/// Which frequencies a `Biquad` lets through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+/// Low-pass or high-pass filter using the coefficients from the Audio EQ Cookbook.
+/// A low-pass on everything works well for underwater sections
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    filter_type: FilterType,
+    cutoff: f32,
+    q: f32,
+    coefficients: [f32; 5],
+    // Sample rate the coefficients were worked out for
+    sample_rate: u32,
+    states: Vec<BiquadState>,
+}
+
+impl Biquad {
+    /// `q` of 0.707 has no resonance
+    pub fn new(filter_type: FilterType, cutoff: f32, q: f32) -> Self {
+        Self {
+            filter_type,
+            cutoff,
+            q,
+            coefficients: [1.0, 0.0, 0.0, 0.0, 0.0],
+            sample_rate: 0,
+            states: Vec::new(),
+        }
+    }
+    pub fn low_pass(cutoff: f32, q: f32) -> Self {
+        Self::new(FilterType::LowPass, cutoff, q)
+    }
+    pub fn high_pass(cutoff: f32, q: f32) -> Self {
+        Self::new(FilterType::HighPass, cutoff, q)
+    }
+
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        self.cutoff = cutoff;
+        // Makes the coefficients get worked out again
+        self.sample_rate = 0;
+    }
+    pub fn get_cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    fn update_coefficients(&mut self, sample_rate: u32) {
+        let cutoff = self.cutoff.clamp(1.0, sample_rate as f32 * 0.49);
+        let omega = 2.0 * PI * cutoff / sample_rate as f32;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * self.q.max(0.01));
+
+        let (b0, b1, b2) = match self.filter_type {
+            FilterType::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0),
+            FilterType::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0),
+        };
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos;
+        let a2 = 1.0 - alpha;
+        self.coefficients = [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0];
+        self.sample_rate = sample_rate;
+    }
+}
+
+impl Effect for Biquad {
+    fn prepare(&mut self, channels: usize, sample_rate: u32) {
+        self.states = vec![BiquadState::default(); channels];
+        self.update_coefficients(sample_rate);
+    }
+    fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32) {
+        if self.sample_rate != sample_rate {
+            self.update_coefficients(sample_rate);
+        }
+        let [b0, b1, b2, a1, a2] = self.coefficients;
+
+        for frame in buffer.chunks_mut(channels) {
+            for (sample, state) in frame.iter_mut().zip(self.states.iter_mut()) {
+                let x = *sample;
+                let y = b0 * x + b1 * state.x1 + b2 * state.x2 - a1 * state.y1 - a2 * state.y2;
+                state.x2 = state.x1;
+                state.x1 = x;
+                state.y2 = state.y1;
+                state.y1 = y;
+                *sample = y;
+            }
+        }
+    }
+    fn reset(&mut self) {
+        self.states.fill(BiquadState::default());
+    }
+}
+
+/// Echo that repeats the sound every `time` seconds
+#[derive(Debug, Clone)]
+pub struct Delay {
+    /// In seconds
+    pub time: f32,
+    /// How much of each echo is fed back into the next one, keep it below 1.0
+    pub feedback: f32,
+    /// 0.0 is only the original sound and 1.0 is only the echo
+    pub mix: f32,
+    lines: Vec<Vec<f32>>,
+    position: usize,
+}
+
+impl Delay {
+    pub fn new(time: f32, feedback: f32, mix: f32) -> Self {
+        Self {
+            time,
+            feedback,
+            mix,
+            lines: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Effect for Delay {
+    fn prepare(&mut self, channels: usize, sample_rate: u32) {
+        let length = ((self.time * sample_rate as f32) as usize).max(1);
+        self.lines = vec![vec![0.0; length]; channels];
+        self.position = 0;
+    }
+    fn process(&mut self, buffer: &mut [f32], channels: usize, _: u32) {
+        // Left alone until it's prepared for this many channels
+        let Some(length) = self.lines.first().map(Vec::len) else {
+            return;
+        };
+        if self.lines.len() != channels {
+            return;
+        }
+
+        for frame in buffer.chunks_mut(channels) {
+            for (sample, line) in frame.iter_mut().zip(self.lines.iter_mut()) {
+                let delayed = line[self.position];
+                line[self.position] = *sample + delayed * self.feedback;
+                *sample = *sample * (1.0 - self.mix) + delayed * self.mix;
+            }
+            self.position = (self.position + 1) % length;
+        }
+    }
+    fn reset(&mut self) {
+        self.lines.iter_mut().for_each(|line| line.fill(0.0));
+        self.position = 0;
+    }
+    /// Until the echoes are 60 dB quieter
+    fn tail(&self) -> f32 {
+        let feedback = self.feedback.abs().min(0.999);
+        let echoes = if feedback > 0.0 {
+            SILENCE.ln() / feedback.ln()
+        } else {
+            0.0
+        };
+        self.time * (echoes + 1.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Comb {
+    buffer: Vec<f32>,
+    position: usize,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            position: 0,
+            filter_store: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.buffer[self.position] = input + self.filter_store * feedback;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AllPass {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl AllPass {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            position: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.position];
+        self.buffer[self.position] = input + buffered * 0.5;
+        self.position = (self.position + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+// Freeverb tunings, made for 44100 Hz and scaled for other sample rates
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALL_PASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+const STEREO_SPREAD: usize = 23;
+// -60 dB, tails are over once they're this quiet
+const SILENCE: f32 = 0.001;
+
+/// Freeverb style reverb
+#[derive(Debug, Clone)]
+pub struct Reverb {
+    /// 0.0 to 1.0, bigger rooms ring out for longer
+    pub room_size: f32,
+    /// 0.0 to 1.0, how quickly high frequencies die out
+    pub damping: f32,
+    /// 0.0 is only the original sound and 1.0 is only the reverb
+    pub mix: f32,
+    combs: Vec<Vec<Comb>>,
+    all_passes: Vec<Vec<AllPass>>,
+}
+
+impl Reverb {
+    pub fn new(room_size: f32, damping: f32, mix: f32) -> Self {
+        Self {
+            room_size,
+            damping,
+            mix,
+            combs: Vec::new(),
+            all_passes: Vec::new(),
+        }
+    }
+
+    fn feedback(&self) -> f32 {
+        0.7 + self.room_size.clamp(0.0, 1.0) * 0.28
+    }
+}
+
+impl Effect for Reverb {
+    fn prepare(&mut self, channels: usize, sample_rate: u32) {
+        let scale = sample_rate as f32 / 44_100.0;
+        let length = |tuning: usize, channel: usize| {
+            ((tuning + channel * STEREO_SPREAD) as f32 * scale) as usize
+        };
+        self.combs = (0..channels)
+            .map(|channel| {
+                COMB_TUNINGS
+                    .iter()
+                    .map(|&tuning| Comb::new(length(tuning, channel)))
+                    .collect()
+            })
+            .collect();
+        self.all_passes = (0..channels)
+            .map(|channel| {
+                ALL_PASS_TUNINGS
+                    .iter()
+                    .map(|&tuning| AllPass::new(length(tuning, channel)))
+                    .collect()
+            })
+            .collect();
+    }
+    fn process(&mut self, buffer: &mut [f32], channels: usize, _: u32) {
+        // Left alone until it's prepared for this many channels
+        if self.combs.len() != channels {
+            return;
+        }
+        let feedback = self.feedback();
+        let damping = self.damping.clamp(0.0, 1.0) * 0.4;
+        // Keeps the combs from adding up to something really loud
+        let input_gain = 0.015;
+
+        for frame in buffer.chunks_mut(channels) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let input = *sample * input_gain;
+                let mut wet = self.combs[channel]
+                    .iter_mut()
+                    .map(|comb| comb.process(input, feedback, damping))
+                    .sum::<f32>();
+                for all_pass in self.all_passes[channel].iter_mut() {
+                    wet = all_pass.process(wet);
+                }
+                *sample = *sample * (1.0 - self.mix) + wet * self.mix * 3.0;
+            }
+        }
+    }
+    fn reset(&mut self) {
+        for comb in self.combs.iter_mut().flatten() {
+            comb.buffer.fill(0.0);
+            comb.filter_store = 0.0;
+        }
+        for all_pass in self.all_passes.iter_mut().flatten() {
+            all_pass.buffer.fill(0.0);
+        }
+    }
+    /// Until the longest comb filter has rung out
+    fn tail(&self) -> f32 {
+        let longest = (COMB_TUNINGS[COMB_TUNINGS.len() - 1] + STEREO_SPREAD) as f32 / 44_100.0;
+        longest * SILENCE.ln() / self.feedback().ln()
+    }
+}
+
+/// Turns down anything louder than `threshold`, all channels share the same gain
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    /// In decibels
+    pub threshold: f32,
+    /// How much the sound over the threshold is reduced, 4.0 means 4 dB over becomes 1 dB over.
+    /// `f32::INFINITY` makes it a limiter
+    pub ratio: f32,
+    /// Seconds it takes to start turning down the sound
+    pub attack: f32,
+    /// Seconds it takes to stop turning down the sound
+    pub release: f32,
+    /// Gain in decibels applied after compressing
+    pub makeup_gain: f32,
+    envelope: f32,
+}
+
+impl Compressor {
+    pub fn new(threshold: f32, ratio: f32, attack: f32, release: f32, makeup_gain: f32) -> Self {
+        Self {
+            threshold,
+            ratio,
+            attack,
+            release,
+            makeup_gain,
+            envelope: 0.0,
+        }
+    }
+
+    /// Stops the sound from going over `ceiling` decibels, put this on the master bus to
+    /// stop clipping when lots of sounds overlap
+    pub fn limiter(ceiling: f32) -> Self {
+        Self::new(ceiling, f32::INFINITY, 0.0, 0.05, 0.0)
+    }
+}
+
+impl Effect for Compressor {
+    fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32) {
+        let coefficient = |time: f32| {
+            if time <= 0.0 {
+                0.0
+            } else {
+                (-1.0 / (time * sample_rate as f32)).exp()
+            }
+        };
+        let attack = coefficient(self.attack);
+        let release = coefficient(self.release);
+        let makeup = db_to_gain(self.makeup_gain);
+
+        for frame in buffer.chunks_mut(channels) {
+            let peak = frame
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let coefficient = if peak > self.envelope {
+                attack
+            } else {
+                release
+            };
+            self.envelope = peak + coefficient * (self.envelope - peak);
+
+            let level = gain_to_db(self.envelope);
+            let gain = if level > self.threshold {
+                let compressed = self.threshold + (level - self.threshold) / self.ratio;
+                db_to_gain(compressed - level)
+            } else {
+                1.0
+            };
+            for sample in frame.iter_mut() {
+                *sample *= gain * makeup;
+            }
+        }
+    }
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-6).log10()
+}
@@ -0,0 +1,330 @@
+use crate::context::audio::{
+    MASTER_BUS, PlayOptions,
+    audio_play_value::{self, AudioPlayValue},
+    effects::Effect,
+    resampler::Resampler,
+    sound::Sound,
+    spatial::{self, Emitter, Listener, SpatialMix},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// A sound that is currently playing
+pub(crate) struct Voice {
+    samples: Arc<Vec<f32>>,
+    sample_rate: f64,
+    channels: usize,
+    sample_clock: f64,
+    volume: AudioPlayValue,
+    speed: AudioPlayValue,
+    emitter: Option<Emitter>,
+    bus: String,
+    effects: Vec<Box<dyn Effect>>,
+    // Seconds left of the effects ringing out after the sound ends
+    tail: f32,
+}
+
+impl Voice {
+    pub(crate) fn new(
+        sound: &Sound,
+        volume: &AudioPlayValue,
+        speed: &AudioPlayValue,
+        options: PlayOptions,
+    ) -> Self {
+        Self {
+            samples: Arc::clone(&sound.samples),
+            sample_rate: sound.header.sample_rate as f64,
+            channels: (sound.header.channels as usize).max(1),
+            sample_clock: 0.0,
+            volume: volume.clone(),
+            speed: speed.clone(),
+            emitter: options.emitter,
+            bus: options.bus.unwrap_or_else(|| MASTER_BUS.to_string()),
+            tail: options.effects.iter().map(|effect| effect.tail()).sum(),
+            effects: options.effects,
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    fn is_finished(&self) -> bool {
+        self.sample_clock >= self.frames() as f64
+    }
+
+    /// A frame of the sound mixed down to mono, silent outside of the sound
+    fn frame(&self, index: isize) -> f32 {
+        if index < 0 || index as usize >= self.frames() {
+            return 0.0;
+        }
+        let start = index as usize * self.channels;
+        // Normalize volume across channels
+        self.samples[start..start + self.channels]
+            .iter()
+            .sum::<f32>()
+            / self.channels as f32
+    }
+
+    /// Adds the voice on top of what is already in `output`
+    fn render(
+        &mut self,
+        output: &mut [f32],
+        channels: usize,
+        sample_rate: u32,
+        listener: &Listener,
+        resampler: Resampler,
+    ) {
+        // Only worked out once per buffer since the emitter is behind a lock
+        let mix = match &self.emitter {
+            Some(emitter) => spatial::spatialize(listener, &emitter.state()),
+            None => SpatialMix::CENTER,
+        };
+        let volume = audio_play_value::atomic_f64_load(&self.volume) as f32;
+        let speed = audio_play_value::atomic_f64_load(&self.speed);
+        let step = self.sample_rate / sample_rate as f64 * speed * mix.pitch as f64;
+
+        for frame in output.chunks_mut(channels) {
+            if self.is_finished() {
+                break;
+            }
+            let value = resampler.interpolate(|index| self.frame(index), self.sample_clock);
+            add_frame(frame, value * volume, &mix);
+            self.sample_clock += step;
+        }
+    }
+}
+
+/// Group of sounds that share effects and a volume, every bus ends up in the master bus
+pub(crate) struct Bus {
+    pub(crate) effects: Vec<Box<dyn Effect>>,
+    pub(crate) volume: f32,
+    buffer: Vec<f32>,
+}
+
+impl Bus {
+    pub(crate) fn new() -> Self {
+        Self {
+            effects: Vec::new(),
+            volume: 1.0,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self, len: usize) {
+        self.buffer.clear();
+        self.buffer.resize(len, 0.0);
+    }
+
+    fn prepare(&mut self, channels: usize, sample_rate: u32) {
+        self.effects
+            .iter_mut()
+            .for_each(|effect| effect.prepare(channels, sample_rate));
+    }
+
+    fn apply(&mut self, channels: usize, sample_rate: u32) {
+        for effect in self.effects.iter_mut() {
+            effect.process(&mut self.buffer, channels, sample_rate);
+        }
+        self.buffer
+            .iter_mut()
+            .for_each(|sample| *sample *= self.volume);
+    }
+}
+
+/// Mixes every playing `Voice` into one interleaved buffer, shared between `Audio` and the output stream
+pub(crate) struct Mixer {
+    voices: Vec<Voice>,
+    pub(crate) buses: HashMap<String, Bus>,
+    pub(crate) master: Bus,
+    pub(crate) listener: Listener,
+    pub(crate) resampler: Resampler,
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    /// Outputs silence without moving any voices forward
+    pub(crate) paused: bool,
+    // Sounds with their own effects are rendered here first
+    voice_buffer: Vec<f32>,
+}
+
+impl Mixer {
+    pub(crate) fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            voices: Vec::new(),
+            buses: HashMap::new(),
+            master: Bus::new(),
+            listener: Listener::default(),
+            resampler: Resampler::default(),
+            sample_rate,
+            channels: channels.max(1),
+            paused: false,
+            voice_buffer: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add_voice(&mut self, voice: Voice) {
+        self.voices.push(voice);
+    }
+
+    pub(crate) fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Gets every effect ready for the current channels and sample rate, call it when they change
+    pub(crate) fn prepare_effects(&mut self) {
+        let channels = self.channels as usize;
+        self.master.prepare(channels, self.sample_rate);
+        for bus in self.buses.values_mut() {
+            bus.prepare(channels, self.sample_rate);
+        }
+        for effect in self
+            .voices
+            .iter_mut()
+            .flat_map(|voice| voice.effects.iter_mut())
+        {
+            effect.prepare(channels, self.sample_rate);
+        }
+    }
+
+    /// Gets the bus by name, `MASTER_BUS` is always there
+    pub(crate) fn get_bus_mut(&mut self, name: &str) -> Option<&mut Bus> {
+        if name == MASTER_BUS {
+            Some(&mut self.master)
+        } else {
+            self.buses.get_mut(name)
+        }
+    }
+
+    /// Overwrites `output` with the next `output.len() / channels` frames, finished voices are dropped
+    /// once their effects have rung out
+    pub(crate) fn mix(&mut self, output: &mut [f32]) {
+        if self.paused {
+            output.fill(0.0);
+            return;
+        }
+        let channels = self.channels as usize;
+        let len = output.len();
+        let duration = (len / channels) as f32 / self.sample_rate as f32;
+        self.master.clear(len);
+        self.buses.values_mut().for_each(|bus| bus.clear(len));
+
+        for voice in self.voices.iter_mut() {
+            let target = match self.buses.get_mut(&voice.bus) {
+                Some(bus) => &mut bus.buffer,
+                None => &mut self.master.buffer,
+            };
+            if voice.effects.is_empty() {
+                voice.render(
+                    target,
+                    channels,
+                    self.sample_rate,
+                    &self.listener,
+                    self.resampler,
+                );
+            } else {
+                if voice.is_finished() {
+                    voice.tail -= duration;
+                }
+                self.voice_buffer.clear();
+                self.voice_buffer.resize(len, 0.0);
+                voice.render(
+                    &mut self.voice_buffer,
+                    channels,
+                    self.sample_rate,
+                    &self.listener,
+                    self.resampler,
+                );
+                for effect in voice.effects.iter_mut() {
+                    effect.process(&mut self.voice_buffer, channels, self.sample_rate);
+                }
+                target
+                    .iter_mut()
+                    .zip(self.voice_buffer.iter())
+                    .for_each(|(sample, voice_sample)| *sample += voice_sample);
+            }
+        }
+        self.voices
+            .retain(|voice| voice.frames() > 0 && (!voice.is_finished() || voice.tail > 0.0));
+
+        for bus in self.buses.values_mut() {
+            bus.apply(channels, self.sample_rate);
+            self.master
+                .buffer
+                .iter_mut()
+                .zip(bus.buffer.iter())
+                .for_each(|(sample, bus_sample)| *sample += bus_sample);
+        }
+        self.master.apply(channels, self.sample_rate);
+        output.copy_from_slice(&self.master.buffer);
+    }
+}
+
+fn add_frame(frame: &mut [f32], value: f32, mix: &SpatialMix) {
+    let center = value * (mix.left + mix.right) / 2.0;
+    if let [left, right, rest @ ..] = frame {
+        *left += value * mix.left;
+        *right += value * mix.right;
+        rest.iter_mut().for_each(|sample| *sample += center);
+    } else {
+        frame.iter_mut().for_each(|sample| *sample += center);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::audio::{
+        Audio, PlayOptions, audio_play_value::new_audio_play_value, sound::Sound,
+    };
+    use std::time::Duration;
+
+    const SAMPLE_RATE: u32 = 1000;
+
+    /// Mono sound of `frames` samples all at `value`, at the same rate as the output so nothing is resampled
+    fn constant_sound(audio: &Audio, frames: usize, value: f32) -> Sound {
+        let mut header = audio.wav_header();
+        header.channels = 1;
+        Sound::from_samples(header, vec![value; frames])
+    }
+
+    #[test]
+    fn silent_without_voices() {
+        let mut audio = Audio::new_offline(SAMPLE_RATE, 2);
+        let samples = audio.render(Duration::from_millis(20)).unwrap();
+        assert_eq!(samples.len(), 40);
+        assert!(samples.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn bus_volume_scales_voices() {
+        let mut audio = Audio::new_offline(SAMPLE_RATE, 2);
+        audio.add_bus("sfx");
+        audio.set_bus_volume("sfx", 0.25).unwrap();
+        let sound = constant_sound(&audio, 100, 0.5);
+        let one = new_audio_play_value(1.0);
+        audio
+            .play_with(
+                &sound,
+                &one,
+                &one,
+                PlayOptions {
+                    bus: Some("sfx".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let samples = audio.render(Duration::from_millis(10)).unwrap();
+        assert!(samples.iter().all(|sample| (sample - 0.125).abs() < 1e-6));
+    }
+
+    #[test]
+    fn voice_ends_on_its_last_frame() {
+        let mut audio = Audio::new_offline(SAMPLE_RATE, 2);
+        let sound = constant_sound(&audio, 10, 1.0);
+        let one = new_audio_play_value(1.0);
+        audio.play(&sound, &one, &one).unwrap();
+        let samples = audio.render(Duration::from_millis(16)).unwrap();
+        let (playing, after) = samples.split_at(10 * 2);
+        assert!(playing.iter().all(|sample| (sample - 1.0).abs() < 1e-6));
+        assert!(after.iter().all(|sample| *sample == 0.0));
+        assert_eq!(audio.mixer.lock().unwrap().voice_count(), 0);
+    }
+}
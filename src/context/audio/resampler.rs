@@ -0,0 +1,58 @@
+use std::f64::consts::PI;
+
+/// How sounds are stretched to fit the sample rate of the output and the speed they are played at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Resampler {
+    /// Fastest but muffles high frequencies and adds some noise
+    Linear,
+    /// Catmull-Rom spline through the four closest samples
+    #[default]
+    Cubic,
+    /// Windowed sinc over the eight closest samples, best quality but the slowest
+    Sinc,
+}
+
+const SINC_RADIUS: isize = 4;
+
+impl Resampler {
+    /// `sample` gets a sample by its index and `position` is a fractional index between samples
+    pub fn interpolate(&self, sample: impl Fn(isize) -> f32, position: f64) -> f32 {
+        let index = position.floor() as isize;
+        let frac = position - position.floor();
+        match self {
+            Resampler::Linear => {
+                ((1.0 - frac) * sample(index) as f64 + frac * sample(index + 1) as f64) as f32
+            }
+            Resampler::Cubic => {
+                let p0 = sample(index - 1) as f64;
+                let p1 = sample(index) as f64;
+                let p2 = sample(index + 1) as f64;
+                let p3 = sample(index + 2) as f64;
+                let a = -0.5 * p0 + 1.5 * p1 - 1.5 * p2 + 0.5 * p3;
+                let b = p0 - 2.5 * p1 + 2.0 * p2 - 0.5 * p3;
+                let c = -0.5 * p0 + 0.5 * p2;
+                (((a * frac + b) * frac + c) * frac + p1) as f32
+            }
+            Resampler::Sinc => {
+                let mut output = 0.0;
+                for offset in (1 - SINC_RADIUS)..=SINC_RADIUS {
+                    let x = offset as f64 - frac;
+                    output += sample(index + offset) as f64 * lanczos(x);
+                }
+                output as f32
+            }
+        }
+    }
+}
+
+fn lanczos(x: f64) -> f64 {
+    if x.abs() < f64::EPSILON {
+        return 1.0;
+    }
+    let radius = SINC_RADIUS as f64;
+    if x.abs() >= radius {
+        return 0.0;
+    }
+    let pi_x = PI * x;
+    radius * pi_x.sin() * (pi_x / radius).sin() / (pi_x * pi_x)
+}
@@ -17,4 +17,12 @@ impl Sound {
             samples: Arc::new(samples),
         })
     }
+
+    /// Samples are interleaved by channel, such as the output of `Audio::render` with `Audio::wav_header`
+    pub fn from_samples(header: WavHeader, samples: Vec<f32>) -> Self {
+        Self {
+            header,
+            samples: Arc::new(samples),
+        }
+    }
 }
@@ -0,0 +1,230 @@
+use crate::context::camera::CameraState;
+use std::{
+    f32::consts::{FRAC_PI_4, SQRT_2},
+    sync::{Arc, Mutex},
+};
+use vek::Vec3;
+
+/// How the volume of an `Emitter` falls off over distance, same models as OpenAL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rolloff {
+    /// Volume doesn't change with distance
+    None,
+    /// Fades out linearly between `reference_distance` and `max_distance`
+    Linear,
+    /// `reference / (reference + rolloff_factor * (distance - reference))`, closest to real life
+    Inverse,
+    /// `(distance / reference) ^ -rolloff_factor`
+    Exponential,
+}
+
+/// Distance attenuation settings for an `Emitter`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub rolloff: Rolloff,
+    /// Distance where the sound is at full volume
+    pub reference_distance: f32,
+    /// Past this distance the sound doesn't get any quieter
+    pub max_distance: f32,
+    /// How fast the volume drops, 1.0 is the default for every model
+    pub rolloff_factor: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            rolloff: Rolloff::Inverse,
+            reference_distance: 1.0,
+            max_distance: 100.0,
+            rolloff_factor: 1.0,
+        }
+    }
+}
+
+impl Attenuation {
+    /// Gets the gain from 0.0 to 1.0 for something `distance` away from the listener
+    pub fn gain(&self, distance: f32) -> f32 {
+        let reference = self.reference_distance.max(f32::EPSILON);
+        let distance = distance.clamp(reference, self.max_distance.max(reference));
+        let gain = match self.rolloff {
+            Rolloff::None => 1.0,
+            Rolloff::Linear => {
+                let range = self.max_distance - reference;
+                if range <= 0.0 {
+                    1.0
+                } else {
+                    1.0 - self.rolloff_factor * (distance - reference) / range
+                }
+            }
+            Rolloff::Inverse => {
+                reference / (reference + self.rolloff_factor * (distance - reference))
+            }
+            Rolloff::Exponential => (distance / reference).powf(-self.rolloff_factor),
+        };
+        gain.clamp(0.0, 1.0)
+    }
+}
+
+/// Where sounds are heard from, by default it follows `ctx.camera` every frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    pub position: Vec3<f32>,
+    pub forward: Vec3<f32>,
+    pub up: Vec3<f32>,
+    /// Only used for the Doppler effect
+    pub velocity: Vec3<f32>,
+    /// Scales the Doppler effect, 0.0 turns it off
+    pub doppler_factor: f32,
+    /// In world units per second, defaults to 343.3 (meters per second in air)
+    pub speed_of_sound: f32,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            position: Vec3::zero(),
+            forward: Vec3::new(0.0, 0.0, -1.0),
+            up: Vec3::unit_y(),
+            velocity: Vec3::zero(),
+            doppler_factor: 1.0,
+            speed_of_sound: 343.3,
+        }
+    }
+}
+
+impl Listener {
+    /// Moves the listener to the camera, velocity is worked out from how far it moved in `dt`
+    pub fn follow_camera(&mut self, camera: &CameraState, dt: f32) {
+        let (forward, _, up) = camera.get_directions();
+        if dt > 0.0 {
+            self.velocity = (camera.position - self.position) / dt;
+        }
+        self.position = camera.position;
+        self.forward = forward;
+        self.up = up;
+    }
+
+    /// Points to the right of the listener, used for panning
+    pub fn right(&self) -> Vec3<f32> {
+        self.forward.cross(self.up).normalized()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EmitterState {
+    pub(crate) position: Vec3<f32>,
+    pub(crate) velocity: Vec3<f32>,
+    pub(crate) attenuation: Attenuation,
+}
+
+/// A point in the world that sounds can be played from using `Audio::play_spatial`.
+/// Clones share the same state so moving one will move the sound that is already playing
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    state: Arc<Mutex<EmitterState>>,
+}
+
+impl Emitter {
+    pub fn new(position: Vec3<f32>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(EmitterState {
+                position,
+                velocity: Vec3::zero(),
+                attenuation: Attenuation::default(),
+            })),
+        }
+    }
+
+    pub fn with_attenuation(self, attenuation: Attenuation) -> Self {
+        self.set_attenuation(attenuation);
+        self
+    }
+
+    pub fn position(&self) -> Vec3<f32> {
+        self.state().position
+    }
+    pub fn set_position(&self, position: Vec3<f32>) {
+        self.state.lock().unwrap().position = position;
+    }
+
+    /// Only used for the Doppler effect
+    pub fn velocity(&self) -> Vec3<f32> {
+        self.state().velocity
+    }
+    pub fn set_velocity(&self, velocity: Vec3<f32>) {
+        self.state.lock().unwrap().velocity = velocity;
+    }
+
+    pub fn attenuation(&self) -> Attenuation {
+        self.state().attenuation
+    }
+    pub fn set_attenuation(&self, attenuation: Attenuation) {
+        self.state.lock().unwrap().attenuation = attenuation;
+    }
+
+    pub(crate) fn state(&self) -> EmitterState {
+        *self.state.lock().unwrap()
+    }
+}
+
+/// Gain of the left and right channel and the pitch multiplier from the Doppler effect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SpatialMix {
+    pub(crate) left: f32,
+    pub(crate) right: f32,
+    pub(crate) pitch: f32,
+}
+
+impl SpatialMix {
+    /// Used for sounds without an `Emitter`
+    pub(crate) const CENTER: Self = Self {
+        left: 1.0,
+        right: 1.0,
+        pitch: 1.0,
+    };
+}
+
+pub(crate) fn spatialize(listener: &Listener, emitter: &EmitterState) -> SpatialMix {
+    let offset = emitter.position - listener.position;
+    let distance = offset.magnitude();
+    let gain = emitter.attenuation.gain(distance);
+
+    let pan = if distance > f32::EPSILON {
+        (offset.dot(listener.right()) / distance).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+    // Equal power panning so the sound doesn't get quieter in the middle. Scaled so the middle is 1.0
+    // on both sides like `SpatialMix::CENTER`, otherwise spatial sounds are 3 dB quieter
+    let angle = (pan + 1.0) * FRAC_PI_4;
+
+    SpatialMix {
+        left: angle.cos() * SQRT_2 * gain,
+        right: angle.sin() * SQRT_2 * gain,
+        pitch: doppler_pitch(listener, emitter, offset, distance),
+    }
+}
+
+fn doppler_pitch(
+    listener: &Listener,
+    emitter: &EmitterState,
+    offset: Vec3<f32>,
+    distance: f32,
+) -> f32 {
+    if listener.doppler_factor <= 0.0 || distance <= f32::EPSILON {
+        return 1.0;
+    }
+    // Direction from the emitter to the listener
+    let direction = -offset / distance;
+    let max_speed = listener.speed_of_sound / listener.doppler_factor;
+    let listener_speed = listener.velocity.dot(direction).min(max_speed);
+    let emitter_speed = emitter.velocity.dot(direction).min(max_speed);
+
+    let pitch = (listener.speed_of_sound - listener.doppler_factor * listener_speed)
+        / (listener.speed_of_sound - listener.doppler_factor * emitter_speed);
+    if pitch.is_finite() {
+        pitch.max(0.0)
+    } else {
+        1.0
+    }
+}
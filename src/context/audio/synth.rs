@@ -0,0 +1,358 @@
+// Based on the parameters from sfxr by DrPetter, made for generating retro sound effects at runtime
+
+use crate::{context::audio::sound::Sound, rng::Rng};
+use std::{f32::consts::PI, ops::Range};
+
+/// Shape of the sound wave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    /// Uses `SynthParams::duty` for the width of the pulse
+    Square,
+    Saw,
+    Triangle,
+    Noise,
+}
+
+/// Attack, decay, sustain, release envelope, times are in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    /// Time to fade in to full volume
+    pub attack: f32,
+    /// Time to fall from full volume to `sustain_level`
+    pub decay: f32,
+    /// 0.0 to 1.0
+    pub sustain_level: f32,
+    /// How long `sustain_level` is held for
+    pub sustain_time: f32,
+    /// Time to fade out from `sustain_level`
+    pub release: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self {
+            attack: 0.0,
+            decay: 0.05,
+            sustain_level: 0.6,
+            sustain_time: 0.15,
+            release: 0.2,
+        }
+    }
+}
+
+impl Envelope {
+    /// How long the sound lasts in seconds
+    pub fn duration(&self) -> f32 {
+        self.attack + self.decay + self.sustain_time + self.release
+    }
+
+    /// Volume from 0.0 to 1.0 at `time` seconds in
+    pub fn amplitude(&self, time: f32) -> f32 {
+        let mut time = time;
+        if time < self.attack {
+            return time / self.attack;
+        }
+        time -= self.attack;
+        if time < self.decay {
+            return 1.0 - (1.0 - self.sustain_level) * time / self.decay;
+        }
+        time -= self.decay;
+        if time < self.sustain_time {
+            return self.sustain_level;
+        }
+        time -= self.sustain_time;
+        if time < self.release {
+            return self.sustain_level * (1.0 - time / self.release);
+        }
+        0.0
+    }
+}
+
+/// Everything needed to generate a sound effect, use one of the presets like `SynthParams::pickup`
+/// with a seeded `Rng` to get the same sound every time
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthParams {
+    pub waveform: Waveform,
+    pub envelope: Envelope,
+    /// Starting frequency in Hz
+    pub frequency: f32,
+    /// The frequency never goes below this
+    pub min_frequency: f32,
+    /// Octaves per second, negative values slide the pitch down
+    pub frequency_slide: f32,
+    /// Octaves per second per second, changes `frequency_slide` over time
+    pub frequency_slide_acceleration: f32,
+    /// Frequency is multiplied by this after `arpeggio_time`, 1.0 turns it off
+    pub arpeggio_multiplier: f32,
+    /// Seconds until the arpeggio jump
+    pub arpeggio_time: f32,
+    /// Fraction of the frequency the vibrato moves by, 0.0 turns it off
+    pub vibrato_depth: f32,
+    /// In Hz
+    pub vibrato_speed: f32,
+    /// Width of the square wave from 0.0 to 1.0, 0.5 is a normal square wave
+    pub duty: f32,
+    /// Change in `duty` per second
+    pub duty_sweep: f32,
+    pub volume: f32,
+    pub sample_rate: u32,
+}
+
+impl Default for SynthParams {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            envelope: Envelope::default(),
+            frequency: 440.0,
+            min_frequency: 20.0,
+            frequency_slide: 0.0,
+            frequency_slide_acceleration: 0.0,
+            arpeggio_multiplier: 1.0,
+            arpeggio_time: 0.0,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            duty: 0.5,
+            duty_sweep: 0.0,
+            volume: 0.5,
+            sample_rate: 44_100,
+        }
+    }
+}
+
+impl SynthParams {
+    /// Coin pickup, a short square blip with a jump up in pitch
+    pub fn pickup(rng: &mut Rng) -> Self {
+        let mut params = Self {
+            waveform: Waveform::Square,
+            frequency: range(rng, 700.0..1400.0),
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.02,
+                sustain_level: 0.8,
+                sustain_time: range(rng, 0.02..0.1),
+                release: range(rng, 0.1..0.3),
+            },
+            ..Default::default()
+        };
+        if rng.bool() {
+            params.arpeggio_multiplier = range(rng, 1.3..1.8);
+            params.arpeggio_time = range(rng, 0.04..0.1);
+        }
+        params
+    }
+
+    /// Laser or shooting sound that slides down in pitch
+    pub fn laser(rng: &mut Rng) -> Self {
+        Self {
+            waveform: random_waveform(rng, &[Waveform::Square, Waveform::Saw, Waveform::Sine]),
+            frequency: range(rng, 500.0..2000.0),
+            min_frequency: range(rng, 50.0..200.0),
+            frequency_slide: -range(rng, 4.0..12.0),
+            duty: range(rng, 0.2..0.5),
+            duty_sweep: range(rng, -0.5..0.5),
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.0,
+                sustain_level: 1.0,
+                sustain_time: range(rng, 0.05..0.2),
+                release: range(rng, 0.05..0.25),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Noisy explosion that slowly falls in pitch
+    pub fn explosion(rng: &mut Rng) -> Self {
+        Self {
+            waveform: Waveform::Noise,
+            frequency: range(rng, 100.0..2000.0),
+            frequency_slide: -range(rng, 0.0..3.0),
+            vibrato_depth: if rng.bool() {
+                range(rng, 0.0..0.3)
+            } else {
+                0.0
+            },
+            vibrato_speed: range(rng, 5.0..20.0),
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.05,
+                sustain_level: range(rng, 0.6..1.0),
+                sustain_time: range(rng, 0.1..0.4),
+                release: range(rng, 0.3..0.8),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Power up that slides up in pitch
+    pub fn powerup(rng: &mut Rng) -> Self {
+        Self {
+            waveform: random_waveform(rng, &[Waveform::Square, Waveform::Saw, Waveform::Triangle]),
+            frequency: range(rng, 200.0..600.0),
+            frequency_slide: range(rng, 1.0..4.0),
+            vibrato_depth: if rng.bool() {
+                range(rng, 0.05..0.2)
+            } else {
+                0.0
+            },
+            vibrato_speed: range(rng, 8.0..20.0),
+            duty: range(rng, 0.2..0.5),
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.0,
+                sustain_level: 0.8,
+                sustain_time: range(rng, 0.1..0.3),
+                release: range(rng, 0.1..0.4),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Short hit or hurt sound
+    pub fn hit(rng: &mut Rng) -> Self {
+        Self {
+            waveform: random_waveform(rng, &[Waveform::Square, Waveform::Saw, Waveform::Noise]),
+            frequency: range(rng, 200.0..1000.0),
+            frequency_slide: -range(rng, 4.0..10.0),
+            min_frequency: 40.0,
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.0,
+                sustain_level: 1.0,
+                sustain_time: range(rng, 0.01..0.05),
+                release: range(rng, 0.05..0.2),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Jump sound that slides up in pitch
+    pub fn jump(rng: &mut Rng) -> Self {
+        Self {
+            waveform: Waveform::Square,
+            frequency: range(rng, 250.0..500.0),
+            frequency_slide: range(rng, 2.0..5.0),
+            duty: range(rng, 0.2..0.5),
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.0,
+                sustain_level: 0.8,
+                sustain_time: range(rng, 0.05..0.15),
+                release: range(rng, 0.05..0.2),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Menu blip
+    pub fn blip(rng: &mut Rng) -> Self {
+        Self {
+            waveform: random_waveform(rng, &[Waveform::Square, Waveform::Sine]),
+            frequency: range(rng, 400.0..1200.0),
+            duty: range(rng, 0.2..0.5),
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.0,
+                sustain_level: 0.8,
+                sustain_time: range(rng, 0.03..0.08),
+                release: 0.02,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Randomly changes every value by up to `amount`, 0.1 gives a slight variation of the same sound
+    pub fn mutate(&self, rng: &mut Rng, amount: f32) -> Self {
+        let mut jitter = |value: f32| value * (1.0 + range(rng, -amount..amount));
+        Self {
+            frequency: jitter(self.frequency),
+            frequency_slide: jitter(self.frequency_slide),
+            frequency_slide_acceleration: jitter(self.frequency_slide_acceleration),
+            arpeggio_multiplier: jitter(self.arpeggio_multiplier),
+            arpeggio_time: jitter(self.arpeggio_time),
+            vibrato_depth: jitter(self.vibrato_depth),
+            vibrato_speed: jitter(self.vibrato_speed),
+            duty: jitter(self.duty).clamp(0.0, 1.0),
+            duty_sweep: jitter(self.duty_sweep),
+            envelope: Envelope {
+                attack: jitter(self.envelope.attack),
+                decay: jitter(self.envelope.decay),
+                sustain_level: jitter(self.envelope.sustain_level).clamp(0.0, 1.0),
+                sustain_time: jitter(self.envelope.sustain_time),
+                release: jitter(self.envelope.release),
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Generates the samples, `rng` is only used for `Waveform::Noise`
+    pub fn generate_samples(&self, rng: &mut Rng) -> Vec<f32> {
+        let sample_rate = self.sample_rate as f32;
+        let length = (self.envelope.duration() * sample_rate) as usize;
+        let mut samples = Vec::with_capacity(length);
+
+        let mut noise = [0.0f32; 32];
+        noise.iter_mut().for_each(|n| *n = rng.f32() * 2.0 - 1.0);
+
+        let mut phase = 0.0f32;
+        let mut octaves = 0.0f32;
+        let mut slide = self.frequency_slide;
+        for i in 0..length {
+            let time = i as f32 / sample_rate;
+
+            slide += self.frequency_slide_acceleration / sample_rate;
+            octaves += slide / sample_rate;
+            let mut frequency = self.frequency * 2.0f32.powf(octaves);
+            if self.arpeggio_time > 0.0 && time >= self.arpeggio_time {
+                frequency *= self.arpeggio_multiplier;
+            }
+            if self.vibrato_depth > 0.0 {
+                frequency *=
+                    1.0 + (2.0 * PI * self.vibrato_speed * time).sin() * self.vibrato_depth;
+            }
+            let frequency = frequency.max(self.min_frequency);
+
+            phase += frequency / sample_rate;
+            if phase >= 1.0 {
+                phase = phase.fract();
+                if self.waveform == Waveform::Noise {
+                    noise.iter_mut().for_each(|n| *n = rng.f32() * 2.0 - 1.0);
+                }
+            }
+
+            let duty = (self.duty + self.duty_sweep * time).clamp(0.05, 0.95);
+            let value = match self.waveform {
+                Waveform::Sine => (2.0 * PI * phase).sin(),
+                Waveform::Square => {
+                    if phase < duty {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Waveform::Saw => 1.0 - 2.0 * phase,
+                Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                Waveform::Noise => noise[(phase * noise.len() as f32) as usize % noise.len()],
+            };
+            samples.push(value * self.envelope.amplitude(time) * self.volume);
+        }
+        samples
+    }
+
+    /// Generates a mono `Sound` that can be played with `Audio::play`
+    pub fn generate(&self, rng: &mut Rng) -> Sound {
+        Sound::from_samples(
+            wav_io::new_header(self.sample_rate, 32, true, true),
+            self.generate_samples(rng),
+        )
+    }
+}
+
+fn random_waveform(rng: &mut Rng, waveforms: &[Waveform]) -> Waveform {
+    waveforms[rng.usize(..waveforms.len())]
+}
+
+fn range(rng: &mut Rng, range: Range<f32>) -> f32 {
+    range.start + rng.f32() * (range.end - range.start)
+}
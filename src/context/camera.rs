@@ -63,7 +63,8 @@ impl CameraState {
         );
     }
 
-    fn get_directions(&self) -> (Vec3<f32>, Vec3<f32>, Vec3<f32>) {
+    /// Forward, left, and up directions of the camera
+    pub(crate) fn get_directions(&self) -> (Vec3<f32>, Vec3<f32>, Vec3<f32>) {
         let f = (self.target - self.position).normalized();
         let r = self.up.cross(f).normalized();
         let u = f.cross(r).normalized();
@@ -94,6 +95,11 @@ impl CameraState {
             ctx.camera.position.y -= cam_speed * dt;
         }
 
+        Self::free_look(dt, ctx, cam_rot_speed);
+    }
+
+    /// The turning part of `free_cam`, arrow keys and the mouse, hold E to free the mouse
+    pub fn free_look(dt: f32, ctx: &mut Context, cam_rot_speed: f32) {
         let cam_rot = cam_rot_speed * dt;
         if ctx.input.is_key_pressed(KeyCode::ArrowUp)
             && ctx.camera.pitch <= PI / 2.0 - 0.01 - cam_rot
@@ -114,7 +120,9 @@ impl CameraState {
 
         // Handle mouse movement for camera rotation
         let mouse_look = !ctx.input.is_key_pressed(KeyCode::KeyE);
-        ctx.input.lock_mouse_near_center(&ctx.window, mouse_look);
+        if let Some(window) = &ctx.window {
+            ctx.input.lock_mouse_near_center(window, mouse_look);
+        }
 
         if !mouse_look {
             return;
@@ -0,0 +1,306 @@
+use crate::{
+    context::{frame_pacing::Vsync, window::WindowConfig},
+    error::{EngineError, EngineResult},
+};
+use glium::{
+    Display, Frame,
+    backend::{Context as GliumContext, Facade},
+    glutin::{
+        context::ContextAttributesBuilder,
+        display::{self, GetGlDisplay},
+        prelude::*,
+        surface::{Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface},
+    },
+    winit::{event_loop::EventLoop, raw_window_handle::HasWindowHandle, window::Window},
+};
+use glutin_winit::DisplayBuilder;
+use std::{cmp::Reverse, num::NonZeroU32, ops::Deref, rc::Rc};
+
+/// The OpenGL context the engine draws with, either from a window or from `init_headless`.
+/// Anything that takes a glium `Facade` can be given `&ctx.display`
+pub enum DisplayWrapper {
+    Window(Display<WindowSurface>),
+    /// Has no default framebuffer, everything is drawn into a `RenderTarget`
+    Headless(Rc<GliumContext>),
+}
+
+impl DisplayWrapper {
+    /// Gets the glium `Display` if there is a window, needed for things like imgui
+    pub fn get_window_display(&self) -> Option<&Display<WindowSurface>> {
+        match self {
+            Self::Window(display) => Some(display),
+            Self::Headless(_) => None,
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self, Self::Headless(_))
+    }
+
+    /// Starts drawing to the window, fails when headless
+    pub fn draw(&self) -> EngineResult<Frame> {
+        match self {
+            Self::Window(display) => Ok(display.draw()),
+            Self::Headless(_) => Err(EngineError::GliumError(
+                "A headless display has no window to draw to".to_string(),
+            )),
+        }
+    }
+
+    /// Does nothing when headless, the `RenderTarget` has its own size
+    pub fn resize(&self, new_size: (u32, u32)) {
+        if let Self::Window(display) = self {
+            display.resize(new_size);
+        }
+    }
+}
+
+impl Facade for DisplayWrapper {
+    fn get_context(&self) -> &Rc<GliumContext> {
+        match self {
+            Self::Window(display) => display.get_context(),
+            Self::Headless(context) => context,
+        }
+    }
+}
+
+impl Deref for DisplayWrapper {
+    type Target = GliumContext;
+    fn deref(&self) -> &GliumContext {
+        self.get_context()
+    }
+}
+
+/// Same as glium's `SimpleWindowBuilder` but sets the swap interval before glium takes the surface.
+/// Also gives back the vsync that was used since `Vsync::Adaptive` might not be supported
+pub(crate) fn create_window(
+    event_loop: &EventLoop<()>,
+    window_config: &WindowConfig,
+) -> EngineResult<(Window, Display<WindowSurface>, Vsync)> {
+    let samples = window_config.multisampling;
+    let (window, config) = DisplayBuilder::new()
+        .with_window_attributes(Some(window_config.window_attributes()?))
+        .build(
+            event_loop,
+            window_config.config_template(),
+            |mut configs| {
+                // Takes the config closest to the samples asked for, the one with more on a tie
+                if samples > 0 {
+                    configs
+                        .min_by_key(|config| {
+                            (
+                                config.num_samples().abs_diff(samples),
+                                Reverse(config.num_samples()),
+                            )
+                        })
+                        .unwrap()
+                } else {
+                    configs.next().unwrap()
+                }
+            },
+        )
+        .map_err(|err| EngineError::GliumError(err.to_string()))?;
+    let window =
+        window.ok_or_else(|| EngineError::GliumError("Unable to create a window".to_string()))?;
+
+    let (width, height): (u32, u32) = window.inner_size().into();
+    let window_handle = window
+        .window_handle()
+        .map_err(|err| EngineError::GliumError(err.to_string()))?
+        .as_raw();
+    let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new()
+        .with_srgb(window_config.srgb)
+        .build(
+            window_handle,
+            NonZeroU32::new(width).unwrap_or(NonZeroU32::MIN),
+            NonZeroU32::new(height).unwrap_or(NonZeroU32::MIN),
+        );
+    let surface = unsafe {
+        config
+            .display()
+            .create_window_surface(&config, &surface_attributes)?
+    };
+    let context_attributes = ContextAttributesBuilder::new().build(Some(window_handle));
+    let context = unsafe {
+        config
+            .display()
+            .create_context(&config, &context_attributes)?
+    }
+    .make_current(&surface)?;
+
+    let mut vsync = window_config.vsync;
+    if vsync == Vsync::Adaptive && !set_adaptive_swap_interval(&config.display(), &surface) {
+        vsync = Vsync::On;
+    }
+    let swap_interval = match vsync {
+        Vsync::On => Some(SwapInterval::Wait(NonZeroU32::MIN)),
+        Vsync::Off => Some(SwapInterval::DontWait),
+        Vsync::Adaptive => None,
+    };
+    if let Some(swap_interval) = swap_interval
+        && let Err(err) = surface.set_swap_interval(&context, swap_interval)
+    {
+        eprintln!("Unable to set vsync: {}", err);
+    }
+
+    let display = Display::from_context_surface(context, surface)?;
+    Ok((window, display, vsync))
+}
+
+/// Sets the swap interval to -1, which only waits for the monitor when the frame is on time.
+/// Glutin only takes positive intervals so the extension is called directly, `false` if it isn't there
+#[allow(unused_variables)]
+fn set_adaptive_swap_interval(
+    gl_display: &display::Display,
+    surface: &Surface<WindowSurface>,
+) -> bool {
+    match gl_display {
+        #[cfg(all(
+            unix,
+            not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+        ))]
+        display::Display::Glx(glx_display) => {
+            use glium::glutin::{
+                display::{AsRawDisplay, GetDisplayExtensions, RawDisplay},
+                surface::{AsRawSurface, RawSurface},
+            };
+            use std::{
+                ffi::{c_int, c_void},
+                mem,
+            };
+
+            let (RawDisplay::Glx(raw_display), RawSurface::Glx(drawable)) =
+                (glx_display.raw_display(), surface.raw_surface())
+            else {
+                return false;
+            };
+            let address = glx_display.get_proc_address(c"glXSwapIntervalEXT");
+            if !glx_display
+                .extensions()
+                .contains("GLX_EXT_swap_control_tear")
+                || address.is_null()
+            {
+                return false;
+            }
+            let swap_interval: unsafe extern "C" fn(*const c_void, u64, c_int) =
+                unsafe { mem::transmute(address) };
+            unsafe { swap_interval(raw_display, drawable, -1) };
+            true
+        }
+        #[cfg(windows)]
+        display::Display::Wgl(wgl_display) => {
+            use glium::glutin::display::GetDisplayExtensions;
+            use std::{ffi::c_int, mem};
+
+            let address = wgl_display.get_proc_address(c"wglSwapIntervalEXT");
+            if !wgl_display
+                .extensions()
+                .contains("WGL_EXT_swap_control_tear")
+                || address.is_null()
+            {
+                return false;
+            }
+            let swap_interval: unsafe extern "system" fn(c_int) -> c_int =
+                unsafe { mem::transmute(address) };
+            unsafe { swap_interval(-1) != 0 }
+        }
+        _ => false,
+    }
+}
+
+/// Offscreen OpenGL context using EGL without any surface, on machines with no GPU
+/// Mesa's software rasterizer (llvmpipe) is picked up as one of the EGL devices
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub(crate) mod headless {
+    use crate::error::{EngineError, EngineResult};
+    use glium::{
+        SwapBuffersError,
+        backend::{Backend, Context as GliumContext},
+        debug::DebugCallbackBehavior,
+        glutin::{
+            api::egl::{context::PossiblyCurrentContext, device::Device, display::Display},
+            config::{ConfigSurfaceTypes, ConfigTemplateBuilder},
+            context::ContextAttributesBuilder,
+            prelude::*,
+        },
+    };
+    use std::{cell::Cell, ffi::CString, os::raw::c_void, rc::Rc};
+
+    struct HeadlessBackend {
+        display: Display,
+        context: PossiblyCurrentContext,
+        dimensions: Cell<(u32, u32)>,
+    }
+
+    unsafe impl Backend for HeadlessBackend {
+        fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+            Ok(())
+        }
+        unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+            match CString::new(symbol) {
+                Ok(symbol) => self.display.get_proc_address(&symbol),
+                Err(_) => std::ptr::null(),
+            }
+        }
+        fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+            self.dimensions.get()
+        }
+        fn resize(&self, new_size: (u32, u32)) {
+            self.dimensions.set(new_size);
+        }
+        fn is_current(&self) -> bool {
+            self.context.is_current()
+        }
+        unsafe fn make_current(&self) {
+            self.context.make_current_surfaceless().unwrap();
+        }
+    }
+
+    /// Tries every EGL device until one can make a surfaceless context
+    pub(crate) fn create_context(size: (u32, u32)) -> EngineResult<Rc<GliumContext>> {
+        let mut last_error = EngineError::GliumError("No EGL devices were found".to_string());
+        for device in Device::query_devices()? {
+            match create_backend(&device, size) {
+                Ok(backend) => {
+                    return Ok(unsafe {
+                        GliumContext::new(backend, true, DebugCallbackBehavior::default())?
+                    });
+                }
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn create_backend(device: &Device, size: (u32, u32)) -> EngineResult<HeadlessBackend> {
+        let display = unsafe { Display::with_device(device, None)? };
+        let template = ConfigTemplateBuilder::new()
+            .with_surface_type(ConfigSurfaceTypes::empty())
+            .build();
+        let config = unsafe { display.find_configs(template)? }
+            .next()
+            .ok_or_else(|| EngineError::GliumError("No EGL config was found".to_string()))?;
+        let context = unsafe {
+            display.create_context(&config, &ContextAttributesBuilder::new().build(None))?
+        }
+        .make_current_surfaceless()?;
+        Ok(HeadlessBackend {
+            display,
+            context,
+            dimensions: Cell::new(size),
+        })
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub(crate) mod headless {
+    use crate::error::{EngineError, EngineResult};
+    use glium::backend::Context as GliumContext;
+    use std::rc::Rc;
+
+    pub(crate) fn create_context(_: (u32, u32)) -> EngineResult<Rc<GliumContext>> {
+        Err(EngineError::GliumError(
+            "Headless rendering needs EGL which isn't available on this platform".to_string(),
+        ))
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{error::EngineResult, renderer::texture::Texture};
-use glium::{Display, glutin::surface::WindowSurface};
+use glium::backend::Facade;
 use image::{GrayImage, ImageBuffer, Rgb};
 use std::collections::HashMap;
 
@@ -7,13 +7,16 @@ pub struct Font {
     pub atlas: Texture,
     pub glyphs: HashMap<char, GlyphData>,
     pub font_size: f32,
+    font: fontdue::Font,
+    scale_factor: f32,
 }
 
 impl Font {
     pub fn new(
-        display: &Display<WindowSurface>,
+        display: &impl Facade,
         font_data: &[u8],
         font_size: f32,
+        scale_factor: f32,
     ) -> EngineResult<Self> {
         let font = fontdue::Font::from_bytes(
             font_data,
@@ -22,82 +25,106 @@ impl Font {
                 ..Default::default()
             },
         )?;
+        let (atlas, glyphs) = rasterize(display, &font, font_size, scale_factor)?;
+        Ok(Self {
+            atlas,
+            glyphs,
+            font_size,
+            font,
+            scale_factor,
+        })
+    }
 
-        let charset: Vec<char> = (32..127).map(|c| c as u8 as char).collect();
-        let mut glyphs = HashMap::new();
+    /// Rasterizes the glyphs again at the new DPI, glyph sizes stay the same so only the sharpness changes.
+    /// `Text`s made before this need `Text::rebuild` since the atlas moved around
+    pub fn set_scale_factor(&mut self, display: &impl Facade, scale_factor: f32) -> EngineResult {
+        if scale_factor == self.scale_factor {
+            return Ok(());
+        }
+        (self.atlas, self.glyphs) = rasterize(display, &self.font, self.font_size, scale_factor)?;
+        self.scale_factor = scale_factor;
+        Ok(())
+    }
+}
 
-        let atlas_width = font_size * 24.0;
-        let atlas_height = font_size * 6.0;
-        let mut atlas = GrayImage::new(atlas_width as u32, atlas_height as u32);
+/// Glyph sizes are divided by `scale_factor` so they are the same no matter the DPI
+fn rasterize(
+    display: &impl Facade,
+    font: &fontdue::Font,
+    font_size: f32,
+    scale_factor: f32,
+) -> EngineResult<(Texture, HashMap<char, GlyphData>)> {
+    let scale_factor = scale_factor.max(0.1);
+    let font_size = font_size * scale_factor;
+    let charset: Vec<char> = (32..127).map(|c| c as u8 as char).collect();
+    let mut glyphs = HashMap::new();
 
-        let atlas_padding = (font_size / 8.0) as usize;
-        let mut x_offset: usize = atlas_padding;
-        let mut y_offset: usize = atlas_padding;
-        let mut max_row_height = 0;
+    let atlas_width = font_size * 24.0;
+    let atlas_height = font_size * 6.0;
+    let mut atlas = GrayImage::new(atlas_width as u32, atlas_height as u32);
 
-        for &c in &charset {
-            let (metrics, bitmap) = font.rasterize(c, font_size);
-            if metrics.width == 0 || metrics.height == 0 {
-                continue;
-            }
+    let atlas_padding = (font_size / 8.0) as usize;
+    let mut x_offset: usize = atlas_padding;
+    let mut y_offset: usize = atlas_padding;
+    let mut max_row_height = 0;
 
-            if x_offset + metrics.width + atlas_padding > atlas_width as usize - atlas_padding {
-                x_offset = atlas_padding;
-                y_offset += max_row_height + atlas_padding;
-                max_row_height = 0;
-            }
+    for &c in &charset {
+        let (metrics, bitmap) = font.rasterize(c, font_size);
+        if metrics.width == 0 || metrics.height == 0 {
+            continue;
+        }
 
-            for row in 0..metrics.height {
-                for col in 0..metrics.width {
-                    let pixel = bitmap[row * metrics.width + col];
-                    atlas.put_pixel(
-                        (x_offset + col) as u32,
-                        (y_offset + row) as u32,
-                        image::Luma([pixel]),
-                    );
-                }
-            }
+        if x_offset + metrics.width + atlas_padding > atlas_width as usize - atlas_padding {
+            x_offset = atlas_padding;
+            y_offset += max_row_height + atlas_padding;
+            max_row_height = 0;
+        }
 
-            let u0 = x_offset as f32 / atlas_width as f32;
-            let v0 = 1.0 - (y_offset + metrics.height) as f32 / atlas_height as f32;
-            let u1 = (x_offset + metrics.width) as f32 / atlas_width as f32;
-            let v1 = 1.0 - y_offset as f32 / atlas_height as f32;
-
-            glyphs.insert(
-                c,
-                GlyphData {
-                    u0,
-                    v0,
-                    u1,
-                    v1,
-                    width: metrics.width as f32,
-                    height: metrics.height as f32,
-                    x_offset: metrics.xmin as f32,
-                    y_offset: metrics.ymin as f32,
-                    x_advance: metrics.advance_width as f32,
-                },
-            );
-
-            x_offset += metrics.width + atlas_padding;
-            max_row_height = max_row_height.max(metrics.height);
+        for row in 0..metrics.height {
+            for col in 0..metrics.width {
+                let pixel = bitmap[row * metrics.width + col];
+                atlas.put_pixel(
+                    (x_offset + col) as u32,
+                    (y_offset + row) as u32,
+                    image::Luma([pixel]),
+                );
+            }
         }
 
-        let (img_width, img_height) = atlas.dimensions();
-        let mut rgb_img = ImageBuffer::new(img_width, img_height);
+        let u0 = x_offset as f32 / atlas_width as f32;
+        let v0 = 1.0 - (y_offset + metrics.height) as f32 / atlas_height as f32;
+        let u1 = (x_offset + metrics.width) as f32 / atlas_width as f32;
+        let v1 = 1.0 - y_offset as f32 / atlas_height as f32;
 
-        for (x, y, pixel) in atlas.enumerate_pixels() {
-            let gray = pixel.0[0];
-            rgb_img.put_pixel(x, y, Rgb([gray, gray, gray]));
-        }
-        rgb_img.save("atlas.png")?;
-        let atlas = Texture::from_rgb_bytes(display, &rgb_img.into_raw(), (img_width, img_height))?;
+        glyphs.insert(
+            c,
+            GlyphData {
+                u0,
+                v0,
+                u1,
+                v1,
+                width: metrics.width as f32 / scale_factor,
+                height: metrics.height as f32 / scale_factor,
+                x_offset: metrics.xmin as f32 / scale_factor,
+                y_offset: metrics.ymin as f32 / scale_factor,
+                x_advance: metrics.advance_width / scale_factor,
+            },
+        );
 
-        Ok(Self {
-            atlas,
-            glyphs,
-            font_size,
-        })
+        x_offset += metrics.width + atlas_padding;
+        max_row_height = max_row_height.max(metrics.height);
+    }
+
+    let (img_width, img_height) = atlas.dimensions();
+    let mut rgb_img = ImageBuffer::new(img_width, img_height);
+
+    for (x, y, pixel) in atlas.enumerate_pixels() {
+        let gray = pixel.0[0];
+        rgb_img.put_pixel(x, y, Rgb([gray, gray, gray]));
     }
+    let atlas = Texture::from_rgb_bytes(display, &rgb_img.into_raw(), (img_width, img_height))?;
+
+    Ok((atlas, glyphs))
 }
 
 #[derive(Debug)]
@@ -0,0 +1,142 @@
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How the window waits for the monitor, picked when the window is made with `init_with_vsync`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Vsync {
+    /// Waits for the monitor every frame, no tearing
+    #[default]
+    On,
+    /// Never waits for the monitor, use `FramePacing::target_fps` to stop it running as fast as possible
+    Off,
+    /// Waits for the monitor unless the frame is late, then it's shown right away and tears instead of
+    /// dropping to half the refresh rate. Needs `EXT_swap_control_tear`, `On` is used without it
+    Adaptive,
+}
+
+// The last bit of the wait is spun since sleeping isn't precise enough
+const SPIN_TIME: Duration = Duration::from_micros(1500);
+
+/// Frame rate limiting, found on `Context::frame_pacing`
+pub struct FramePacing {
+    /// Frames per second to cap the game at, `None` doesn't cap it
+    pub target_fps: Option<f32>,
+    vsync: Vsync,
+    refresh_rate: Option<f32>,
+    last_frame: Instant,
+}
+
+impl FramePacing {
+    pub(crate) fn new(vsync: Vsync, refresh_rate: Option<f32>) -> Self {
+        Self {
+            target_fps: None,
+            vsync,
+            refresh_rate,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// What the window ended up with, `Vsync::Adaptive` turns into `On` if it isn't supported
+    pub fn get_vsync(&self) -> Vsync {
+        self.vsync
+    }
+
+    /// Refresh rate of the monitor the window is on, if it is known
+    pub fn get_refresh_rate(&self) -> Option<f32> {
+        self.refresh_rate
+    }
+    /// Called when the window moves since it might be on another monitor now
+    pub(crate) fn set_refresh_rate(&mut self, refresh_rate: Option<f32>) {
+        self.refresh_rate = refresh_rate;
+    }
+
+    /// Sleeps until it is time for the next frame
+    pub(crate) fn wait(&mut self) {
+        let now = Instant::now();
+        let Some(fps) = self.target_fps.filter(|fps| *fps > 0.0) else {
+            self.last_frame = now;
+            return;
+        };
+        let deadline = self.last_frame + Duration::from_secs_f32(1.0 / fps);
+        if deadline > now {
+            sleep_until(deadline);
+            self.last_frame = deadline;
+        } else {
+            // Running behind, don't try to make the time back up
+            self.last_frame = now;
+        }
+    }
+}
+
+fn sleep_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        let remaining = deadline - now;
+        if remaining > SPIN_TIME {
+            thread::sleep(remaining - SPIN_TIME);
+        } else {
+            thread::yield_now();
+        }
+    }
+}
+
+const FRAME_HISTORY: usize = 120;
+
+/// Frame times over the last 120 frames, in seconds
+pub struct FrameStats {
+    frame_times: VecDeque<f32>,
+    frame_count: u64,
+}
+
+impl FrameStats {
+    pub(crate) fn new() -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+            frame_count: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, frame_time: f32) {
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+        self.frame_count += 1;
+    }
+
+    pub fn get_average_frame_time(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+    }
+    pub fn get_min_frame_time(&self) -> f32 {
+        self.frame_times
+            .iter()
+            .copied()
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    }
+    pub fn get_max_frame_time(&self) -> f32 {
+        self.frame_times
+            .iter()
+            .copied()
+            .reduce(f32::max)
+            .unwrap_or(0.0)
+    }
+    /// Average frames per second
+    pub fn get_fps(&self) -> f32 {
+        let average = self.get_average_frame_time();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+    /// Frames since the game started
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
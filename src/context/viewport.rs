@@ -0,0 +1,179 @@
+use crate::{
+    context::Context,
+    error::EngineResult,
+    renderer::{Renderer, drawable::Drawable, render_target::RenderTarget},
+    shaders::{VIEWPORT_FS, VIEWPORT_VS},
+};
+use glium::{
+    DrawParameters, Rect, VertexBuffer, implement_vertex,
+    index::{IndicesSource, NoIndices, PrimitiveType},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter},
+    vertex::MultiVerticesSource,
+};
+use vek::Vec2;
+
+/// How the game's drawing fits into the window, set with `Context::set_viewport_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViewportMode {
+    /// Draws straight to the window at whatever size it is
+    #[default]
+    Fill,
+    /// Draws at a fixed virtual resolution that is scaled up as much as it fits,
+    /// with black bars filling the rest of the window
+    Letterbox(u32, u32),
+    /// Same as `Letterbox` but only scales by whole numbers so pixel art stays sharp
+    PixelPerfect(u32, u32),
+}
+
+impl ViewportMode {
+    /// The virtual resolution, `None` for `Fill`
+    pub fn get_resolution(&self) -> Option<(u32, u32)> {
+        match *self {
+            Self::Fill => None,
+            Self::Letterbox(width, height) | Self::PixelPerfect(width, height) => {
+                Some((width.max(1), height.max(1)))
+            }
+        }
+    }
+
+    /// Where the game ends up in a window of `window_size`, from the bottom left like OpenGL
+    pub fn get_rect(&self, window_size: (u32, u32)) -> Rect {
+        let Some((width, height)) = self.get_resolution() else {
+            return Rect {
+                left: 0,
+                bottom: 0,
+                width: window_size.0,
+                height: window_size.1,
+            };
+        };
+        let mut scale =
+            (window_size.0 as f32 / width as f32).min(window_size.1 as f32 / height as f32);
+        if let Self::PixelPerfect(..) = self {
+            // Rather too big than nothing at all on tiny windows
+            scale = scale.floor().max(1.0);
+        }
+        let (width, height) = (
+            (width as f32 * scale) as u32,
+            (height as f32 * scale) as u32,
+        );
+        Rect {
+            left: window_size.0.saturating_sub(width) / 2,
+            bottom: window_size.1.saturating_sub(height) / 2,
+            width,
+            height,
+        }
+    }
+
+    /// Turns a position in the window, like `Input::mouse_position`, into virtual pixels.
+    /// `None` if it is on the black bars
+    pub fn window_to_viewport(
+        &self,
+        window_size: (u32, u32),
+        position: Vec2<f32>,
+    ) -> Option<Vec2<f32>> {
+        let (width, height) = self.get_resolution().unwrap_or(window_size);
+        let rect = self.get_rect(window_size);
+        // Window positions start from the top
+        let top = window_size.1 as f32 - (rect.bottom + rect.height) as f32;
+        let x = (position.x - rect.left as f32) / rect.width.max(1) as f32;
+        let y = (position.y - top) / rect.height.max(1) as f32;
+        if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+            return None;
+        }
+        Some(Vec2::new(x * width as f32, y * height as f32))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ViewportVertex {
+    position: [f32; 2],
+}
+implement_vertex!(ViewportVertex, position);
+
+/// The offscreen target the game is drawn into when the mode has a virtual resolution
+pub(crate) struct Viewport {
+    pub(crate) mode: ViewportMode,
+    pub(crate) target: RenderTarget,
+    vbo: VertexBuffer<ViewportVertex>,
+    rect: Rect,
+}
+
+impl Viewport {
+    pub(crate) fn new(
+        ctx: &mut Context,
+        mode: ViewportMode,
+        window_size: (u32, u32),
+    ) -> EngineResult<Option<Self>> {
+        let Some((width, height)) = mode.get_resolution() else {
+            return Ok(None);
+        };
+        if ctx.get_program("viewport").is_none() {
+            ctx.new_program("viewport", VIEWPORT_VS, VIEWPORT_FS, None)?;
+        }
+        Ok(Some(Self {
+            mode,
+            target: RenderTarget::new(&ctx.display, width, height)?,
+            vbo: VertexBuffer::new(
+                &ctx.display,
+                &[
+                    ViewportVertex {
+                        position: [-1.0, -1.0],
+                    },
+                    ViewportVertex {
+                        position: [1.0, -1.0],
+                    },
+                    ViewportVertex {
+                        position: [-1.0, 1.0],
+                    },
+                    ViewportVertex {
+                        position: [1.0, 1.0],
+                    },
+                ],
+            )?,
+            rect: mode.get_rect(window_size),
+        }))
+    }
+
+    pub(crate) fn resize(&mut self, window_size: (u32, u32)) {
+        self.rect = self.mode.get_rect(window_size);
+    }
+
+    /// Scales the virtual resolution up onto the window
+    pub(crate) fn present(&self, ctx: &mut Context, renderer: &mut impl Renderer) -> EngineResult {
+        renderer.clear_color(0.0, 0.0, 0.0, 1.0);
+        let (magnify, minify) = match self.mode {
+            ViewportMode::PixelPerfect(..) => {
+                (MagnifySamplerFilter::Nearest, MinifySamplerFilter::Nearest)
+            }
+            ViewportMode::Fill | ViewportMode::Letterbox(..) => {
+                (MagnifySamplerFilter::Linear, MinifySamplerFilter::Linear)
+            }
+        };
+        renderer.draw(
+            ctx,
+            self,
+            &uniform! {
+                tex: self.target.get_texture().sampled().magnify_filter(magnify).minify_filter(minify),
+            },
+        )
+    }
+}
+
+impl Drawable for Viewport {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        &self.vbo
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TriangleStrip)
+    }
+    fn get_program(&self) -> String {
+        "viewport".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        DrawParameters {
+            viewport: Some(self.rect),
+            ..Default::default()
+        }
+    }
+}
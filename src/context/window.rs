@@ -0,0 +1,188 @@
+use crate::{
+    context::frame_pacing::Vsync,
+    error::{EngineError, EngineResult},
+};
+use glium::{
+    glutin::config::ConfigTemplateBuilder,
+    winit::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        window::{Fullscreen, Icon, Window, WindowAttributes},
+    },
+};
+use image::RgbaImage;
+
+/// How the window covers the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    /// A window without borders the size of the monitor, quick to switch in and out of
+    Borderless,
+    /// Changes the monitor's video mode to the closest one to the window size
+    Exclusive,
+}
+
+/// Everything used to create the window, pass it to `init_with_config`
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    pub(crate) title: String,
+    pub(crate) size: (u32, u32),
+    pub(crate) fullscreen: FullscreenMode,
+    pub(crate) resizable: bool,
+    pub(crate) min_size: Option<(u32, u32)>,
+    pub(crate) max_size: Option<(u32, u32)>,
+    pub(crate) position: Option<(i32, i32)>,
+    pub(crate) icon: Option<RgbaImage>,
+    pub(crate) multisampling: u8,
+    pub(crate) depth_bits: u8,
+    pub(crate) stencil_bits: u8,
+    pub(crate) srgb: Option<bool>,
+    pub(crate) vsync: Vsync,
+    pub(crate) null_audio: bool,
+}
+
+impl WindowConfig {
+    pub fn new(title: impl Into<String>, size: (u32, u32)) -> Self {
+        Self {
+            title: title.into(),
+            size,
+            fullscreen: FullscreenMode::Windowed,
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            position: None,
+            icon: None,
+            multisampling: 0,
+            depth_bits: 24,
+            stencil_bits: 8,
+            srgb: None,
+            vsync: Vsync::On,
+            null_audio: false,
+        }
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: FullscreenMode) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+    pub fn with_min_size(mut self, min_size: (u32, u32)) -> Self {
+        self.min_size = Some(min_size);
+        self
+    }
+    pub fn with_max_size(mut self, max_size: (u32, u32)) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+    /// Position of the top left corner of the window on the desktop in pixels
+    pub fn with_position(mut self, position: (i32, i32)) -> Self {
+        self.position = Some(position);
+        self
+    }
+    /// Load it with `image::open(path)?.to_rgba8()`
+    pub fn with_icon(mut self, icon: RgbaImage) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+    /// Number of MSAA samples, 0 turns it off
+    pub fn with_multisampling(mut self, samples: u8) -> Self {
+        self.multisampling = samples;
+        self
+    }
+    /// Default is 24
+    pub fn with_depth_bits(mut self, depth_bits: u8) -> Self {
+        self.depth_bits = depth_bits;
+        self
+    }
+    /// Default is 8
+    pub fn with_stencil_bits(mut self, stencil_bits: u8) -> Self {
+        self.stencil_bits = stencil_bits;
+        self
+    }
+    /// Makes the window convert colors from linear to sRGB when drawing, if it isn't called
+    /// the driver's default is kept
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = Some(srgb);
+        self
+    }
+    pub fn with_vsync(mut self, vsync: Vsync) -> Self {
+        self.vsync = vsync;
+        self
+    }
+    /// Starts with `Audio::new_null` instead of opening the default output device
+    pub fn with_null_audio(mut self, null_audio: bool) -> Self {
+        self.null_audio = null_audio;
+        self
+    }
+
+    /// Fullscreen is set after the window is made since it needs the window's monitor
+    pub(crate) fn window_attributes(&self) -> EngineResult<WindowAttributes> {
+        let mut attributes = Window::default_attributes()
+            .with_title(&self.title)
+            .with_inner_size(PhysicalSize::new(self.size.0, self.size.1))
+            .with_resizable(self.resizable);
+        if let Some((width, height)) = self.min_size {
+            attributes = attributes.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.max_size {
+            attributes = attributes.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((x, y)) = self.position {
+            attributes = attributes.with_position(PhysicalPosition::new(x, y));
+        }
+        if let Some(icon) = &self.icon {
+            let icon = Icon::from_rgba(icon.as_raw().clone(), icon.width(), icon.height())
+                .map_err(|err| EngineError::ImageError(err.to_string()))?;
+            attributes = attributes.with_window_icon(Some(icon));
+        }
+        Ok(attributes)
+    }
+
+    pub(crate) fn config_template(&self) -> ConfigTemplateBuilder {
+        let mut template = ConfigTemplateBuilder::new()
+            .with_depth_size(self.depth_bits)
+            .with_stencil_size(self.stencil_bits);
+        if self.multisampling > 0 {
+            template = template.with_multisampling(self.multisampling);
+        }
+        template
+    }
+}
+
+pub(crate) fn set_fullscreen(window: &Window, mode: FullscreenMode) {
+    let fullscreen = match mode {
+        FullscreenMode::Windowed => None,
+        FullscreenMode::Borderless => Some(Fullscreen::Borderless(None)),
+        FullscreenMode::Exclusive => {
+            let size = window.inner_size();
+            // Closest video mode to the window size, then the highest refresh rate
+            window
+                .current_monitor()
+                .and_then(|monitor| {
+                    monitor.video_modes().min_by_key(|mode| {
+                        let mode_size = mode.size();
+                        (
+                            mode_size.width.abs_diff(size.width)
+                                + mode_size.height.abs_diff(size.height),
+                            u32::MAX - mode.refresh_rate_millihertz(),
+                        )
+                    })
+                })
+                .map(Fullscreen::Exclusive)
+                // Not every platform has video modes
+                .or(Some(Fullscreen::Borderless(None)))
+        }
+    };
+    window.set_fullscreen(fullscreen);
+}
+
+pub(crate) fn get_fullscreen(window: &Window) -> FullscreenMode {
+    match window.fullscreen() {
+        None => FullscreenMode::Windowed,
+        Some(Fullscreen::Borderless(_)) => FullscreenMode::Borderless,
+        Some(Fullscreen::Exclusive(_)) => FullscreenMode::Exclusive,
+    }
+}
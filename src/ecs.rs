@@ -0,0 +1,295 @@
+use crate::{
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::Renderer,
+};
+use query::{Query, ReadQuery};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+use std::{any::TypeId, collections::HashMap};
+use storage::{AnyStorage, SparseSet};
+
+pub mod components;
+pub mod hierarchy;
+pub mod query;
+pub mod storage;
+mod systems;
+
+/// Id of something in a `World`, it stays unique after being despawned so old ids never point at new entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// Holds entities and their components, any `'static` type can be a component.
+/// Call `update` and `draw` from the `GameState` so the built-in components do their thing
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    len: usize,
+    pub(crate) storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    renderer: Option<systems::WorldRenderer>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes an entity out of a tuple of components, `world.spawn((Transform::default(), sprite))`
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        let entity = match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity {
+                    index,
+                    generation: self.generations[index as usize],
+                }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity {
+                    index: self.generations.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+        self.len += 1;
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// Removes the entity and all of its components, false if it was already gone.
+    /// Its children are detached and kept, use `despawn_recursive` to remove them too
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        self.detach(entity);
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        self.len -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index).copied().unwrap_or(false)
+            && self.generations[index] == entity.generation
+    }
+
+    /// Number of entities alive
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(index, _)| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+
+    /// Despawns everything, the sprite and mesh buffers are kept
+    pub fn clear(&mut self) {
+        let renderer = self.renderer.take();
+        *self = Self {
+            renderer,
+            ..Default::default()
+        };
+    }
+
+    /// Adds a component to the entity, replacing the one of the same type if it had one
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> EngineResult {
+        if !self.is_alive(entity) {
+            return Err(EngineError::Error(format!(
+                "Can't add a component to {:?}, it was despawned",
+                entity
+            )));
+        }
+        self.get_or_add_storage::<T>().insert(entity, component);
+        Ok(())
+    }
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.get_storage_mut::<T>()?.remove(entity)
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.get_storage::<T>()?.get(entity)
+    }
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.get_storage_mut::<T>()?.get_mut(entity)
+    }
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    /// Runs `f` for every entity that has all the components in `Q`.
+    /// `world.query::<(&mut Transform, &Velocity)>(|entity, (transform, velocity)| ...)`
+    pub fn query<Q: Query>(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>)) {
+        let Some(mut fetch) = Q::fetch(self) else {
+            return;
+        };
+        for entity in Q::entities(&fetch) {
+            if let Some(item) = Q::get(&mut fetch, entity) {
+                f(entity, item);
+            }
+        }
+    }
+
+    /// Same as `query` but only reads, so it doesn't need the world to be mutable
+    pub fn read_query<'w, R: ReadQuery>(&'w self, mut f: impl FnMut(Entity, R::Item<'w>)) {
+        let Some(fetch) = R::fetch(self) else {
+            return;
+        };
+        let entities = match R::entities(&fetch) {
+            Some(entities) => entities.to_vec(),
+            None => self.entities().collect(),
+        };
+        for entity in entities {
+            if let Some(item) = R::get(&fetch, entity) {
+                f(entity, item);
+            }
+        }
+    }
+
+    /// Runs `f` on the rayon thread pool for every `T` whose entity also has the components in `R`.
+    /// `R` is only read and can't contain `T`, that's an error. Use `()` to read nothing else.
+    /// The components in `R` are shared between threads so they have to be `Sync`
+    pub fn par_query<T, R>(
+        &mut self,
+        f: impl Fn(Entity, &mut T, R::Item<'_>) + Send + Sync,
+    ) -> EngineResult
+    where
+        T: Send + 'static,
+        R: ReadQuery,
+        for<'w> R::Fetch<'w>: Sync,
+    {
+        if R::reads(TypeId::of::<T>()) {
+            return Err(EngineError::Error(format!(
+                "par_query can't read {} since it writes to it",
+                std::any::type_name::<T>()
+            )));
+        }
+        // Taken out so the rest of the world can be read while `T` is written to
+        let Some(mut storage) = self.storages.remove(&TypeId::of::<T>()) else {
+            return Ok(());
+        };
+        if let Some(fetch) = R::fetch(self)
+            && let Some(set) = storage.as_any_mut().downcast_mut::<SparseSet<T>>()
+        {
+            let (entities, components) = set.split_mut();
+            components
+                .par_iter_mut()
+                .zip(entities.par_iter())
+                .for_each(|(component, entity)| {
+                    if let Some(item) = R::get(&fetch, *entity) {
+                        f(*entity, component, item);
+                    }
+                });
+        }
+        self.storages.insert(TypeId::of::<T>(), storage);
+        Ok(())
+    }
+
+    /// Runs the built-in systems, transforms are propagated down the hierarchy
+    /// then `AudioEmitter`s follow their `GlobalTransform` and play queued sounds
+    pub fn update(&mut self, ctx: &mut Context) {
+        self.propagate_transforms();
+        systems::update_audio(self, ctx);
+    }
+
+    /// Draws every entity with a `Transform` and a `MeshRenderer` using `ctx.camera`, sorted by their
+    /// `RenderLayer`, then every `Sprite` on top of them. Both are drawn with their `GlobalTransform`.
+    /// If any light casts shadows the shadow maps are drawn first
+    pub fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) -> EngineResult {
+        self.propagate_transforms();
+        let mut world_renderer = match self.renderer.take() {
+            Some(world_renderer) => world_renderer,
+            None => systems::WorldRenderer::new(ctx)?,
+        };
+        let shadows = if ctx.lights.any_casts_shadows() {
+            world_renderer.draw_shadows(self, ctx)
+        } else {
+            Ok(())
+        };
+        let result = shadows
+            .and_then(|_| world_renderer.draw_meshes(self, ctx, renderer))
+            .and_then(|_| world_renderer.draw_sprites(self, ctx, renderer));
+        self.renderer = Some(world_renderer);
+        result
+    }
+
+    pub(crate) fn get_storage<T: 'static>(&self) -> Option<&SparseSet<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref()
+    }
+    pub(crate) fn get_storage_mut<T: 'static>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut()
+    }
+    fn get_or_add_storage<T: 'static>(&mut self) -> &mut SparseSet<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+}
+
+/// A tuple of components that `World::spawn` adds to a new entity
+pub trait Bundle {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+/// An entity with no components
+impl Bundle for () {
+    fn insert_into(self, _: &mut World, _: Entity) {}
+}
+
+macro_rules! impl_bundle {
+    ($($name:ident $index:tt),*) => {
+        impl<$($name: 'static),*> Bundle for ($($name,)*) {
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                $(world.get_or_add_storage::<$name>().insert(entity, self.$index);)*
+            }
+        }
+    };
+}
+
+impl_bundle!(A 0);
+impl_bundle!(A 0, B 1);
+impl_bundle!(A 0, B 1, C 2);
+impl_bundle!(A 0, B 1, C 2, D 3);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
@@ -0,0 +1,191 @@
+use crate::{
+    asset::model::Model,
+    context::audio::{
+        audio_play_value::{self, AudioPlayValue},
+        sound::Sound,
+        spatial::{Attenuation, Emitter},
+    },
+    renderer::{
+        lighting::MaterialUniforms, material::Material, pbr::PbrMaterial,
+        render_queue::RenderLayer, texture::Texture,
+    },
+};
+use glium::{BackfaceCullingMode, Depth, DepthTest, DrawParameters};
+use std::rc::Rc;
+use vek::{Rgba, Vec2, Vec3};
+
+/// A textured quad drawn in screen space like `Text`, (-1, -1) is the bottom left of the screen.
+/// Uses the entity's `GlobalTransform`, the position's z picks which sprites are on top
+pub struct Sprite {
+    pub texture: Rc<Texture>,
+    /// Width and height before the transform's scale
+    pub size: Vec2<f32>,
+    /// Multiplied with the texture
+    pub color: Rgba<f32>,
+    /// Part of the texture to use, from the bottom left as (u0, v0, u1, v1). Good for sprite sheets
+    pub uv_rect: (f32, f32, f32, f32),
+}
+
+impl Sprite {
+    pub fn new(texture: Rc<Texture>, size: Vec2<f32>) -> Self {
+        Self {
+            texture,
+            size,
+            color: Rgba::white(),
+            uv_rect: (0.0, 0.0, 1.0, 1.0),
+        }
+    }
+    pub fn with_color(mut self, color: Rgba<f32>) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn with_uv_rect(mut self, uv_rect: (f32, f32, f32, f32)) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+}
+
+/// Draws a `Model` at the entity's `GlobalTransform` with the camera in `Context`.
+/// The model needs position, normal and tex_coords attributes like the ones from `load_wavefront`
+pub struct MeshRenderer {
+    pub model: Rc<Model>,
+    pub color: Rgba<f32>,
+    pub texture: Option<Rc<Texture>>,
+    /// Lit by `ctx.lights` when set, `color` and `texture` are ignored for the material's
+    pub material: Option<Material>,
+    /// Same as `material` but drawn with the "pbr" program, `material` wins if both are set
+    pub pbr_material: Option<PbrMaterial>,
+    /// Drawn into the shadow maps by `World::draw`, defaults to true
+    pub casts_shadows: bool,
+    /// Defaults to the built-in "mesh" program, a custom one gets the `persp`, `view`, `model`,
+    /// `color` and `tex` uniforms
+    pub program: String,
+    pub draw_params: DrawParameters<'static>,
+    /// Where it's sorted when `World::draw` queues it, set `Transparent` when the draw parameters blend
+    pub layer: RenderLayer,
+}
+
+impl MeshRenderer {
+    pub fn new(model: Rc<Model>) -> Self {
+        Self {
+            model,
+            color: Rgba::white(),
+            texture: None,
+            material: None,
+            pbr_material: None,
+            casts_shadows: true,
+            program: "mesh".to_string(),
+            draw_params: DrawParameters {
+                depth: Depth {
+                    test: DepthTest::IfLess,
+                    write: true,
+                    ..Default::default()
+                },
+                backface_culling: BackfaceCullingMode::CullClockwise,
+                ..Default::default()
+            },
+            layer: RenderLayer::Opaque,
+        }
+    }
+    pub fn with_color(mut self, color: Rgba<f32>) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn with_texture(mut self, texture: Rc<Texture>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+    /// Switches to the built-in "lit" program and the material's draw parameters
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.program = "lit".to_string();
+        self.draw_params = material.get_draw_params();
+        self.material = Some(material);
+        self
+    }
+    /// Switches to the built-in "pbr" program and the material's draw parameters
+    pub fn with_pbr_material(mut self, pbr_material: PbrMaterial) -> Self {
+        self.program = "pbr".to_string();
+        self.draw_params = pbr_material.get_draw_params();
+        self.pbr_material = Some(pbr_material);
+        self
+    }
+    pub fn with_casts_shadows(mut self, casts_shadows: bool) -> Self {
+        self.casts_shadows = casts_shadows;
+        self
+    }
+    pub fn with_program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+    pub fn with_draw_params(mut self, draw_params: DrawParameters<'static>) -> Self {
+        self.draw_params = draw_params;
+        self
+    }
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub(crate) fn lit_material(&self) -> Option<&dyn MaterialUniforms> {
+        match (&self.material, &self.pbr_material) {
+            (Some(material), _) => Some(material),
+            (None, Some(pbr_material)) => Some(pbr_material),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Plays sounds from the entity's `GlobalTransform`, the position and velocity are kept up to date by `World::update`
+pub struct AudioEmitter {
+    pub emitter: Emitter,
+    pub volume: AudioPlayValue,
+    pub speed: AudioPlayValue,
+    /// Bus to play on, see `PlayOptions::bus`
+    pub bus: Option<String>,
+    queued: Vec<Sound>,
+    last_position: Option<Vec3<f32>>,
+}
+
+impl Default for AudioEmitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioEmitter {
+    pub fn new() -> Self {
+        Self {
+            emitter: Emitter::new(Vec3::zero()),
+            volume: audio_play_value::new_audio_play_value(1.0),
+            speed: audio_play_value::new_audio_play_value(1.0),
+            bus: None,
+            queued: Vec::new(),
+            last_position: None,
+        }
+    }
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.emitter = self.emitter.with_attenuation(attenuation);
+        self
+    }
+    pub fn with_bus(mut self, bus: impl Into<String>) -> Self {
+        self.bus = Some(bus.into());
+        self
+    }
+
+    /// The sound starts on the next `World::update`
+    pub fn play(&mut self, sound: &Sound) {
+        self.queued.push(sound.clone());
+    }
+
+    /// Moves the emitter and gives back the sounds waiting to be played
+    pub(crate) fn follow(&mut self, position: Vec3<f32>, dt: f32) -> Vec<Sound> {
+        if let Some(last_position) = self.last_position
+            && dt > 0.0
+        {
+            self.emitter.set_velocity((position - last_position) / dt);
+        }
+        self.emitter.set_position(position);
+        self.last_position = Some(position);
+        std::mem::take(&mut self.queued)
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    ecs::{Entity, World},
+    error::{EngineError, EngineResult},
+    math::Transform,
+};
+use vek::{Mat4, Vec3};
+
+/// The entity this one is attached to, added by `World::set_parent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// Entities attached to this one, in the order they were attached
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn get(&self) -> &[Entity] {
+        &self.0
+    }
+}
+
+/// Where an entity ends up after its `Transform` is put on top of all its parents' ones.
+/// Kept up to date by `World::update` and `World::draw`, only the parts of the tree that moved get recalculated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform {
+    matrix: Mat4<f32>,
+    // What the matrix was made from, it gets recalculated when the `Transform` doesn't match anymore
+    local: Transform,
+    dirty: bool,
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self {
+            matrix: Mat4::identity(),
+            local: Transform::default(),
+            dirty: true,
+        }
+    }
+}
+
+impl GlobalTransform {
+    pub fn get_matrix(&self) -> Mat4<f32> {
+        self.matrix
+    }
+    /// `get_matrix` in the format glium uniforms take, same as `Transform::get_model`
+    pub fn get_model(&self) -> [[f32; 4]; 4] {
+        self.matrix.into_col_arrays()
+    }
+    pub fn position(&self) -> Vec3<f32> {
+        self.matrix.cols.w.xyz()
+    }
+}
+
+impl World {
+    /// Attaches `child` to `parent` so it moves with it, its `Transform` becomes relative to the parent.
+    /// Errors if either is despawned or if `parent` is already below `child`
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> EngineResult {
+        if !self.is_alive(child) || !self.is_alive(parent) {
+            return Err(EngineError::Error(format!(
+                "Can't attach {:?} to {:?}, one of them was despawned",
+                child, parent
+            )));
+        }
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                return Err(EngineError::Error(format!(
+                    "Can't attach {:?} to {:?}, it would be its own parent",
+                    child, parent
+                )));
+            }
+            ancestor = self.get_parent(entity);
+        }
+
+        self.remove_parent(child);
+        self.insert(child, Parent(parent))?;
+        match self.get_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => self.insert(parent, Children(vec![child]))?,
+        }
+        self.mark_dirty(child);
+        Ok(())
+    }
+
+    /// Detaches the entity from its parent, it stays where its `Transform` puts it in world space
+    pub fn remove_parent(&mut self, child: Entity) -> Option<Entity> {
+        let Parent(parent) = self.remove::<Parent>(child)?;
+        if let Some(children) = self.get_mut::<Children>(parent) {
+            children.0.retain(|entity| *entity != child);
+            if children.0.is_empty() {
+                self.remove::<Children>(parent);
+            }
+        }
+        self.mark_dirty(child);
+        Some(parent)
+    }
+
+    pub fn get_parent(&self, entity: Entity) -> Option<Entity> {
+        self.get::<Parent>(entity).map(Parent::get)
+    }
+    /// Empty if nothing is attached
+    pub fn get_children(&self, entity: Entity) -> &[Entity] {
+        self.get::<Children>(entity).map_or(&[], Children::get)
+    }
+
+    /// Despawns the entity and everything attached below it
+    pub fn despawn_recursive(&mut self, entity: Entity) -> bool {
+        let mut stack = vec![entity];
+        let mut found = false;
+        while let Some(entity) = stack.pop() {
+            stack.extend_from_slice(self.get_children(entity));
+            found |= self.despawn(entity);
+        }
+        found
+    }
+
+    /// The entity's world matrix from the last time transforms were propagated,
+    /// falls back to its own `Transform` if it hasn't been propagated yet
+    pub fn world_matrix(&self, entity: Entity) -> Option<Mat4<f32>> {
+        match self.get::<GlobalTransform>(entity) {
+            Some(global) if !global.dirty => Some(global.matrix),
+            _ => self.get::<Transform>(entity).map(Transform::get_matrix),
+        }
+    }
+
+    /// Updates every `GlobalTransform`, called by `World::update` and `World::draw`.
+    /// Call it yourself if you need world positions that are up to date in between
+    pub fn propagate_transforms(&mut self) {
+        // Entities without a `Transform` can still be in the tree, they just don't move their children
+        let transforms = self
+            .get_storage::<Transform>()
+            .map_or(&[][..], |set| set.entities());
+        let parents = self
+            .get_storage::<Children>()
+            .map_or(&[][..], |set| set.entities());
+        let roots: Vec<Entity> = transforms
+            .iter()
+            .chain(
+                parents
+                    .iter()
+                    .filter(|entity| !self.has::<Transform>(**entity)),
+            )
+            .copied()
+            .filter(|entity| !self.has::<Parent>(*entity))
+            .collect();
+
+        let mut stack: Vec<(Entity, Mat4<f32>, bool)> = roots
+            .into_iter()
+            .map(|root| (root, Mat4::identity(), false))
+            .collect();
+        while let Some((entity, parent_matrix, parent_changed)) = stack.pop() {
+            let local = self.get::<Transform>(entity).copied().unwrap_or_default();
+            let (matrix, changed) = match self.get_mut::<GlobalTransform>(entity) {
+                Some(global) if !parent_changed && !global.dirty && global.local == local => {
+                    (global.matrix, false)
+                }
+                Some(global) => {
+                    global.matrix = parent_matrix * local.get_matrix();
+                    global.local = local;
+                    global.dirty = false;
+                    (global.matrix, true)
+                }
+                None => {
+                    let global = GlobalTransform {
+                        matrix: parent_matrix * local.get_matrix(),
+                        local,
+                        dirty: false,
+                    };
+                    let _ = self.insert(entity, global);
+                    (global.matrix, true)
+                }
+            };
+            stack.extend(
+                self.get_children(entity)
+                    .iter()
+                    .map(|child| (*child, matrix, changed)),
+            );
+        }
+    }
+
+    /// Detaches everything from the entity before it's despawned
+    pub(crate) fn detach(&mut self, entity: Entity) {
+        self.remove_parent(entity);
+        if let Some(Children(children)) = self.remove::<Children>(entity) {
+            for child in children {
+                self.remove::<Parent>(child);
+                self.mark_dirty(child);
+            }
+        }
+    }
+
+    fn mark_dirty(&mut self, entity: Entity) {
+        if let Some(global) = self.get_mut::<GlobalTransform>(entity) {
+            global.dirty = true;
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::ecs::{Entity, World, storage::SparseSet};
+use std::any::TypeId;
+
+/// One part of a `Query`, either `&T` or `&mut T`
+pub trait QueryElement {
+    type Component: 'static;
+    type Storage<'w>;
+    type Item<'a>;
+    fn from_storage(storage: &mut SparseSet<Self::Component>) -> Self::Storage<'_>;
+    fn entities<'s>(storage: &'s Self::Storage<'_>) -> &'s [Entity];
+    fn get<'a>(storage: &'a mut Self::Storage<'_>, entity: Entity) -> Option<Self::Item<'a>>;
+}
+
+impl<T: 'static> QueryElement for &T {
+    type Component = T;
+    type Storage<'w> = &'w SparseSet<T>;
+    type Item<'a> = &'a T;
+    fn from_storage(storage: &mut SparseSet<T>) -> &SparseSet<T> {
+        storage
+    }
+    fn entities<'s>(storage: &'s &SparseSet<T>) -> &'s [Entity] {
+        storage.entities()
+    }
+    fn get<'a>(storage: &'a mut &SparseSet<T>, entity: Entity) -> Option<&'a T> {
+        storage.get(entity)
+    }
+}
+
+impl<T: 'static> QueryElement for &mut T {
+    type Component = T;
+    type Storage<'w> = &'w mut SparseSet<T>;
+    type Item<'a> = &'a mut T;
+    fn from_storage(storage: &mut SparseSet<T>) -> &mut SparseSet<T> {
+        storage
+    }
+    fn entities<'s>(storage: &'s &mut SparseSet<T>) -> &'s [Entity] {
+        storage.entities()
+    }
+    fn get<'a>(storage: &'a mut &mut SparseSet<T>, entity: Entity) -> Option<&'a mut T> {
+        storage.get_mut(entity)
+    }
+}
+
+/// Components to look up with `World::query`, such as `(&mut Transform, &Velocity)`.
+/// A component type can only be in a query once, a query that has one twice matches nothing
+pub trait Query {
+    type Fetch<'w>;
+    type Item<'a>;
+    /// `None` if one of the component types has never been added or is in the query twice
+    fn fetch(world: &mut World) -> Option<Self::Fetch<'_>>;
+    /// Entities that might match, taken from the smallest storage
+    fn entities(fetch: &Self::Fetch<'_>) -> Vec<Entity>;
+    fn get<'a>(fetch: &'a mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'a>>;
+}
+
+impl<E: QueryElement> Query for E {
+    type Fetch<'w> = E::Storage<'w>;
+    type Item<'a> = E::Item<'a>;
+    fn fetch(world: &mut World) -> Option<Self::Fetch<'_>> {
+        world
+            .get_storage_mut::<E::Component>()
+            .map(|storage| E::from_storage(storage))
+    }
+    fn entities(fetch: &Self::Fetch<'_>) -> Vec<Entity> {
+        E::entities(fetch).to_vec()
+    }
+    fn get<'a>(fetch: &'a mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'a>> {
+        E::get(fetch, entity)
+    }
+}
+
+macro_rules! impl_query {
+    ($($name:ident $index:tt),*) => {
+        impl<$($name: QueryElement),*> Query for ($($name,)*) {
+            type Fetch<'w> = ($($name::Storage<'w>,)*);
+            type Item<'a> = ($($name::Item<'a>,)*);
+            fn fetch(world: &mut World) -> Option<Self::Fetch<'_>> {
+                let type_ids = [$(TypeId::of::<$name::Component>()),*];
+                // `get_disjoint_mut` panics if a storage is asked for twice
+                if type_ids
+                    .iter()
+                    .enumerate()
+                    .any(|(index, type_id)| type_ids[..index].contains(type_id))
+                {
+                    return None;
+                }
+                let mut storages = world
+                    .storages
+                    .get_disjoint_mut(type_ids.each_ref())
+                    .into_iter();
+                Some(($($name::from_storage(
+                    storages
+                        .next()??
+                        .as_any_mut()
+                        .downcast_mut::<SparseSet<$name::Component>>()?,
+                ),)*))
+            }
+            fn entities(fetch: &Self::Fetch<'_>) -> Vec<Entity> {
+                [$($name::entities(&fetch.$index)),*]
+                    .into_iter()
+                    .min_by_key(|entities| entities.len())
+                    .unwrap_or_default()
+                    .to_vec()
+            }
+            fn get<'a>(fetch: &'a mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'a>> {
+                Some(($($name::get(&mut fetch.$index, entity)?,)*))
+            }
+        }
+    };
+}
+
+impl_query!(A 0);
+impl_query!(A 0, B 1);
+impl_query!(A 0, B 1, C 2);
+impl_query!(A 0, B 1, C 2, D 3);
+impl_query!(A 0, B 1, C 2, D 3, E 4);
+impl_query!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_query!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_query!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Components that are only read, used by `World::read_query` and `World::par_query`.
+/// `()` reads nothing
+pub trait ReadQuery {
+    type Fetch<'w>;
+    type Item<'a>;
+    fn fetch(world: &World) -> Option<Self::Fetch<'_>>;
+    /// `None` when any entity could match
+    fn entities<'s>(fetch: &'s Self::Fetch<'_>) -> Option<&'s [Entity]>;
+    fn get<'w>(fetch: &Self::Fetch<'w>, entity: Entity) -> Option<Self::Item<'w>>;
+    /// Whether one of the components read is `type_id`
+    fn reads(type_id: TypeId) -> bool;
+}
+
+impl ReadQuery for () {
+    type Fetch<'w> = ();
+    type Item<'a> = ();
+    fn fetch(_: &World) -> Option<()> {
+        Some(())
+    }
+    fn entities(_: &()) -> Option<&[Entity]> {
+        None
+    }
+    fn get<'w>(_: &Self::Fetch<'w>, _: Entity) -> Option<Self::Item<'w>> {
+        Some(())
+    }
+    fn reads(_: TypeId) -> bool {
+        false
+    }
+}
+
+impl<T: 'static> ReadQuery for &T {
+    type Fetch<'w> = &'w SparseSet<T>;
+    type Item<'a> = &'a T;
+    fn fetch(world: &World) -> Option<&SparseSet<T>> {
+        world.get_storage::<T>()
+    }
+    fn entities<'s>(fetch: &'s &SparseSet<T>) -> Option<&'s [Entity]> {
+        Some(fetch.entities())
+    }
+    fn get<'w>(fetch: &Self::Fetch<'w>, entity: Entity) -> Option<Self::Item<'w>> {
+        (*fetch).get(entity)
+    }
+    fn reads(type_id: TypeId) -> bool {
+        type_id == TypeId::of::<T>()
+    }
+}
+
+macro_rules! impl_read_query {
+    ($($name:ident $index:tt),*) => {
+        impl<$($name: ReadQuery),*> ReadQuery for ($($name,)*) {
+            type Fetch<'w> = ($($name::Fetch<'w>,)*);
+            type Item<'a> = ($($name::Item<'a>,)*);
+            fn fetch(world: &World) -> Option<Self::Fetch<'_>> {
+                Some(($($name::fetch(world)?,)*))
+            }
+            fn entities<'s>(fetch: &'s Self::Fetch<'_>) -> Option<&'s [Entity]> {
+                [$($name::entities(&fetch.$index)),*]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|entities| entities.len())
+            }
+            fn get<'w>(fetch: &Self::Fetch<'w>, entity: Entity) -> Option<Self::Item<'w>> {
+                Some(($($name::get(&fetch.$index, entity)?,)*))
+            }
+            fn reads(type_id: TypeId) -> bool {
+                $($name::reads(type_id))||*
+            }
+        }
+    };
+}
+
+impl_read_query!(A 0);
+impl_read_query!(A 0, B 1);
+impl_read_query!(A 0, B 1, C 2);
+impl_read_query!(A 0, B 1, C 2, D 3);
+impl_read_query!(A 0, B 1, C 2, D 3, E 4);
+impl_read_query!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_read_query!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_read_query!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
@@ -0,0 +1,92 @@
+use crate::ecs::Entity;
+use std::any::Any;
+
+const EMPTY: u32 = u32::MAX;
+
+/// Where components of one type are kept, only used through `World`.
+/// Components packed tightly in `dense`, `sparse` is indexed by the entity's index
+/// and points into `dense`
+pub struct SparseSet<T> {
+    sparse: Vec<u32>,
+    dense: Vec<T>,
+    entities: Vec<Entity>,
+}
+
+impl<T> SparseSet<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = *self.sparse.get(entity.index as usize)?;
+        if index == EMPTY || self.entities[index as usize] != entity {
+            return None;
+        }
+        Some(index as usize)
+    }
+
+    /// Replaces the old component if the entity already had one
+    pub(crate) fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(index) = self.dense_index(entity) {
+            return Some(std::mem::replace(&mut self.dense[index], component));
+        }
+        let sparse_index = entity.index as usize;
+        if sparse_index >= self.sparse.len() {
+            self.sparse.resize(sparse_index + 1, EMPTY);
+        }
+        self.sparse[sparse_index] = self.dense.len() as u32;
+        self.dense.push(component);
+        self.entities.push(entity);
+        None
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.dense_index(entity)?;
+        self.sparse[entity.index as usize] = EMPTY;
+        // The last component fills the hole
+        let component = self.dense.swap_remove(index);
+        self.entities.swap_remove(index);
+        if let Some(moved) = self.entities.get(index) {
+            self.sparse[moved.index as usize] = index as u32;
+        }
+        Some(component)
+    }
+
+    pub(crate) fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(|index| &self.dense[index])
+    }
+    pub(crate) fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.dense_index(entity).map(|index| &mut self.dense[index])
+    }
+
+    pub(crate) fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+    /// Entities and their components, lined up with each other
+    pub(crate) fn split_mut(&mut self) -> (&[Entity], &mut [T]) {
+        (&self.entities, &mut self.dense)
+    }
+}
+
+/// Lets the `World` hold storages of any component type
+pub(crate) trait AnyStorage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
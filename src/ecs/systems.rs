@@ -0,0 +1,270 @@
+use crate::{
+    context::{Context, audio::PlayOptions},
+    ecs::{
+        World,
+        components::{AudioEmitter, MeshRenderer, Sprite},
+        hierarchy::GlobalTransform,
+    },
+    error::EngineResult,
+    renderer::{
+        Renderer,
+        drawable::Drawable,
+        lighting::{self, LitUniforms},
+        pbr,
+        render_queue::RenderQueue,
+        shadow::{self, ShadowMap},
+        texture::Texture,
+    },
+    shaders::{MESH_FS, MESH_VS, SPRITE_FS, SPRITE_VS},
+};
+use glium::{
+    Blend, DrawParameters, VertexBuffer, implement_vertex,
+    index::{IndicesSource, NoIndices, PrimitiveType},
+    uniform,
+    vertex::MultiVerticesSource,
+};
+use std::{ops::Range, rc::Rc};
+use vek::{Vec2, Vec4};
+
+pub(crate) fn update_audio(world: &mut World, ctx: &mut Context) {
+    let dt = ctx.dt;
+    world.query::<(&GlobalTransform, &mut AudioEmitter)>(|_, (transform, audio_emitter)| {
+        for sound in audio_emitter.follow(transform.position(), dt) {
+            let options = PlayOptions {
+                emitter: Some(audio_emitter.emitter.clone()),
+                bus: audio_emitter.bus.clone(),
+                ..Default::default()
+            };
+            if let Err(err) =
+                ctx.audio
+                    .play_with(&sound, &audio_emitter.volume, &audio_emitter.speed, options)
+            {
+                eprintln!("Unable to play a sound from an AudioEmitter: {}", err);
+            }
+        }
+    });
+}
+
+#[derive(Clone, Copy)]
+struct SpriteVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+implement_vertex!(SpriteVertex, position, tex_coords, color);
+
+/// Buffers kept between frames by `World::draw`
+pub(crate) struct WorldRenderer {
+    // Used when a mesh has no texture
+    white: Texture,
+    sprite_vbo: VertexBuffer<SpriteVertex>,
+}
+
+impl WorldRenderer {
+    pub(crate) fn new(ctx: &mut Context) -> EngineResult<Self> {
+        if ctx.get_program("mesh").is_none() {
+            ctx.new_program("mesh", MESH_VS, MESH_FS, None)?;
+        }
+        if ctx.get_program("sprite").is_none() {
+            ctx.new_program("sprite", SPRITE_VS, SPRITE_FS, None)?;
+        }
+        lighting::ensure_program(ctx)?;
+        pbr::ensure_program(ctx)?;
+        Ok(Self {
+            white: Texture::from_rgba_bytes(&ctx.display, &[255; 4], (1, 1))?,
+            sprite_vbo: VertexBuffer::empty_dynamic(&ctx.display, 6 * 64)?,
+        })
+    }
+
+    pub(crate) fn draw_meshes(
+        &mut self,
+        world: &World,
+        ctx: &mut Context,
+        renderer: &mut impl Renderer,
+    ) -> EngineResult {
+        let mut meshes = Vec::new();
+        world.read_query::<(&GlobalTransform, &MeshRenderer)>(|_, (transform, mesh)| {
+            meshes.push((transform, mesh));
+        });
+        // Taken out so lit meshes can borrow it while the context is borrowed to draw
+        let shadow_map = ctx.shadow_map.take();
+        let result = self.draw_mesh_list(meshes, ctx, renderer, shadow_map.as_ref());
+        ctx.shadow_map = shadow_map;
+        result
+    }
+
+    /// Queued by each mesh's `layer` so transparent meshes are drawn after the rest, back to front
+    fn draw_mesh_list<R: Renderer>(
+        &self,
+        meshes: Vec<(&GlobalTransform, &MeshRenderer)>,
+        ctx: &mut Context,
+        renderer: &mut R,
+        shadow_map: Option<&ShadowMap>,
+    ) -> EngineResult {
+        let persp = ctx.camera.get_perspective();
+        let view = ctx.camera.get_view();
+        let mut queue = RenderQueue::new();
+        for (transform, mesh) in meshes {
+            let model = transform.get_model();
+            if let Some(material) = mesh.lit_material() {
+                let uniforms = LitUniforms::new(
+                    ctx,
+                    uniform! {
+                        persp: persp,
+                        view: view,
+                        model: model,
+                    },
+                    material,
+                    transform.position(),
+                    shadow_map,
+                );
+                queue.push(mesh.layer, transform.position(), mesh, uniforms);
+                continue;
+            }
+            let texture = mesh.texture.as_deref().unwrap_or(&self.white);
+            queue.push(
+                mesh.layer,
+                transform.position(),
+                mesh,
+                uniform! {
+                    persp: persp,
+                    view: view,
+                    model: model,
+                    color: mesh.color.into_array(),
+                    tex: texture.get_texture(),
+                },
+            );
+        }
+        queue.flush(ctx, renderer)
+    }
+
+    /// Draws every `MeshRenderer` that casts shadows into the shadow maps
+    pub(crate) fn draw_shadows(&mut self, world: &World, ctx: &mut Context) -> EngineResult {
+        let mut casters = Vec::new();
+        world.read_query::<(&GlobalTransform, &MeshRenderer)>(|_, (transform, mesh)| {
+            if mesh.casts_shadows {
+                casters.push((transform.get_matrix(), mesh));
+            }
+        });
+        shadow::draw_shadows(ctx, |caster| {
+            for (transform, mesh) in &casters {
+                caster.draw(*mesh, *transform)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Sprites are put into one buffer and drawn in a call per run of the same texture
+    pub(crate) fn draw_sprites(
+        &mut self,
+        world: &World,
+        ctx: &mut Context,
+        renderer: &mut impl Renderer,
+    ) -> EngineResult {
+        let mut sprites = Vec::new();
+        world.read_query::<(&GlobalTransform, &Sprite)>(|_, (transform, sprite)| {
+            sprites.push((transform, sprite));
+        });
+        if sprites.is_empty() {
+            return Ok(());
+        }
+        sprites.sort_by(|(a, _), (b, _)| a.position().z.total_cmp(&b.position().z));
+
+        let mut vertices = Vec::with_capacity(sprites.len() * 6);
+        let mut batches: Vec<(&Rc<Texture>, Range<usize>)> = Vec::new();
+        for (transform, sprite) in sprites.iter() {
+            let start = vertices.len();
+            push_sprite(&mut vertices, transform, sprite);
+            match batches.last_mut() {
+                Some((texture, range)) if Rc::ptr_eq(texture, &sprite.texture) => {
+                    range.end = vertices.len()
+                }
+                _ => batches.push((&sprite.texture, start..vertices.len())),
+            }
+        }
+
+        if vertices.len() > self.sprite_vbo.len() {
+            self.sprite_vbo =
+                VertexBuffer::empty_dynamic(&ctx.display, vertices.len().next_power_of_two())?;
+        }
+        if let Some(slice) = self.sprite_vbo.slice(0..vertices.len()) {
+            slice.write(&vertices);
+        }
+        for (texture, range) in batches {
+            renderer.draw(
+                ctx,
+                &SpriteBatch {
+                    vbo: &self.sprite_vbo,
+                    range,
+                },
+                &uniform! { tex: texture.get_texture() },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn push_sprite(vertices: &mut Vec<SpriteVertex>, transform: &GlobalTransform, sprite: &Sprite) {
+    let matrix = transform.get_matrix();
+    let half = sprite.size / 2.0;
+    let (u0, v0, u1, v1) = sprite.uv_rect;
+    let color = sprite.color.into_array();
+    let corner = |x: f32, y: f32, u: f32, v: f32| {
+        let position = matrix * Vec4::new(x, y, 0.0, 1.0);
+        SpriteVertex {
+            position: Vec2::new(position.x, position.y).into_array(),
+            tex_coords: [u, v],
+            color,
+        }
+    };
+    let bottom_left = corner(-half.x, -half.y, u0, v0);
+    let bottom_right = corner(half.x, -half.y, u1, v0);
+    let top_right = corner(half.x, half.y, u1, v1);
+    let top_left = corner(-half.x, half.y, u0, v1);
+    vertices.extend_from_slice(&[
+        bottom_left,
+        bottom_right,
+        top_right,
+        bottom_left,
+        top_right,
+        top_left,
+    ]);
+}
+
+impl Drawable for MeshRenderer {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        self.model.get_vbo()
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TrianglesList)
+    }
+    fn get_program(&self) -> String {
+        self.program.clone()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        self.draw_params.clone()
+    }
+}
+
+struct SpriteBatch<'a> {
+    vbo: &'a VertexBuffer<SpriteVertex>,
+    range: Range<usize>,
+}
+
+impl Drawable for SpriteBatch<'_> {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        self.vbo.slice(self.range.clone()).unwrap()
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TrianglesList)
+    }
+    fn get_program(&self) -> String {
+        "sprite".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        }
+    }
+}
@@ -4,9 +4,10 @@ use cpal::{
     BuildStreamError, DefaultStreamConfigError, DeviceNameError, DevicesError, PlayStreamError,
 };
 use glium::{
-    DrawError, ProgramCreationError,
+    DrawError, IncompatibleOpenGl, ProgramCreationError,
+    backend::glutin::DisplayCreationError,
     framebuffer::{RenderBufferCreationError, ValidationError},
-    index,
+    glutin, index,
     program::ProgramChooserCreationError,
     texture::TextureCreationError,
     vertex,
@@ -14,7 +15,7 @@ use glium::{
 };
 use image::ImageError;
 use obj::ObjError;
-use wav_io::reader::DecodeError;
+use wav_io::{reader::DecodeError, writer::EncoderError};
 
 pub type EngineResult<T = ()> = Result<T, EngineError>;
 #[derive(Debug)]
@@ -95,6 +96,21 @@ impl From<RenderBufferCreationError> for EngineError {
         Self::GliumError(value.to_string())
     }
 }
+impl From<DisplayCreationError> for EngineError {
+    fn from(value: DisplayCreationError) -> Self {
+        Self::GliumError(value.to_string())
+    }
+}
+impl From<IncompatibleOpenGl> for EngineError {
+    fn from(value: IncompatibleOpenGl) -> Self {
+        Self::GliumError(value.to_string())
+    }
+}
+impl From<glutin::error::Error> for EngineError {
+    fn from(value: glutin::error::Error) -> Self {
+        Self::GliumError(value.to_string())
+    }
+}
 
 // Image
 impl From<ImageError> for EngineError {
@@ -157,6 +173,11 @@ impl From<DecodeError> for EngineError {
         Self::AudioError(value.to_string())
     }
 }
+impl From<EncoderError> for EngineError {
+    fn from(value: EncoderError) -> Self {
+        Self::AudioError(value.to_string())
+    }
+}
 
 impl From<&str> for EngineError {
     fn from(value: &str) -> Self {
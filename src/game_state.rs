@@ -3,6 +3,7 @@ use glium::winit::event::WindowEvent;
 
 pub(crate) mod app_wrapper;
 mod dt;
+pub mod state_stack;
 
 #[allow(unused_variables)]
 pub trait GameState {
@@ -10,6 +11,27 @@ pub trait GameState {
     fn update(&mut self, ctx: &mut Context) {}
     /// Runs after update()
     fn fixed_update(&mut self, ctx: &mut Context) {}
-    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) {}
+    /// `alpha` is how far this frame is between the last two fixed updates with `LoopMode::FixedInterpolated`
+    /// and 1.0 otherwise, lerp what `fixed_update` moves by it to draw it smoothly
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, alpha: f32) {}
     fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {}
+    /// Called after the window is resized and the camera's aspect ratio is updated,
+    /// `size` is the new window size. Anything given to `Context::resize_with_window` is already resized
+    fn resized(&mut self, ctx: &mut Context, size: (u32, u32)) {}
+    /// The window stopped being focused, `ctx.audio.pause_on_focus_lost` pauses the audio for you
+    fn on_focus_lost(&mut self, ctx: &mut Context) {}
+    fn on_focus_gained(&mut self, ctx: &mut Context) {}
+    /// Fonts have already been rasterized again at the new DPI, call `Text::rebuild` on any text
+    fn on_scale_factor_changed(&mut self, ctx: &mut Context, scale_factor: f64) {}
+    /// The app was sent to the background, mostly on mobile
+    fn on_suspend(&mut self, ctx: &mut Context) {}
+    /// The app came back from the background, also called once at startup on some platforms
+    fn on_resume(&mut self, ctx: &mut Context) {}
+    /// Last thing called before the game exits, good for flushing saves
+    fn shutdown(&mut self, ctx: &mut Context) {}
+    /// Called when the window is closed or Escape is pressed, return false to keep running.
+    /// `ctx.quit()` can be called later to actually quit, after a "save before quitting?" dialog for example
+    fn on_quit_requested(&mut self, ctx: &mut Context) -> bool {
+        true
+    }
 }
@@ -1,4 +1,9 @@
-use crate::{context::Context, game_state::GameState, renderer::FrameWrapper};
+use crate::{
+    context::{Context, LoopMode, monitor_refresh_rate},
+    error::EngineResult,
+    game_state::GameState,
+    renderer::{FrameWrapper, Renderer, capture, render_target::TargetRenderer},
+};
 use glium::winit::{
     application::ApplicationHandler,
     event::{ElementState, KeyEvent, WindowEvent},
@@ -25,45 +30,165 @@ impl<G> AppWrapper<G> {
     }
 }
 
+impl<G: GameState> AppWrapper<G> {
+    /// The window is closing or Escape was pressed, the game gets a chance to say no
+    fn request_quit(&mut self) {
+        if self.game.on_quit_requested(&mut self.ctx) {
+            self.ctx.quit();
+        }
+    }
+}
+
 impl<G> ApplicationHandler for AppWrapper<G>
 where
     G: GameState,
 {
-    fn resumed(&mut self, _: &ActiveEventLoop) {}
+    fn resumed(&mut self, _: &ActiveEventLoop) {
+        self.game.on_resume(&mut self.ctx);
+    }
+    fn suspended(&mut self, _: &ActiveEventLoop) {
+        self.game.on_suspend(&mut self.ctx);
+    }
+    fn exiting(&mut self, _: &ActiveEventLoop) {
+        self.game.shutdown(&mut self.ctx);
+    }
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
         self.ctx.input.process_input(&event);
         self.game.event(&mut self.ctx, &event);
         match event {
             WindowEvent::RedrawRequested => {
-                let dt = self.dt.get_dt();
-                self.ctx.dt = dt;
-                self.game.update(&mut self.ctx);
-                self.ctx.fixed_update.accumulator += dt;
-                while self.ctx.fixed_update.accumulator > self.ctx.fixed_update.tick_rate {
-                    self.game.fixed_update(&mut self.ctx);
-                    self.ctx.fixed_update.accumulator -= self.ctx.fixed_update.tick_rate;
+                let mut dt = self.dt.get_dt();
+                self.ctx.get_frame_stats_mut().record(dt);
+                if let Some(fixed_dt) = self.ctx.capture.as_ref().and_then(|c| c.fixed_dt()) {
+                    dt = fixed_dt;
+                }
+                update(&mut self.game, &mut self.ctx, dt);
+                match self.ctx.display.draw() {
+                    Ok(frame) => {
+                        let mut frame = FrameWrapper::new(frame);
+                        if let Err(err) = draw(&mut self.game, &mut self.ctx, &mut frame) {
+                            eprintln!("Unable to draw the frame: {}", err);
+                        }
+                        frame.finish();
+                    }
+                    Err(err) => eprintln!("Unable to start drawing the frame: {}", err),
                 }
-                self.ctx.camera.update();
-                let mut frame = FrameWrapper::new(self.ctx.display.draw());
-                self.game.draw(&mut self.ctx, &mut frame);
-                frame.finish();
+                self.ctx.frame_pacing.wait();
                 self.ctx.input.reset();
-                self.ctx.window.request_redraw();
+                if !self.ctx.is_quit_requested()
+                    && let Some(window) = &self.ctx.window
+                {
+                    window.request_redraw();
+                }
+            }
+            // Minimizing resizes to 0 on some platforms
+            WindowEvent::Resized(new_size) if new_size.width > 0 && new_size.height > 0 => {
+                self.ctx.resize(new_size.into());
+                self.game.resized(&mut self.ctx, new_size.into());
+            }
+            WindowEvent::Moved(_) => {
+                if let Some(window) = &self.ctx.window {
+                    let refresh_rate = monitor_refresh_rate(window);
+                    self.ctx.frame_pacing.set_refresh_rate(refresh_rate);
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                self.ctx.audio.focus_changed(focused);
+                if focused {
+                    self.game.on_focus_gained(&mut self.ctx);
+                } else {
+                    self.game.on_focus_lost(&mut self.ctx);
+                }
             }
-            WindowEvent::Resized(new_size) => {
-                self.ctx.display.resize(new_size.into());
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                if let Err(err) = self.ctx.set_scale_factor(scale_factor) {
+                    eprintln!("Unable to rasterize fonts at the new scale factor: {}", err);
+                }
+                self.game
+                    .on_scale_factor_changed(&mut self.ctx, scale_factor);
             }
-            WindowEvent::CloseRequested
-            | WindowEvent::KeyboardInput {
+            WindowEvent::CloseRequested => self.request_quit(),
+            WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
                         state: ElementState::Pressed,
                         logical_key: Key::Named(NamedKey::Escape),
+                        repeat: false,
                         ..
                     },
                 ..
-            } => event_loop.exit(),
+            } if self.ctx.quit_on_escape => self.request_quit(),
             _ => {}
         }
+        if self.ctx.is_quit_requested() {
+            event_loop.exit();
+        }
+    }
+}
+
+/// Everything that happens each frame before drawing, shared with `run_headless_frame`
+pub(crate) fn update(game: &mut impl GameState, ctx: &mut Context, dt: f32) {
+    ctx.dt = dt;
+    game.update(ctx);
+    match ctx.fixed_update.mode {
+        LoopMode::Variable => {
+            game.fixed_update(ctx);
+            ctx.fixed_update.accumulator = 0.0;
+            ctx.fixed_update.alpha = 1.0;
+        }
+        LoopMode::Fixed | LoopMode::FixedInterpolated => {
+            ctx.fixed_update.accumulator += dt;
+            let mut steps = 0;
+            while ctx.fixed_update.accumulator > ctx.fixed_update.tick_rate {
+                if steps >= ctx.fixed_update.max_steps {
+                    // Too far behind, skip the time instead of spiralling
+                    ctx.fixed_update.accumulator %= ctx.fixed_update.tick_rate;
+                    break;
+                }
+                game.fixed_update(ctx);
+                ctx.fixed_update.accumulator -= ctx.fixed_update.tick_rate;
+                steps += 1;
+            }
+            ctx.fixed_update.alpha = if ctx.fixed_update.mode == LoopMode::FixedInterpolated {
+                (ctx.fixed_update.accumulator / ctx.fixed_update.tick_rate).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+        }
     }
+    ctx.camera.update();
+    ctx.audio.update(&ctx.camera, dt);
+}
+
+/// Draws the game to the renderer, going through the viewport's target if it has one.
+/// Shared with `run_headless_frame`
+pub(crate) fn draw(
+    game: &mut impl GameState,
+    ctx: &mut Context,
+    renderer: &mut impl Renderer,
+) -> EngineResult {
+    let alpha = ctx.fixed_update.get_alpha();
+    let Some(viewport) = ctx.viewport.take() else {
+        game.draw(ctx, renderer, alpha);
+        flush_render_queue(ctx, renderer)?;
+        capture::capture_frame(ctx, renderer);
+        return Ok(());
+    };
+    let result = TargetRenderer::new(ctx, &viewport.target)
+        .and_then(|mut target_renderer| {
+            game.draw(ctx, &mut target_renderer, alpha);
+            flush_render_queue(ctx, &mut target_renderer)?;
+            // Captures at the virtual resolution
+            capture::capture_frame(ctx, &target_renderer);
+            Ok(())
+        })
+        .and_then(|_| viewport.present(ctx, renderer));
+    ctx.viewport = Some(viewport);
+    result
+}
+
+/// Taken out of `ctx` while it's flushed since the draws need the context too
+fn flush_render_queue(ctx: &mut Context, renderer: &mut impl Renderer) -> EngineResult {
+    let mut queue = std::mem::take(&mut ctx.render_queue);
+    queue.flush(ctx, renderer)
 }
@@ -0,0 +1,381 @@
+use crate::{
+    context::Context,
+    error::EngineResult,
+    game_state::GameState,
+    renderer::{Renderer, drawable::Drawable, dyn_renderer::DynRenderer},
+    shaders::{FADE_FS, FADE_VS},
+};
+use glium::{
+    Blend, BlendingFunction, DrawParameters, LinearBlendingFactor, VertexBuffer, implement_vertex,
+    index::{IndicesSource, NoIndices, PrimitiveType},
+    uniform,
+    vertex::MultiVerticesSource,
+    winit::event::WindowEvent,
+};
+
+/// What the `StateStack` should do after a state's update
+pub enum Transition {
+    None,
+    /// Pauses the current state and puts this one on top
+    Push(Box<dyn State>),
+    /// Removes the current state and resumes the one under it, quits if there is nothing left
+    Pop,
+    /// Swaps the current state for this one
+    Replace(Box<dyn State>),
+    Quit,
+}
+
+/// One screen of the game such as a menu, the gameplay or a pause screen.
+/// Only the state on top of the stack is updated and gets events
+#[allow(unused_variables)]
+pub trait State {
+    /// Called when the state is added to the stack
+    fn init(&mut self, ctx: &mut Context) {}
+    fn update(&mut self, ctx: &mut Context) -> Transition {
+        Transition::None
+    }
+    fn fixed_update(&mut self, ctx: &mut Context) {}
+    /// Drawn straight to whatever the `StateStack` is drawn to, `alpha` is the same as in `GameState::draw`
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut DynRenderer, alpha: f32) {}
+    fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {}
+    /// Unlike the other callbacks every state on the stack gets this, not just the top one
+    fn resized(&mut self, ctx: &mut Context, size: (u32, u32)) {}
+    /// Every state gets this too
+    fn on_scale_factor_changed(&mut self, ctx: &mut Context, scale_factor: f64) {}
+    fn on_focus_lost(&mut self, ctx: &mut Context) {}
+    fn on_focus_gained(&mut self, ctx: &mut Context) {}
+    /// Every state gets this and `on_resume`, same as `GameState::on_suspend`
+    fn on_suspend(&mut self, ctx: &mut Context) {}
+    fn on_resume(&mut self, ctx: &mut Context) {}
+    /// Another state was pushed on top of this one
+    fn pause(&mut self, ctx: &mut Context) {}
+    /// The state on top of this one was popped
+    fn resume(&mut self, ctx: &mut Context) {}
+    /// Called when the state is popped or replaced, and for every state left when the game shuts down
+    fn exit(&mut self, ctx: &mut Context) {}
+    /// Same as `GameState::on_quit_requested`, only asked of the state on top
+    fn on_quit_requested(&mut self, ctx: &mut Context) -> bool {
+        true
+    }
+    /// Overlays let the states under them be drawn first, good for pause menus and dialogs
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+enum FadePhase {
+    /// Fading to the color, the transition happens once it is fully covered
+    Out(Transition),
+    In,
+}
+
+struct Fade {
+    phase: FadePhase,
+    time: f32,
+}
+
+/// Runs a stack of `State`s, pass it to `run` like any other `GameState`
+pub struct StateStack {
+    states: Vec<Box<dyn State>>,
+    fade_duration: f32,
+    fade_color: (f32, f32, f32),
+    fade: Option<Fade>,
+    fade_quad: Option<FadeQuad>,
+}
+
+impl StateStack {
+    pub fn new(state: impl State + 'static) -> Self {
+        Self {
+            states: vec![Box::new(state)],
+            fade_duration: 0.0,
+            fade_color: (0.0, 0.0, 0.0),
+            fade: None,
+            fade_quad: None,
+        }
+    }
+
+    /// Every transition fades out to `color` and back in, `duration` is the time for both in seconds
+    pub fn with_fade(mut self, duration: f32, color: (f32, f32, f32)) -> Self {
+        self.fade_duration = duration;
+        self.fade_color = color;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    fn start_transition(&mut self, ctx: &mut Context, transition: Transition) {
+        if let Transition::None = transition {
+            return;
+        }
+        if self.fade_duration > 0.0 {
+            self.fade = Some(Fade {
+                phase: FadePhase::Out(transition),
+                time: 0.0,
+            });
+        } else {
+            self.apply(ctx, transition);
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(mut state) => {
+                if let Some(top) = self.states.last_mut() {
+                    top.pause(ctx);
+                }
+                state.init(ctx);
+                self.states.push(state);
+            }
+            Transition::Pop => {
+                if let Some(mut state) = self.states.pop() {
+                    state.exit(ctx);
+                }
+                match self.states.last_mut() {
+                    Some(top) => top.resume(ctx),
+                    None => ctx.quit(),
+                }
+            }
+            Transition::Replace(mut state) => {
+                if let Some(mut old) = self.states.pop() {
+                    old.exit(ctx);
+                }
+                state.init(ctx);
+                self.states.push(state);
+            }
+            Transition::Quit => ctx.quit(),
+        }
+    }
+
+    /// 0.0 is not covered at all and 1.0 is fully covered by the fade color
+    fn fade_amount(&self) -> f32 {
+        let half = self.fade_duration / 2.0;
+        match &self.fade {
+            Some(Fade {
+                phase: FadePhase::Out(_),
+                time,
+            }) => (time / half).min(1.0),
+            Some(Fade {
+                phase: FadePhase::In,
+                time,
+            }) => 1.0 - (time / half).min(1.0),
+            None => 0.0,
+        }
+    }
+
+    fn update_fade(&mut self, ctx: &mut Context) {
+        let half = self.fade_duration / 2.0;
+        let Some(fade) = &mut self.fade else {
+            return;
+        };
+        fade.time += ctx.dt;
+        if fade.time < half {
+            return;
+        }
+        match self.fade.take() {
+            Some(Fade {
+                phase: FadePhase::Out(transition),
+                ..
+            }) => {
+                self.apply(ctx, transition);
+                self.fade = Some(Fade {
+                    phase: FadePhase::In,
+                    time: 0.0,
+                });
+            }
+            _ => self.fade = None,
+        }
+    }
+
+    fn is_fading_out(&self) -> bool {
+        matches!(
+            self.fade,
+            Some(Fade {
+                phase: FadePhase::Out(_),
+                ..
+            })
+        )
+    }
+}
+
+impl GameState for StateStack {
+    fn init(&mut self, ctx: &mut Context) {
+        if self.fade_duration > 0.0 {
+            match FadeQuad::new(ctx) {
+                Ok(quad) => self.fade_quad = Some(quad),
+                Err(err) => eprintln!(
+                    "Unable to create the state fade, states will change without it: {}",
+                    err
+                ),
+            }
+        }
+        for state in self.states.iter_mut() {
+            state.init(ctx);
+        }
+    }
+    fn update(&mut self, ctx: &mut Context) {
+        self.update_fade(ctx);
+        // Input is ignored until the transition has happened
+        if self.is_fading_out() {
+            return;
+        }
+        let transition = match self.states.last_mut() {
+            Some(state) => state.update(ctx),
+            None => Transition::Quit,
+        };
+        self.start_transition(ctx, transition);
+    }
+    fn fixed_update(&mut self, ctx: &mut Context) {
+        if self.is_fading_out() {
+            return;
+        }
+        if let Some(state) = self.states.last_mut() {
+            state.fixed_update(ctx);
+        }
+    }
+    fn draw(&mut self, ctx: &mut Context, renderer: &mut impl Renderer, alpha: f32) {
+        renderer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        {
+            let mut dyn_renderer = DynRenderer::new(renderer);
+            // Starts at the highest state that isn't an overlay
+            let first_visible = self
+                .states
+                .iter()
+                .rposition(|state| !state.is_overlay())
+                .unwrap_or(0);
+            for state in self.states[first_visible..].iter_mut() {
+                state.draw(ctx, &mut dyn_renderer, alpha);
+            }
+        }
+
+        // Drawn over whatever the states drew, so nothing has to go offscreen
+        let fade_amount = self.fade_amount();
+        if fade_amount > 0.0
+            && let Some(quad) = &self.fade_quad
+        {
+            let (r, g, b) = self.fade_color;
+            if let Err(err) =
+                renderer.draw(ctx, quad, &uniform! { fade_color: [r, g, b, fade_amount] })
+            {
+                eprintln!("Unable to draw the state fade: {}", err);
+            }
+        }
+    }
+    fn event(&mut self, ctx: &mut Context, event: &WindowEvent) {
+        if let Some(state) = self.states.last_mut() {
+            state.event(ctx, event);
+        }
+    }
+    fn resized(&mut self, ctx: &mut Context, size: (u32, u32)) {
+        for state in self.states.iter_mut() {
+            state.resized(ctx, size);
+        }
+    }
+    fn on_scale_factor_changed(&mut self, ctx: &mut Context, scale_factor: f64) {
+        for state in self.states.iter_mut() {
+            state.on_scale_factor_changed(ctx, scale_factor);
+        }
+    }
+    fn on_focus_lost(&mut self, ctx: &mut Context) {
+        if let Some(state) = self.states.last_mut() {
+            state.on_focus_lost(ctx);
+        }
+    }
+    fn on_focus_gained(&mut self, ctx: &mut Context) {
+        if let Some(state) = self.states.last_mut() {
+            state.on_focus_gained(ctx);
+        }
+    }
+    fn on_suspend(&mut self, ctx: &mut Context) {
+        for state in self.states.iter_mut() {
+            state.on_suspend(ctx);
+        }
+    }
+    fn on_resume(&mut self, ctx: &mut Context) {
+        for state in self.states.iter_mut() {
+            state.on_resume(ctx);
+        }
+    }
+    fn on_quit_requested(&mut self, ctx: &mut Context) -> bool {
+        self.states
+            .last_mut()
+            .is_none_or(|state| state.on_quit_requested(ctx))
+    }
+    /// Every state left on the stack is exited, top first
+    fn shutdown(&mut self, ctx: &mut Context) {
+        while let Some(mut state) = self.states.pop() {
+            state.exit(ctx);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct FadeVertex {
+    position: [f32; 2],
+}
+implement_vertex!(FadeVertex, position);
+
+/// Covers the whole screen
+struct FadeQuad {
+    vbo: VertexBuffer<FadeVertex>,
+}
+
+impl FadeQuad {
+    fn new(ctx: &mut Context) -> EngineResult<Self> {
+        if ctx.get_program("state_fade").is_none() {
+            ctx.new_program("state_fade", FADE_VS, FADE_FS, None)?;
+        }
+        Ok(Self {
+            vbo: VertexBuffer::new(
+                &ctx.display,
+                &[
+                    FadeVertex {
+                        position: [-1.0, -1.0],
+                    },
+                    FadeVertex {
+                        position: [1.0, -1.0],
+                    },
+                    FadeVertex {
+                        position: [-1.0, 1.0],
+                    },
+                    FadeVertex {
+                        position: [1.0, 1.0],
+                    },
+                ],
+            )?,
+        })
+    }
+}
+
+impl Drawable for FadeQuad {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        &self.vbo
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TriangleStrip)
+    }
+    fn get_program(&self) -> String {
+        "state_fade".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        DrawParameters {
+            // Leaves the alpha alone so screenshots don't end up see through
+            blend: Blend {
+                alpha: BlendingFunction::Addition {
+                    source: LinearBlendingFactor::Zero,
+                    destination: LinearBlendingFactor::One,
+                },
+                ..Blend::alpha_blending()
+            },
+            ..Default::default()
+        }
+    }
+}
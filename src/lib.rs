@@ -5,6 +5,8 @@
 //! something that implements the [Drawable](https://docs.rs/bugsyth_engine/0.4.0/bugsyth_engine/renderer/drawable/trait.Drawable.html) trait,
 //! a struct that implements [GameState](https://docs.rs/bugsyth_engine/0.4.0/bugsyth_engine/game_state/trait.GameState.html) to hold your game's data,
 //! and then finally to run it all by calling [run](https://docs.rs/bugsyth_engine/0.4.0/bugsyth_engine/fn.run.html)
+//!
+//! For tests without a display, use `init_headless` and step frames with `run_headless_frame`
 
 /*
     game_wrapper will hold all the code that runs the game
@@ -13,15 +15,27 @@
     I feel like some of them don't make sense and could be moved around
 */
 
-use context::Context;
-use error::EngineResult;
-use game_state::{GameState, app_wrapper::AppWrapper};
-use glium::{backend::glutin::SimpleWindowBuilder, winit::event_loop::EventLoop};
+use context::{
+    Context, display,
+    frame_pacing::Vsync,
+    window::{self, WindowConfig},
+};
+use error::{EngineError, EngineResult};
+use game_state::{
+    GameState,
+    app_wrapper::{self, AppWrapper},
+};
+use glium::winit::event_loop::EventLoop;
+use image::RgbaImage;
+use renderer::render_target::TargetRenderer;
 
+pub mod collision3d;
 pub mod context;
+pub mod ecs;
 pub mod error;
 pub mod game_state;
 pub mod math;
+pub mod physics2d;
 pub mod prelude;
 pub mod renderer;
 pub mod shaders;
@@ -35,12 +49,26 @@ pub fn init(
     window_title: impl Into<String>,
     window_size: (u32, u32),
 ) -> EngineResult<(EventLoop<()>, Context)> {
+    init_with_vsync(window_title, window_size, Vsync::On)
+}
+
+/// Same as `init` but picks how the window waits for the monitor, this can't be changed later.
+/// A frame rate cap can be set any time with `ctx.frame_pacing.target_fps`
+pub fn init_with_vsync(
+    window_title: impl Into<String>,
+    window_size: (u32, u32),
+    vsync: Vsync,
+) -> EngineResult<(EventLoop<()>, Context)> {
+    init_with_config(WindowConfig::new(window_title, window_size).with_vsync(vsync))
+}
+/// Same as `init` but with everything about the window and its OpenGL surface set by `WindowConfig`
+pub fn init_with_config(config: WindowConfig) -> EngineResult<(EventLoop<()>, Context)> {
     let event_loop = EventLoop::new()?;
-    let (window, display) = SimpleWindowBuilder::new()
-        .with_title(&window_title.into())
-        .with_inner_size(window_size.0, window_size.1)
-        .build(&event_loop);
-    Ok((event_loop, Context::new(window, display)?))
+    let (window, display, vsync) = display::create_window(&event_loop, &config)?;
+    if config.fullscreen != window::FullscreenMode::Windowed {
+        window::set_fullscreen(&window, config.fullscreen);
+    }
+    Ok((event_loop, Context::new(window, display, vsync, &config)?))
 }
 
 pub fn run(game: impl GameState, event_loop: EventLoop<()>, ctx: Context) -> EngineResult {
@@ -50,6 +78,33 @@ pub fn run(game: impl GameState, event_loop: EventLoop<()>, ctx: Context) -> Eng
     Ok(())
 }
 
+/// Creates a `Context` with an offscreen OpenGL context and no window, made for tests on machines
+/// without a display. Uses EGL so Mesa's software rasterizer works when there is no GPU
+pub fn init_headless(size: (u32, u32)) -> EngineResult<Context> {
+    Context::new_headless(size)
+}
+
+/// Runs a single frame of the game with `dt` as the delta time and returns what was drawn.
+/// `game.init` has to be called before the first frame
+pub fn run_headless_frame(
+    game: &mut impl GameState,
+    ctx: &mut Context,
+    dt: f32,
+) -> EngineResult<RgbaImage> {
+    // Taken out of the context so the game can still borrow the context while drawing
+    let target = ctx.headless_target.take().ok_or_else(|| {
+        EngineError::Error("run_headless_frame needs a context from init_headless".to_string())
+    })?;
+    ctx.get_frame_stats_mut().record(dt);
+    app_wrapper::update(game, ctx, dt);
+    let result = TargetRenderer::new(ctx, &target)
+        .and_then(|mut renderer| app_wrapper::draw(game, ctx, &mut renderer));
+    ctx.input.reset();
+    let image = result.and_then(|_| target.read_image());
+    ctx.headless_target = Some(target);
+    image
+}
+
 /// Creates an instant to make measuring times easier
 #[macro_export]
 macro_rules! start_timer {
@@ -1 +1,4 @@
+pub use model_transform::Transform;
 pub use vek::*;
+
+mod model_transform;
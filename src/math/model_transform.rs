@@ -0,0 +1,63 @@
+use vek::{Mat4, Quaternion, Vec3};
+
+/// Position, rotation and scale of something in the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: Vec3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vec3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Vec3::zero(),
+            rotation: Quaternion::identity(),
+            scale: Vec3::one(),
+        }
+    }
+}
+
+impl Transform {
+    pub fn new(position: Vec3<f32>) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+    pub fn with_rotation(mut self, rotation: Quaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    pub fn with_scale(mut self, scale: Vec3<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Scales, then rotates, then moves
+    pub fn get_matrix(&self) -> Mat4<f32> {
+        Mat4::<f32>::translation_3d(self.position)
+            * Mat4::<f32>::from(self.rotation)
+            * Mat4::<f32>::scaling_3d(self.scale)
+    }
+    /// `get_matrix` in the format glium uniforms take, same as `CameraState::get_view`
+    pub fn get_model(&self) -> [[f32; 4]; 4] {
+        self.get_matrix().into_col_arrays()
+    }
+
+    /// Rotates on top of the current rotation
+    pub fn rotate(&mut self, rotation: Quaternion<f32>) {
+        self.rotation = (rotation * self.rotation).normalized();
+    }
+
+    /// Negative z, the way the camera looks
+    pub fn forward(&self) -> Vec3<f32> {
+        self.rotation * -Vec3::<f32>::unit_z()
+    }
+    pub fn right(&self) -> Vec3<f32> {
+        self.rotation * Vec3::<f32>::unit_x()
+    }
+    pub fn up(&self) -> Vec3<f32> {
+        self.rotation * Vec3::<f32>::unit_y()
+    }
+}
@@ -0,0 +1,459 @@
+use crate::context::{Context, LoopMode};
+use body::{BodyType, RigidBody};
+use collision::ContactPoint;
+use shape::{Core, cross, perp};
+use std::collections::HashSet;
+use vek::{Aabr, Vec2};
+
+pub mod body;
+mod broadphase;
+mod collision;
+pub mod shape;
+
+// Overlap that's left alone so resting bodies don't jitter
+const PENETRATION_SLOP: f32 = 0.01;
+// How much of the overlap is pushed out each step
+const POSITION_CORRECTION: f32 = 0.8;
+// Slower hits than this don't bounce so things can come to rest
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+
+/// Id of a body in a `PhysicsWorld2d`, old ids never point at new bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// Sent when two colliders start or stop touching, `a` is always the smaller handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionEvent {
+    Started {
+        a: BodyHandle,
+        b: BodyHandle,
+        /// One of them is a trigger, nothing was pushed
+        trigger: bool,
+    },
+    Stopped {
+        a: BodyHandle,
+        b: BodyHandle,
+        trigger: bool,
+    },
+}
+
+/// Where a ray hit a body
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub body: BodyHandle,
+    pub point: Vec2<f32>,
+    pub normal: Vec2<f32>,
+    pub distance: f32,
+}
+
+/// Holds the bodies and moves them, call `fixed_update` from `GameState::fixed_update`
+pub struct PhysicsWorld2d {
+    pub gravity: Vec2<f32>,
+    /// Times the contacts are solved each step, more makes stacks steadier. Default is 8
+    pub iterations: u32,
+    bodies: Vec<Option<RigidBody>>,
+    generations: Vec<u32>,
+    free: Vec<u32>,
+    touching: HashSet<(BodyHandle, BodyHandle)>,
+    events: Vec<CollisionEvent>,
+}
+
+impl Default for PhysicsWorld2d {
+    fn default() -> Self {
+        Self::new(Vec2::new(0.0, -9.81))
+    }
+}
+
+struct SolverContact {
+    a: usize,
+    b: usize,
+    normal: Vec2<f32>,
+    depth: f32,
+    // Each point of a pair does its share of pushing them apart
+    share: f32,
+    a_offset: Vec2<f32>,
+    b_offset: Vec2<f32>,
+    normal_mass: f32,
+    tangent_mass: f32,
+    bounce: f32,
+    friction: f32,
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+impl PhysicsWorld2d {
+    pub fn new(gravity: Vec2<f32>) -> Self {
+        Self {
+            gravity,
+            iterations: 8,
+            bodies: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            touching: HashSet::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, body: RigidBody) -> BodyHandle {
+        match self.free.pop() {
+            Some(index) => {
+                self.bodies[index as usize] = Some(body);
+                BodyHandle {
+                    index,
+                    generation: self.generations[index as usize],
+                }
+            }
+            None => {
+                self.bodies.push(Some(body));
+                self.generations.push(0);
+                BodyHandle {
+                    index: self.bodies.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Anything it was touching gets a `Stopped` event on the next step
+    pub fn remove(&mut self, handle: BodyHandle) -> Option<RigidBody> {
+        self.get(handle)?;
+        let index = handle.index as usize;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(handle.index);
+        self.bodies[index].take()
+    }
+
+    pub fn get(&self, handle: BodyHandle) -> Option<&RigidBody> {
+        if *self.generations.get(handle.index as usize)? != handle.generation {
+            return None;
+        }
+        self.bodies[handle.index as usize].as_ref()
+    }
+    pub fn get_mut(&mut self, handle: BodyHandle) -> Option<&mut RigidBody> {
+        if *self.generations.get(handle.index as usize)? != handle.generation {
+            return None;
+        }
+        self.bodies[handle.index as usize].as_mut()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BodyHandle, &RigidBody)> {
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(index, body)| Some((self.handle(index), body.as_ref()?)))
+    }
+    pub fn len(&self) -> usize {
+        self.bodies.len() - self.free.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Steps by `ctx.fixed_update.tick_rate`, or by `ctx.dt` with `LoopMode::Variable`
+    pub fn fixed_update(&mut self, ctx: &Context) {
+        match ctx.fixed_update.mode {
+            LoopMode::Variable => self.step(ctx.dt),
+            LoopMode::Fixed | LoopMode::FixedInterpolated => self.step(ctx.fixed_update.tick_rate),
+        }
+    }
+
+    /// Moves everything forward by `dt` seconds and works out the collision events
+    pub fn step(&mut self, dt: f32) {
+        self.events.clear();
+        if dt <= 0.0 {
+            return;
+        }
+
+        for body in self.bodies.iter_mut().flatten() {
+            let (force, torque) = body.take_forces();
+            if body.body_type != BodyType::Dynamic {
+                continue;
+            }
+            body.velocity += (self.gravity * body.gravity_scale + force * body.inverse_mass()) * dt;
+            body.angular_velocity += torque * body.inverse_inertia() * dt;
+            body.velocity *= 1.0 / (1.0 + dt * body.linear_damping);
+            body.angular_velocity *= 1.0 / (1.0 + dt * body.angular_damping);
+            if body.fixed_rotation {
+                body.angular_velocity = 0.0;
+            }
+        }
+
+        let mut contacts = self.find_contacts();
+        self.solve_velocities(&mut contacts);
+
+        for body in self.bodies.iter_mut().flatten() {
+            if body.body_type == BodyType::Static {
+                continue;
+            }
+            body.position += body.velocity * dt;
+            body.rotation += body.angular_velocity * dt;
+        }
+        self.correct_positions(&contacts);
+    }
+
+    /// Events from the last step, read them in `fixed_update` right after stepping
+    pub fn events(&self) -> &[CollisionEvent] {
+        &self.events
+    }
+
+    pub fn is_touching(&self, a: BodyHandle, b: BodyHandle) -> bool {
+        self.touching.contains(&(a.min(b), a.max(b)))
+    }
+    /// Every body touching this one
+    pub fn get_touching(&self, handle: BodyHandle) -> Vec<BodyHandle> {
+        self.touching
+            .iter()
+            .filter_map(|(a, b)| {
+                if *a == handle {
+                    Some(*b)
+                } else if *b == handle {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Closest body along the ray whose layer is in `mask`, triggers are skipped
+    pub fn raycast(
+        &self,
+        origin: Vec2<f32>,
+        direction: Vec2<f32>,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<RayHit> {
+        if direction.magnitude_squared() == 0.0 {
+            return None;
+        }
+        let direction = direction.normalized();
+        self.iter()
+            .filter(|(_, body)| !body.collider.is_trigger && body.collider.layer & mask != 0)
+            .filter_map(|(handle, body)| {
+                let (distance, normal) = body
+                    .collider
+                    .shape
+                    .core(body.position, body.rotation)
+                    .raycast(origin, direction, max_distance)?;
+                Some(RayHit {
+                    body: handle,
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Bodies whose shape has the point in it
+    pub fn query_point(&self, point: Vec2<f32>) -> Vec<BodyHandle> {
+        self.iter()
+            .filter(|(_, body)| {
+                body.collider
+                    .shape
+                    .core(body.position, body.rotation)
+                    .contains(point)
+            })
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+    /// Bodies whose bounding box overlaps `aabr`
+    pub fn query_aabr(&self, aabr: Aabr<f32>) -> Vec<BodyHandle> {
+        self.iter()
+            .filter(|(_, body)| {
+                body.collider
+                    .shape
+                    .core(body.position, body.rotation)
+                    .aabr()
+                    .collides_with_aabr(aabr)
+            })
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    fn handle(&self, index: usize) -> BodyHandle {
+        BodyHandle {
+            index: index as u32,
+            generation: self.generations[index],
+        }
+    }
+
+    /// Checks the pairs from the broadphase, updates `touching` and sends the events
+    fn find_contacts(&mut self) -> Vec<SolverContact> {
+        let mut indices = Vec::new();
+        let mut cores: Vec<Core> = Vec::new();
+        for (index, body) in self.bodies.iter().enumerate() {
+            if let Some(body) = body {
+                indices.push(index);
+                cores.push(body.collider.shape.core(body.position, body.rotation));
+            }
+        }
+        let aabrs: Vec<Aabr<f32>> = cores.iter().map(Core::aabr).collect();
+
+        let mut contacts = Vec::new();
+        let mut touching = HashSet::new();
+        for (i, j) in broadphase::overlapping_pairs(&aabrs) {
+            let (a_index, b_index) = (indices[i], indices[j]);
+            let (Some(a), Some(b)) = (&self.bodies[a_index], &self.bodies[b_index]) else {
+                continue;
+            };
+            if (a.body_type == BodyType::Static && b.body_type == BodyType::Static)
+                || !a.collider.hits(&b.collider)
+            {
+                continue;
+            }
+            let Some(contact) = collision::collide(&cores[i], &cores[j]) else {
+                continue;
+            };
+            let trigger = a.collider.is_trigger || b.collider.is_trigger;
+            let pair = (self.handle(a_index), self.handle(b_index));
+            if !self.touching.contains(&pair) {
+                self.events.push(CollisionEvent::Started {
+                    a: pair.0,
+                    b: pair.1,
+                    trigger,
+                });
+            }
+            touching.insert(pair);
+            if !trigger && (a.inverse_mass() > 0.0 || b.inverse_mass() > 0.0) {
+                let share = 1.0 / contact.points.len() as f32;
+                for point in &contact.points {
+                    contacts.push(SolverContact::new(
+                        a_index,
+                        a,
+                        b_index,
+                        b,
+                        contact.normal,
+                        point,
+                        share,
+                    ));
+                }
+            }
+        }
+
+        for (a, b) in self.touching.difference(&touching) {
+            let trigger = [a, b].into_iter().any(|handle| {
+                self.get(*handle)
+                    .is_some_and(|body| body.collider.is_trigger)
+            });
+            self.events.push(CollisionEvent::Stopped {
+                a: *a,
+                b: *b,
+                trigger,
+            });
+        }
+        self.touching = touching;
+        contacts
+    }
+
+    /// Sequential impulses, each contact is fixed on its own a few times over
+    fn solve_velocities(&mut self, contacts: &mut [SolverContact]) {
+        for _ in 0..self.iterations {
+            for contact in contacts.iter_mut() {
+                let (a, b) = self.pair_mut(contact.a, contact.b);
+                let normal = contact.normal;
+                let tangent = perp(normal);
+                let relative = b.velocity_at(b.position + contact.b_offset)
+                    - a.velocity_at(a.position + contact.a_offset);
+
+                let normal_speed = relative.dot(normal);
+                let impulse = contact.normal_mass * (contact.bounce - normal_speed);
+                let total = (contact.normal_impulse + impulse).max(0.0);
+                let impulse = total - contact.normal_impulse;
+                contact.normal_impulse = total;
+                apply(a, b, contact, normal * impulse);
+
+                let relative = b.velocity_at(b.position + contact.b_offset)
+                    - a.velocity_at(a.position + contact.a_offset);
+                let max_friction = contact.friction * contact.normal_impulse;
+                let impulse = contact.tangent_mass * -relative.dot(tangent);
+                let total = (contact.tangent_impulse + impulse).clamp(-max_friction, max_friction);
+                let impulse = total - contact.tangent_impulse;
+                contact.tangent_impulse = total;
+                apply(a, b, contact, tangent * impulse);
+            }
+        }
+    }
+
+    /// Pushes overlapping bodies apart so they don't sink into each other over time
+    fn correct_positions(&mut self, contacts: &[SolverContact]) {
+        for contact in contacts {
+            let (a, b) = self.pair_mut(contact.a, contact.b);
+            let (a_inverse_mass, b_inverse_mass) = (a.inverse_mass(), b.inverse_mass());
+            let depth = contact.depth - PENETRATION_SLOP;
+            if depth <= 0.0 {
+                continue;
+            }
+            let correction = contact.normal * depth * contact.share * POSITION_CORRECTION
+                / (a_inverse_mass + b_inverse_mass);
+            a.position -= correction * a_inverse_mass;
+            b.position += correction * b_inverse_mass;
+        }
+    }
+
+    fn pair_mut(&mut self, a: usize, b: usize) -> (&mut RigidBody, &mut RigidBody) {
+        let [Some(a), Some(b)] = self.bodies.get_disjoint_mut([a, b]).unwrap() else {
+            unreachable!("contacts are only made between bodies that exist")
+        };
+        (a, b)
+    }
+}
+
+impl SolverContact {
+    fn new(
+        a_index: usize,
+        a: &RigidBody,
+        b_index: usize,
+        b: &RigidBody,
+        normal: Vec2<f32>,
+        point: &ContactPoint,
+        share: f32,
+    ) -> Self {
+        let a_offset = point.point - a.position;
+        let b_offset = point.point - b.position;
+        let effective_mass = |direction: Vec2<f32>| {
+            let a_cross = cross(a_offset, direction);
+            let b_cross = cross(b_offset, direction);
+            let inverse = a.inverse_mass()
+                + b.inverse_mass()
+                + a_cross * a_cross * a.inverse_inertia()
+                + b_cross * b_cross * b.inverse_inertia();
+            if inverse > 0.0 { 1.0 / inverse } else { 0.0 }
+        };
+
+        let relative = b.velocity_at(point.point) - a.velocity_at(point.point);
+        let normal_speed = relative.dot(normal);
+        let restitution = a.collider.restitution.max(b.collider.restitution);
+        let bounce = if normal_speed < -RESTITUTION_THRESHOLD {
+            -restitution * normal_speed
+        } else {
+            0.0
+        };
+
+        Self {
+            a: a_index,
+            b: b_index,
+            normal,
+            depth: point.depth,
+            share,
+            a_offset,
+            b_offset,
+            normal_mass: effective_mass(normal),
+            tangent_mass: effective_mass(perp(normal)),
+            bounce,
+            friction: (a.collider.friction * b.collider.friction).sqrt(),
+            normal_impulse: 0.0,
+            tangent_impulse: 0.0,
+        }
+    }
+}
+
+fn apply(a: &mut RigidBody, b: &mut RigidBody, contact: &SolverContact, impulse: Vec2<f32>) {
+    a.velocity -= impulse * a.inverse_mass();
+    a.angular_velocity -= cross(contact.a_offset, impulse) * a.inverse_inertia();
+    b.velocity += impulse * b.inverse_mass();
+    b.angular_velocity += cross(contact.b_offset, impulse) * b.inverse_inertia();
+}
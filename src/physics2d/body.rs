@@ -0,0 +1,203 @@
+use crate::physics2d::shape::{Shape, cross};
+use vek::Vec2;
+
+/// How a body is moved by the `PhysicsWorld2d`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyType {
+    /// Never moves, good for the level
+    Static,
+    /// Moves by its velocity but nothing pushes it, good for moving platforms
+    Kinematic,
+    /// Has mass, is pulled by gravity and gets pushed around
+    Dynamic,
+}
+
+/// Shape and surface of a body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collider {
+    pub shape: Shape,
+    /// Mass per unit of area. Default is 1.0
+    pub density: f32,
+    /// 0.0 is ice. Default is 0.5
+    pub friction: f32,
+    /// Bounciness, 0.0 stops dead and 1.0 bounces back at the same speed. Default is 0.0
+    pub restitution: f32,
+    /// Only sends collision events, nothing gets pushed
+    pub is_trigger: bool,
+    /// Bits of what this collider is. Default is 1
+    pub layer: u32,
+    /// Bits of the layers this collider hits, both colliders have to hit each other. Default is all of them
+    pub mask: u32,
+}
+
+impl Collider {
+    pub fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            is_trigger: false,
+            layer: 1,
+            mask: u32::MAX,
+        }
+    }
+    pub fn circle(radius: f32) -> Self {
+        Self::new(Shape::Circle { radius })
+    }
+    /// Takes the full width and height
+    pub fn rect(width: f32, height: f32) -> Self {
+        Self::new(Shape::Box {
+            half_extents: Vec2::new(width, height) / 2.0,
+        })
+    }
+    /// `height` is from the bottom of the pill to the top
+    pub fn capsule(height: f32, radius: f32) -> Self {
+        Self::new(Shape::Capsule {
+            half_height: (height / 2.0 - radius).max(0.0),
+            radius,
+        })
+    }
+
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+    pub fn as_trigger(mut self) -> Self {
+        self.is_trigger = true;
+        self
+    }
+    pub fn with_layers(mut self, layer: u32, mask: u32) -> Self {
+        self.layer = layer;
+        self.mask = mask;
+        self
+    }
+
+    pub(crate) fn hits(&self, other: &Collider) -> bool {
+        self.layer & other.mask != 0 && other.layer & self.mask != 0
+    }
+}
+
+/// Something in a `PhysicsWorld2d`, rotation is in radians counter clockwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct RigidBody {
+    pub body_type: BodyType,
+    pub collider: Collider,
+    pub position: Vec2<f32>,
+    pub rotation: f32,
+    pub velocity: Vec2<f32>,
+    pub angular_velocity: f32,
+    /// Multiplied with the world's gravity. Default is 1.0
+    pub gravity_scale: f32,
+    /// Stops the body from spinning, good for platformer characters
+    pub fixed_rotation: bool,
+    /// How much velocity is lost every second. Default is 0.0
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    force: Vec2<f32>,
+    torque: f32,
+}
+
+impl RigidBody {
+    pub fn new(body_type: BodyType, collider: Collider, position: Vec2<f32>) -> Self {
+        Self {
+            body_type,
+            collider,
+            position,
+            rotation: 0.0,
+            velocity: Vec2::zero(),
+            angular_velocity: 0.0,
+            gravity_scale: 1.0,
+            fixed_rotation: false,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            force: Vec2::zero(),
+            torque: 0.0,
+        }
+    }
+    pub fn new_static(collider: Collider, position: Vec2<f32>) -> Self {
+        Self::new(BodyType::Static, collider, position)
+    }
+    pub fn new_kinematic(collider: Collider, position: Vec2<f32>) -> Self {
+        Self::new(BodyType::Kinematic, collider, position)
+    }
+    pub fn new_dynamic(collider: Collider, position: Vec2<f32>) -> Self {
+        Self::new(BodyType::Dynamic, collider, position)
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    pub fn with_velocity(mut self, velocity: Vec2<f32>) -> Self {
+        self.velocity = velocity;
+        self
+    }
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+    pub fn with_fixed_rotation(mut self) -> Self {
+        self.fixed_rotation = true;
+        self
+    }
+    pub fn with_damping(mut self, linear_damping: f32, angular_damping: f32) -> Self {
+        self.linear_damping = linear_damping;
+        self.angular_damping = angular_damping;
+        self
+    }
+
+    /// 0.0 for anything that isn't dynamic
+    pub fn mass(&self) -> f32 {
+        match self.body_type {
+            BodyType::Dynamic => self.collider.shape.area() * self.collider.density,
+            _ => 0.0,
+        }
+    }
+    pub(crate) fn inverse_mass(&self) -> f32 {
+        let mass = self.mass();
+        if mass > 0.0 { 1.0 / mass } else { 0.0 }
+    }
+    pub(crate) fn inverse_inertia(&self) -> f32 {
+        let inertia = self.mass() * self.collider.shape.unit_inertia();
+        if self.fixed_rotation || inertia <= 0.0 {
+            0.0
+        } else {
+            1.0 / inertia
+        }
+    }
+
+    /// Pushes the body during the next step, the force is cleared after
+    pub fn apply_force(&mut self, force: Vec2<f32>) {
+        self.force += force;
+    }
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.torque += torque;
+    }
+    /// Changes the velocity right away, `point` is in world space
+    pub fn apply_impulse(&mut self, impulse: Vec2<f32>, point: Vec2<f32>) {
+        self.velocity += impulse * self.inverse_mass();
+        self.angular_velocity += cross(point - self.position, impulse) * self.inverse_inertia();
+    }
+
+    /// Velocity of a point on the body in world space
+    pub fn velocity_at(&self, point: Vec2<f32>) -> Vec2<f32> {
+        let offset = point - self.position;
+        self.velocity + Vec2::new(-offset.y, offset.x) * self.angular_velocity
+    }
+
+    pub(crate) fn take_forces(&mut self) -> (Vec2<f32>, f32) {
+        (
+            std::mem::take(&mut self.force),
+            std::mem::take(&mut self.torque),
+        )
+    }
+}
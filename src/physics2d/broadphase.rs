@@ -0,0 +1,22 @@
+use vek::Aabr;
+
+/// Pairs of indices into `aabrs` whose boxes overlap.
+/// Sweep and prune, the boxes are sorted along x so only neighbours are compared
+pub(crate) fn overlapping_pairs(aabrs: &[Aabr<f32>]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..aabrs.len()).collect();
+    order.sort_by(|a, b| aabrs[*a].min.x.total_cmp(&aabrs[*b].min.x));
+
+    let mut pairs = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for index in order {
+        let aabr = aabrs[index];
+        active.retain(|other| aabrs[*other].max.x >= aabr.min.x);
+        for other in &active {
+            if aabrs[*other].collides_with_aabr(aabr) {
+                pairs.push(((*other).min(index), (*other).max(index)));
+            }
+        }
+        active.push(index);
+    }
+    pairs
+}
@@ -0,0 +1,248 @@
+use crate::physics2d::shape::{Core, closest_on_segment, perp};
+use vek::Vec2;
+
+// Points closer than this along the normal count as one side touching
+const FEATURE_TOLERANCE: f32 = 0.005;
+
+/// How two shapes overlap, `normal` points from the first shape to the second
+#[derive(Debug, Clone)]
+pub(crate) struct Contact {
+    pub(crate) normal: Vec2<f32>,
+    /// One point, or two when sides lie against each other so a box can rest flat
+    pub(crate) points: Vec<ContactPoint>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ContactPoint {
+    pub(crate) point: Vec2<f32>,
+    pub(crate) depth: f32,
+}
+
+pub(crate) fn collide(a: &Core, b: &Core) -> Option<Contact> {
+    match separating_axis(a, b) {
+        Some(contact) => Some(contact),
+        None => closest_points(a, b),
+    }
+}
+
+/// Used when the cores themselves overlap, the radii are only added on top
+fn separating_axis(a: &Core, b: &Core) -> Option<Contact> {
+    let mut axes = Vec::new();
+    for core in [a, b] {
+        for (start, end) in core.sides() {
+            if start == end {
+                continue;
+            }
+            let direction = (end - start).normalized();
+            axes.push(perp(direction));
+            // A point can be in line with a capsule's core without touching it
+            if core.points.len() == 2 {
+                axes.push(direction);
+            }
+        }
+    }
+    if axes.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(f32, Vec2<f32>)> = None;
+    for axis in axes {
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        let forward = a_max - b_min;
+        let backward = b_max - a_min;
+        if forward <= 0.0 || backward <= 0.0 {
+            return None;
+        }
+        let (overlap, normal) = if forward < backward {
+            (forward, axis)
+        } else {
+            (backward, -axis)
+        };
+        if best.is_none_or(|(depth, _)| overlap < depth) {
+            best = Some((overlap, normal));
+        }
+    }
+    let (overlap, normal) = best?;
+    let points = clip_sides(a, b, normal)
+        .filter(|points| !points.is_empty())
+        .unwrap_or_else(|| {
+            vec![ContactPoint {
+                point: contact_point(a, b, normal),
+                depth: overlap + a.radius + b.radius,
+            }]
+        });
+    Some(Contact { normal, points })
+}
+
+/// The side most in line with `normal` is the reference, the other core's side facing it is
+/// clipped to the ends of the reference side and whatever is still overlapping are the contact points.
+/// `None` when either core has no sides
+fn clip_sides(a: &Core, b: &Core, normal: Vec2<f32>) -> Option<Vec<ContactPoint>> {
+    let (a_side, a_normal) = facing_side(a, normal)?;
+    let (b_side, b_normal) = facing_side(b, -normal)?;
+    let (reference, reference_normal, reference_core, incident_core) =
+        if b_normal.dot(-normal) > a_normal.dot(normal) + FEATURE_TOLERANCE {
+            (b_side, b_normal, b, a)
+        } else {
+            (a_side, a_normal, a, b)
+        };
+    let (incident, _) = facing_side(incident_core, -reference_normal)?;
+
+    let [start, end] = reference;
+    let tangent = (end - start).normalized();
+    let incident = clip(incident, -tangent, -tangent.dot(start))?;
+    let incident = clip(incident, tangent, tangent.dot(end))?;
+
+    let radii = a.radius + b.radius;
+    let points = incident
+        .into_iter()
+        .filter_map(|point| {
+            let separation = (point - start).dot(reference_normal);
+            let depth = radii - separation;
+            // Halfway between the two surfaces
+            let offset = (reference_core.radius - incident_core.radius - separation) / 2.0;
+            (depth > 0.0).then(|| ContactPoint {
+                point: point + reference_normal * offset,
+                depth,
+            })
+        })
+        .collect();
+    Some(points)
+}
+
+/// Side of the core with its outward normal most in line with `direction`, and that normal
+fn facing_side(core: &Core, direction: Vec2<f32>) -> Option<([Vec2<f32>; 2], Vec2<f32>)> {
+    core.sides()
+        .filter(|(start, end)| start != end)
+        .map(|(start, end)| {
+            // Counter clockwise so outward is to the right
+            let normal = -perp((end - start).normalized());
+            // A capsule's one side faces both ways
+            let normal = if core.points.len() == 2 && normal.dot(direction) < 0.0 {
+                -normal
+            } else {
+                normal
+            };
+            ([start, end], normal)
+        })
+        .max_by(|(_, a), (_, b)| a.dot(direction).total_cmp(&b.dot(direction)))
+}
+
+/// Sutherland–Hodgman against one plane, keeps the part of the segment where `normal.dot(point) <= offset`
+fn clip(segment: [Vec2<f32>; 2], normal: Vec2<f32>, offset: f32) -> Option<[Vec2<f32>; 2]> {
+    let [start, end] = segment;
+    let start_distance = normal.dot(start) - offset;
+    let end_distance = normal.dot(end) - offset;
+    match (start_distance <= 0.0, end_distance <= 0.0) {
+        (true, true) => Some(segment),
+        (false, false) => None,
+        (start_inside, _) => {
+            let crossing =
+                start + (end - start) * (start_distance / (start_distance - end_distance));
+            if start_inside {
+                Some([start, crossing])
+            } else {
+                Some([crossing, end])
+            }
+        }
+    }
+}
+
+/// Middle of where the two cores touch along `normal`
+fn contact_point(a: &Core, b: &Core, normal: Vec2<f32>) -> Vec2<f32> {
+    let a_support = support(a, normal);
+    let b_support = support(b, -normal);
+    match (a_support.as_slice(), b_support.as_slice()) {
+        ([a_point], [b_point]) => (*a_point + *b_point) / 2.0,
+        ([point], _) | (_, [point]) => *point,
+        _ => {
+            // Two sides lying on each other, use the middle of where they overlap
+            let tangent = perp(normal);
+            let range = |points: &[Vec2<f32>]| {
+                points
+                    .iter()
+                    .map(|point| point.dot(tangent))
+                    .fold((f32::MAX, f32::MIN), |(min, max), t| {
+                        (min.min(t), max.max(t))
+                    })
+            };
+            let (a_min, a_max) = range(&a_support);
+            let (b_min, b_max) = range(&b_support);
+            let middle = (a_min.max(b_min) + a_max.min(b_max)) / 2.0;
+            normal * b_support[0].dot(normal) + tangent * middle
+        }
+    }
+}
+
+/// Points of the core furthest along `direction`
+fn support(core: &Core, direction: Vec2<f32>) -> Vec<Vec2<f32>> {
+    let furthest = core
+        .points
+        .iter()
+        .map(|point| point.dot(direction))
+        .fold(f32::MIN, f32::max);
+    core.points
+        .iter()
+        .copied()
+        .filter(|point| point.dot(direction) >= furthest - FEATURE_TOLERANCE)
+        .collect()
+}
+
+fn project(core: &Core, axis: Vec2<f32>) -> (f32, f32) {
+    core.points
+        .iter()
+        .map(|point| point.dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), d| {
+            (min.min(d), max.max(d))
+        })
+}
+
+/// Used when the cores are apart, they only touch if the radii cover the gap
+fn closest_points(a: &Core, b: &Core) -> Option<Contact> {
+    let mut best = (f32::MAX, a.points[0], b.points[0]);
+    let mut check = |a_point: Vec2<f32>, b_point: Vec2<f32>| {
+        let distance_squared = a_point.distance_squared(b_point);
+        if distance_squared < best.0 {
+            best = (distance_squared, a_point, b_point);
+        }
+    };
+    for a_point in &a.points {
+        for b_point in &b.points {
+            check(*a_point, *b_point);
+        }
+        for (start, end) in b.sides() {
+            check(*a_point, closest_on_segment(*a_point, start, end));
+        }
+    }
+    for b_point in &b.points {
+        for (start, end) in a.sides() {
+            check(closest_on_segment(*b_point, start, end), *b_point);
+        }
+    }
+
+    let (distance_squared, a_point, b_point) = best;
+    let radii = a.radius + b.radius;
+    if distance_squared >= radii * radii {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    let normal = if distance > f32::EPSILON {
+        (b_point - a_point) / distance
+    } else {
+        // Right on top of each other, push them apart however the centers are
+        let between = b.center() - a.center();
+        if between.magnitude_squared() > f32::EPSILON {
+            between.normalized()
+        } else {
+            Vec2::unit_y()
+        }
+    };
+    Some(Contact {
+        normal,
+        points: vec![ContactPoint {
+            point: (a_point + normal * a.radius + b_point - normal * b.radius) / 2.0,
+            depth: radii - distance,
+        }],
+    })
+}
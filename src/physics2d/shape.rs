@@ -0,0 +1,305 @@
+use crate::error::{EngineError, EngineResult};
+use std::f32::consts::PI;
+use vek::{Aabr, Vec2};
+
+/// Outline of a `Collider`, the points are relative to the body's position
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    /// Half the width and height
+    Box {
+        half_extents: Vec2<f32>,
+    },
+    /// Upright pill, `half_height` goes from the center to the middle of either round end
+    Capsule {
+        half_height: f32,
+        radius: f32,
+    },
+    /// Convex and counter clockwise, make it with `Shape::polygon` so that's true
+    Polygon(Vec<Vec2<f32>>),
+}
+
+impl Shape {
+    /// Takes the convex hull of the points, errors if they don't make an area
+    pub fn polygon(points: &[Vec2<f32>]) -> EngineResult<Self> {
+        let hull = convex_hull(points);
+        if hull.len() < 3 {
+            return Err(EngineError::Error(format!(
+                "A polygon needs at least 3 points that aren't in a line, got {}",
+                points.len()
+            )));
+        }
+        Ok(Self::Polygon(hull))
+    }
+
+    pub(crate) fn area(&self) -> f32 {
+        match self {
+            Self::Circle { radius } => PI * radius * radius,
+            Self::Box { half_extents } => 4.0 * half_extents.x * half_extents.y,
+            Self::Capsule {
+                half_height,
+                radius,
+            } => 4.0 * half_height * radius + PI * radius * radius,
+            Self::Polygon(points) => edges(points).map(|(a, b)| cross(a, b)).sum::<f32>() / 2.0,
+        }
+    }
+
+    /// Rotational inertia around the body's position for a mass of 1
+    pub(crate) fn unit_inertia(&self) -> f32 {
+        match self {
+            Self::Circle { radius } => radius * radius / 2.0,
+            Self::Box { half_extents } => half_extents.magnitude_squared() / 3.0,
+            Self::Capsule {
+                half_height,
+                radius,
+            } => {
+                // The middle box and the two ends as if they were one circle, close enough
+                let box_area = 4.0 * half_height * radius;
+                let circle_area = PI * radius * radius;
+                let box_inertia = (radius * radius + half_height * half_height) / 3.0;
+                let circle_inertia = radius * radius / 2.0 + half_height * half_height;
+                (box_area * box_inertia + circle_area * circle_inertia) / (box_area + circle_area)
+            }
+            Self::Polygon(points) => {
+                let (mut numerator, mut denominator) = (0.0, 0.0);
+                for (a, b) in edges(points) {
+                    let c = cross(a, b);
+                    numerator += c * (a.dot(a) + a.dot(b) + b.dot(b));
+                    denominator += c;
+                }
+                numerator / (6.0 * denominator)
+            }
+        }
+    }
+
+    /// The shape in world space as points with a radius around them
+    pub(crate) fn core(&self, position: Vec2<f32>, rotation: f32) -> Core {
+        let (sin, cos) = rotation.sin_cos();
+        let to_world = |point: Vec2<f32>| position + rotate(point, sin, cos);
+        match self {
+            Self::Circle { radius } => Core {
+                points: vec![position],
+                radius: *radius,
+            },
+            Self::Box { half_extents } => Core {
+                points: [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .into_iter()
+                    .map(|(x, y)| to_world(Vec2::new(x, y) * *half_extents))
+                    .collect(),
+                radius: 0.0,
+            },
+            Self::Capsule {
+                half_height,
+                radius,
+            } => Core {
+                points: vec![
+                    to_world(Vec2::new(0.0, -half_height)),
+                    to_world(Vec2::new(0.0, *half_height)),
+                ],
+                radius: *radius,
+            },
+            Self::Polygon(points) => Core {
+                points: points.iter().map(|point| to_world(*point)).collect(),
+                radius: 0.0,
+            },
+        }
+    }
+}
+
+/// A convex set of 1, 2 or more points grown by a radius.
+/// Circles are one point, capsules are two and boxes and polygons have no radius
+pub(crate) struct Core {
+    pub(crate) points: Vec<Vec2<f32>>,
+    pub(crate) radius: f32,
+}
+
+impl Core {
+    pub(crate) fn aabr(&self) -> Aabr<f32> {
+        let mut min = self.points[0];
+        let mut max = self.points[0];
+        for point in &self.points[1..] {
+            min = Vec2::partial_min(min, *point);
+            max = Vec2::partial_max(max, *point);
+        }
+        Aabr {
+            min: min - self.radius,
+            max: max + self.radius,
+        }
+    }
+
+    pub(crate) fn center(&self) -> Vec2<f32> {
+        self.points
+            .iter()
+            .fold(Vec2::zero(), |sum, point| sum + *point)
+            / self.points.len() as f32
+    }
+
+    /// Sides of the core, a capsule's core is one side and a circle's has none
+    pub(crate) fn sides(&self) -> impl Iterator<Item = (Vec2<f32>, Vec2<f32>)> + '_ {
+        let count = match self.points.len() {
+            1 => 0,
+            2 => 1,
+            len => len,
+        };
+        (0..count).map(|i| (self.points[i], self.points[(i + 1) % self.points.len()]))
+    }
+
+    pub(crate) fn contains(&self, point: Vec2<f32>) -> bool {
+        if self.points.len() >= 3 {
+            return edges(&self.points).all(|(a, b)| cross(b - a, point - a) >= 0.0);
+        }
+        let closest = match self.points.len() {
+            1 => self.points[0],
+            _ => closest_on_segment(point, self.points[0], self.points[1]),
+        };
+        closest.distance_squared(point) <= self.radius * self.radius
+    }
+
+    /// Distance along the ray to where it enters the shape, and the normal there.
+    /// `direction` has to be normalized
+    pub(crate) fn raycast(
+        &self,
+        origin: Vec2<f32>,
+        direction: Vec2<f32>,
+        max_distance: f32,
+    ) -> Option<(f32, Vec2<f32>)> {
+        match self.points.len() {
+            1 => raycast_circle(origin, direction, max_distance, self.points[0], self.radius),
+            2 if self.points[0] == self.points[1] => {
+                raycast_circle(origin, direction, max_distance, self.points[0], self.radius)
+            }
+            2 => {
+                let (a, b) = (self.points[0], self.points[1]);
+                let offset = perp((b - a).normalized()) * self.radius;
+                let sides = [a - offset, b - offset, b + offset, a + offset];
+                [
+                    raycast_circle(origin, direction, max_distance, a, self.radius),
+                    raycast_circle(origin, direction, max_distance, b, self.radius),
+                    raycast_polygon(origin, direction, max_distance, &sides),
+                ]
+                .into_iter()
+                .flatten()
+                .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            }
+            _ => raycast_polygon(origin, direction, max_distance, &self.points),
+        }
+    }
+}
+
+fn raycast_circle(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_distance: f32,
+    center: Vec2<f32>,
+    radius: f32,
+) -> Option<(f32, Vec2<f32>)> {
+    let to_origin = origin - center;
+    let b = to_origin.dot(direction);
+    let c = to_origin.magnitude_squared() - radius * radius;
+    if c <= 0.0 {
+        // Started inside
+        return Some((0.0, -direction));
+    }
+    let discriminant = b * b - c;
+    if b > 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    (distance <= max_distance).then(|| {
+        let normal = (origin + direction * distance - center) / radius;
+        (distance, normal)
+    })
+}
+
+/// `points` have to be counter clockwise
+fn raycast_polygon(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_distance: f32,
+    points: &[Vec2<f32>],
+) -> Option<(f32, Vec2<f32>)> {
+    // Clips the ray against every side, what's left is inside the polygon
+    let (mut enter, mut exit) = (0.0, max_distance);
+    let mut normal = -direction;
+    for (a, b) in edges(points) {
+        let outward = -perp((b - a).normalized());
+        let numerator = outward.dot(a - origin);
+        let denominator = outward.dot(direction);
+        if denominator == 0.0 {
+            if numerator < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = numerator / denominator;
+        if denominator < 0.0 && t > enter {
+            enter = t;
+            normal = outward;
+        } else if denominator > 0.0 && t < exit {
+            exit = t;
+        }
+        if exit < enter {
+            return None;
+        }
+    }
+    Some((enter, normal))
+}
+
+/// Andrew's monotone chain, counter clockwise
+fn convex_hull(points: &[Vec2<f32>]) -> Vec<Vec2<f32>> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let turns_left = |hull: &[Vec2<f32>], point: Vec2<f32>| {
+        let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+        cross(b - a, point - a) > 0.0
+    };
+    let mut hull: Vec<Vec2<f32>> = Vec::with_capacity(points.len() + 1);
+    for point in points.iter() {
+        while hull.len() >= 2 && !turns_left(&hull, *point) {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    let lower_len = hull.len() + 1;
+    for point in points.iter().rev().skip(1) {
+        while hull.len() >= lower_len && !turns_left(&hull, *point) {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    // The first point ends up on both ends
+    hull.pop();
+    hull
+}
+
+pub(crate) fn edges(points: &[Vec2<f32>]) -> impl Iterator<Item = (Vec2<f32>, Vec2<f32>)> + '_ {
+    (0..points.len()).map(|i| (points[i], points[(i + 1) % points.len()]))
+}
+
+pub(crate) fn closest_on_segment(point: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>) -> Vec2<f32> {
+    let ab = b - a;
+    let length_squared = ab.magnitude_squared();
+    if length_squared == 0.0 {
+        return a;
+    }
+    a + ab * ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0)
+}
+
+pub(crate) fn rotate(point: Vec2<f32>, sin: f32, cos: f32) -> Vec2<f32> {
+    Vec2::new(point.x * cos - point.y * sin, point.x * sin + point.y * cos)
+}
+
+pub(crate) fn cross(a: Vec2<f32>, b: Vec2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Turned 90 degrees counter clockwise
+pub(crate) fn perp(v: Vec2<f32>) -> Vec2<f32> {
+    Vec2::new(-v.y, v.x)
+}
@@ -1,21 +1,63 @@
 pub use crate::{
-    asset::{self, model::Model},
+    asset::{self, GltfMesh, GltfScene, model::Model},
+    collision3d::{
+        Capsule, Collider3d, ColliderHandle, CollisionWorld3d, Penetration, RayHit3d, SweepHit,
+        controller::CharacterController, mesh::TriangleMesh,
+    },
     context::{
-        Context,
-        audio::{audio_play_value, sound::Sound},
+        Context, LoopMode,
+        audio::{
+            Audio, AudioBackend, MASTER_BUS, PlayOptions, audio_play_value, effects,
+            sound::Sound,
+            spatial::{Attenuation, Emitter, Listener, Rolloff},
+            synth::{Envelope, SynthParams, Waveform},
+        },
+        frame_pacing::{FrameStats, Vsync},
+        viewport::ViewportMode,
+        window::{FullscreenMode, WindowConfig},
+    },
+    ecs::{
+        Entity, World,
+        components::{AudioEmitter, MeshRenderer, Sprite},
+        hierarchy::GlobalTransform,
     },
     error::*,
-    game_state::GameState,
+    game_state::{
+        GameState,
+        state_stack::{State, StateStack, Transition},
+    },
     glium::{
         BackfaceCullingMode, Blend, Depth, DepthTest, DrawParameters, implement_vertex,
         index::{IndexBuffer, IndexBufferAny, IndicesSource, NoIndices, PrimitiveType},
         uniform,
         uniforms::MagnifySamplerFilter,
         vertex::{MultiVerticesSource, VertexBuffer, VertexBufferAny},
-        winit::{event::WindowEvent, keyboard::KeyCode},
+        winit::{event::WindowEvent, keyboard::KeyCode, window::CursorIcon},
     },
-    init,
+    init, init_headless, init_with_config, init_with_vsync,
     math::*,
-    renderer::{Renderer, drawable::Drawable, skybox::Skybox, text::Text, texture::Texture},
-    rng, run,
+    physics2d::{
+        BodyHandle, CollisionEvent, PhysicsWorld2d, RayHit,
+        body::{BodyType, Collider, RigidBody},
+        shape::Shape,
+    },
+    renderer::{
+        Renderer,
+        capture::{CaptureMode, FrameCapture, save_png},
+        deferred::{GBuffer, GBufferRenderer, GBufferView},
+        drawable::Drawable,
+        dyn_renderer::DynRenderer,
+        environment::Environment,
+        instancing::{Instance, InstanceBuffer, InstancedModel},
+        lighting::{Light, LightHandle, Lights},
+        material::{Material, Shading},
+        pbr::PbrMaterial,
+        render_queue::{RenderLayer, RenderQueue},
+        render_target::{RenderTarget, Resizable, TargetRenderer},
+        shadow::{ShadowCaster, ShadowSettings, draw_shadows},
+        skybox::{ProceduralSky, Skybox},
+        text::Text,
+        texture::Texture,
+    },
+    rng, run, run_headless_frame,
 };
@@ -1,9 +1,28 @@
-use crate::{context::Context, error::EngineResult};
+use crate::{asset::model::Model, context::Context, error::EngineResult};
 use drawable::Drawable;
-use glium::{Frame, Surface, uniforms::Uniforms};
+use glium::{
+    Frame, Surface, Texture2d,
+    uniforms::{MagnifySamplerFilter, Uniforms},
+};
+use image::RgbaImage;
+use lighting::LitModel;
+use material::Material;
+use pbr::PbrMaterial;
+use vek::Mat4;
 
+pub mod capture;
+pub mod deferred;
 pub mod drawable;
+pub mod dyn_renderer;
+pub mod environment;
 pub mod fxaa;
+pub mod instancing;
+pub mod lighting;
+pub mod material;
+pub mod pbr;
+pub mod render_queue;
+pub mod render_target;
+pub mod shadow;
 pub mod skybox;
 pub mod text;
 pub mod texture;
@@ -41,11 +60,68 @@ pub trait Renderer {
         surface.clear_color_and_depth(color, depth);
     }
 
+    /// Draws a `Model` with the built-in "lit" program, lit by the lights in `ctx.lights`.
+    /// `transform` is where the model is in the world, like `Transform::get_matrix`.
+    /// Shadows come from the last `draw_shadows`
+    fn draw_model(
+        &mut self,
+        ctx: &mut Context,
+        model: &Model,
+        transform: Mat4<f32>,
+        material: &Material,
+    ) -> EngineResult {
+        lighting::ensure_program(ctx)?;
+        lighting::draw_lit(
+            self,
+            ctx,
+            &LitModel {
+                model,
+                program: "lit",
+                draw_params: material.get_draw_params(),
+            },
+            transform,
+            material,
+        )
+    }
+
+    /// Draws a `Model` with the built-in "pbr" program, like `draw_model`. Lit by `ctx.lights`
+    /// and `ctx.lights.environment` if there is one
+    fn draw_pbr(
+        &mut self,
+        ctx: &mut Context,
+        model: &Model,
+        transform: Mat4<f32>,
+        material: &PbrMaterial,
+    ) -> EngineResult {
+        pbr::ensure_program(ctx)?;
+        lighting::draw_lit(
+            self,
+            ctx,
+            &LitModel {
+                model,
+                program: "pbr",
+                draw_params: material.get_draw_params(),
+            },
+            transform,
+            material,
+        )
+    }
+
     /// Gets the dimensions of the `Renderer`
     fn get_dimensions(&self) -> (u32, u32) {
         let surface = self.get_surface();
         surface.get_dimensions()
     }
+
+    /// Copies everything drawn so far into an image, call it at the end of `draw` to get the whole frame.
+    /// Save it with `capture::save_png`
+    fn screenshot(&self, ctx: &Context) -> EngineResult<RgbaImage> {
+        let (width, height) = self.get_dimensions();
+        let texture = Texture2d::empty(&ctx.display, width, height)?;
+        self.get_surface()
+            .fill(&texture.as_surface(), MagnifySamplerFilter::Nearest);
+        capture::texture_to_image(&texture)
+    }
 }
 
 pub struct FrameWrapper {
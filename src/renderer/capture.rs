@@ -0,0 +1,105 @@
+use crate::{
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::Renderer,
+};
+use glium::{Texture2d, texture::RawImage2d};
+use image::{ImageFormat, RgbaImage};
+use std::{fs, path::PathBuf};
+
+/// Saves an image from `Renderer::screenshot` as a PNG
+pub fn save_png(image: &RgbaImage, path: impl AsRef<std::path::Path>) -> EngineResult {
+    image.save_with_format(path, ImageFormat::Png)?;
+    Ok(())
+}
+
+/// Reads a texture back from the GPU, the top row of the image is the top of the screen
+pub(crate) fn texture_to_image(texture: &Texture2d) -> EngineResult<RgbaImage> {
+    let raw: RawImage2d<u8> = texture.read();
+    let mut image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+        .ok_or_else(|| EngineError::ImageError("Pixels read don't fit the image".to_string()))?;
+    // OpenGL starts from the bottom row
+    image::imageops::flip_vertical_in_place(&mut image);
+    Ok(image)
+}
+
+/// Which frames a `FrameCapture` saves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureMode {
+    /// Saves every nth frame, 1 saves all of them
+    EveryNthFrame(u32),
+    /// Saves every frame and sets `ctx.dt` to this many seconds, so the images play back
+    /// smoothly no matter how long saving them takes. Good for trailers
+    FixedTimestep(f32),
+}
+
+/// Dumps frames to numbered PNGs, start it with `Context::start_capture`
+pub struct FrameCapture {
+    pub mode: CaptureMode,
+    directory: PathBuf,
+    max_images: Option<u32>,
+    frame: u64,
+    saved: u32,
+}
+
+impl FrameCapture {
+    /// Images are saved as `directory/frame_00000.png` and so on, the directory is created if needed
+    pub fn new(directory: impl Into<PathBuf>, mode: CaptureMode) -> Self {
+        Self {
+            mode,
+            directory: directory.into(),
+            max_images: None,
+            frame: 0,
+            saved: 0,
+        }
+    }
+
+    /// Stops capturing after `max_images` have been saved
+    pub fn with_max_images(mut self, max_images: u32) -> Self {
+        self.max_images = Some(max_images);
+        self
+    }
+
+    pub fn get_saved_count(&self) -> u32 {
+        self.saved
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.max_images.is_some_and(|max| self.saved >= max)
+    }
+
+    pub(crate) fn fixed_dt(&self) -> Option<f32> {
+        match self.mode {
+            CaptureMode::FixedTimestep(dt) => Some(dt),
+            CaptureMode::EveryNthFrame(_) => None,
+        }
+    }
+
+    fn save_frame(&mut self, ctx: &Context, renderer: &impl Renderer) -> EngineResult {
+        let should_save = match self.mode {
+            CaptureMode::EveryNthFrame(n) => self.frame.is_multiple_of(n.max(1) as u64),
+            CaptureMode::FixedTimestep(_) => true,
+        };
+        self.frame += 1;
+        if !should_save {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(format!("frame_{:05}.png", self.saved));
+        save_png(&renderer.screenshot(ctx)?, path)?;
+        self.saved += 1;
+        Ok(())
+    }
+}
+
+/// Runs after the game has drawn, stops capturing if a frame can't be saved
+pub(crate) fn capture_frame(ctx: &mut Context, renderer: &impl Renderer) {
+    let Some(mut capture) = ctx.capture.take() else {
+        return;
+    };
+    match capture.save_frame(ctx, renderer) {
+        Ok(()) if !capture.is_finished() => ctx.capture = Some(capture),
+        Ok(()) => {}
+        Err(err) => eprintln!("Stopped capturing frames: {}", err),
+    }
+}
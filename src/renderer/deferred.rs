@@ -0,0 +1,559 @@
+use crate::{
+    asset::model::Model,
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::{
+        Renderer,
+        drawable::Drawable,
+        environment::Environment,
+        lighting::{self, Light, LightUniform, LitModel, MaterialUniforms},
+        material::Material,
+        pbr::PbrMaterial,
+        render_target::Resizable,
+        shadow::{ShadowMap, ShadowSettings},
+    },
+    shaders::{
+        DEFERRED_COMPOSITE_FS, DEFERRED_COMPOSITE_VS, DEFERRED_LIGHT_FS, DEFERRED_LIGHT_VS,
+        GBUFFER_FS, LIT_VS,
+    },
+};
+use glium::{
+    BackfaceCullingMode, Blend, BlendingFunction, Depth, DepthTest, DrawParameters,
+    LinearBlendingFactor, Surface, Texture2d, VertexBuffer,
+    framebuffer::{MultiOutputFrameBuffer, SimpleFrameBuffer},
+    implement_vertex,
+    index::{IndicesSource, NoIndices, PrimitiveType},
+    texture::{DepthFormat, DepthTexture2d, MipmapsOption, UncompressedFloatFormat},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, UniformValue, Uniforms},
+    vertex::MultiVerticesSource,
+};
+use std::{f32::consts::PI, rc::Rc};
+use vek::{Mat4, Vec3};
+
+const SPHERE_RINGS: usize = 8;
+const SPHERE_SEGMENTS: usize = 12;
+const CONE_SEGMENTS: usize = 12;
+/// Wider spot lights are drawn with a sphere, the cone would be too flat
+const MAX_CONE_ANGLE: f32 = 1.4;
+
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 3],
+}
+implement_vertex!(Vertex, position);
+
+struct Buffers {
+    albedo: Texture2d,
+    normal: Texture2d,
+    material: Texture2d,
+    depth: DepthTexture2d,
+    /// Where the lights add up before the composite
+    light: Texture2d,
+}
+
+impl Buffers {
+    fn new(ctx: &Context, width: u32, height: u32) -> EngineResult<Self> {
+        let color = |format| {
+            Texture2d::empty_with_format(
+                &ctx.display,
+                format,
+                MipmapsOption::NoMipmap,
+                width,
+                height,
+            )
+        };
+        Ok(Self {
+            albedo: color(UncompressedFloatFormat::U8U8U8U8)?,
+            normal: color(UncompressedFloatFormat::F16F16F16F16)?,
+            material: color(UncompressedFloatFormat::U8U8U8U8)?,
+            depth: DepthTexture2d::empty_with_format(
+                &ctx.display,
+                DepthFormat::I24,
+                MipmapsOption::NoMipmap,
+                width,
+                height,
+            )?,
+            light: color(UncompressedFloatFormat::F16F16F16F16)?,
+        })
+    }
+}
+
+/// The G-buffer is read one texel per pixel, without blending between them
+fn nearest<T>(sampler: Sampler<'_, T>) -> Sampler<'_, T> {
+    sampler
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .minify_filter(MinifySamplerFilter::Nearest)
+}
+
+/// What `GBuffer::composite` shows, everything but `Lit` is for looking at what went into the G-buffer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GBufferView {
+    /// The finished image
+    #[default]
+    Lit,
+    Albedo,
+    /// World space normals mapped to 0 to 1
+    Normal,
+    /// Metallic in red, roughness in green and occlusion in blue
+    Material,
+    /// Distance from the camera, darker is closer
+    Depth,
+    /// Only the light from `ctx.lights` and emissive, without ambient or the environment
+    Light,
+}
+
+/// An optional deferred path for scenes with a lot of lights. Models are drawn into the G-buffer with a
+/// `GBufferRenderer`, then `composite` lights every pixel once per light in `ctx.lights` and draws the
+/// result. Point and spot lights only light the pixels inside their range so many small lights are cheap.
+/// Transparent things can't go in the G-buffer, draw them normally after `composite`
+pub struct GBuffer {
+    buffers: Buffers,
+    quad: VertexBuffer<Vertex>,
+    sphere: VertexBuffer<Vertex>,
+    cone: VertexBuffer<Vertex>,
+    pub view: GBufferView,
+}
+
+impl GBuffer {
+    /// Adds the "gbuffer", "deferred_light" and "deferred_composite" programs if they aren't there yet
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> EngineResult<Self> {
+        if ctx.get_program("gbuffer").is_none() {
+            ctx.new_program("gbuffer", LIT_VS, GBUFFER_FS, None)?;
+        }
+        if ctx.get_program("deferred_light").is_none() {
+            ctx.new_program("deferred_light", DEFERRED_LIGHT_VS, DEFERRED_LIGHT_FS, None)?;
+        }
+        if ctx.get_program("deferred_composite").is_none() {
+            ctx.new_srgb_program(
+                "deferred_composite",
+                DEFERRED_COMPOSITE_VS,
+                DEFERRED_COMPOSITE_FS,
+            )?;
+        }
+        Ok(Self {
+            buffers: Buffers::new(ctx, width, height)?,
+            quad: VertexBuffer::new(
+                &ctx.display,
+                &[
+                    Vertex {
+                        position: [-1.0, -1.0, 0.0],
+                    },
+                    Vertex {
+                        position: [1.0, -1.0, 0.0],
+                    },
+                    Vertex {
+                        position: [-1.0, 1.0, 0.0],
+                    },
+                    Vertex {
+                        position: [1.0, 1.0, 0.0],
+                    },
+                ],
+            )?,
+            sphere: VertexBuffer::new(&ctx.display, &sphere_vertices())?,
+            cone: VertexBuffer::new(&ctx.display, &cone_vertices())?,
+            view: GBufferView::default(),
+        })
+    }
+
+    pub fn with_view(mut self, view: GBufferView) -> Self {
+        self.view = view;
+        self
+    }
+
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        self.buffers.albedo.dimensions()
+    }
+
+    /// Recreates the buffers, `Context::resize_with_window` can call it for you
+    pub fn resize(&mut self, ctx: &Context, width: u32, height: u32) -> EngineResult {
+        if self.get_dimensions() != (width, height) {
+            self.buffers = Buffers::new(ctx, width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Lights what was drawn into the G-buffer with `ctx.lights` and draws it to `renderer`. That's how it
+    /// connects to post-processing, pass an `FXAARenderer` and then call `draw_frame_buffer`, or a
+    /// `TargetRenderer`. With a `ViewportMode` that has a resolution the renderer `GameState::draw` gets is
+    /// already the viewport's target, so the G-buffer should be the render size like
+    /// `Context::resize_with_window` keeps it. Shadows come from the last `draw_shadows`.
+    /// The depth is written too, so anything drawn afterwards is hidden behind it
+    pub fn composite(&self, ctx: &mut Context, renderer: &mut impl Renderer) -> EngineResult {
+        let persp_view = Mat4::from_col_arrays(ctx.camera.get_perspective())
+            * Mat4::from_col_arrays(ctx.camera.get_view());
+        let inverse_persp_view = persp_view.inverted().into_col_arrays();
+        self.accumulate_lights(ctx, persp_view)?;
+
+        let buffers = &self.buffers;
+        let uniforms = CompositeUniforms {
+            uniforms: uniform! {
+                albedo_buffer: nearest(buffers.albedo.sampled()),
+                normal_buffer: nearest(buffers.normal.sampled()),
+                material_buffer: nearest(buffers.material.sampled()),
+                depth_buffer: nearest(buffers.depth.sampled()),
+                light_buffer: nearest(buffers.light.sampled()),
+                inverse_persp_view: inverse_persp_view,
+                camera_position: ctx.camera.position.into_array(),
+                ambient: ctx.lights.ambient.into_array(),
+                view: self.view as i32,
+            },
+            environment: ctx
+                .lights
+                .environment
+                .clone()
+                .map(|environment| (environment, ctx.lights.environment_intensity)),
+        };
+        renderer.draw(ctx, self, &uniforms)
+    }
+
+    /// Draws each light's volume into the light buffer with additive blending. Only back faces are
+    /// drawn, so the pixels behind the front of the volume get lit even when the camera is inside it
+    fn accumulate_lights(&self, ctx: &Context, persp_view: Mat4<f32>) -> EngineResult {
+        let program = ctx.get_program("deferred_light").ok_or_else(|| {
+            EngineError::Error("Program deferred_light not found in the context".to_string())
+        })?;
+        let shadow_map = ctx
+            .shadow_map
+            .as_ref()
+            .filter(|shadow_map| !shadow_map.tiles.is_empty());
+        let (width, height) = self.get_dimensions();
+        let inverse_persp_view = persp_view.inverted().into_col_arrays();
+        let additive = Blend {
+            color: BlendingFunction::Addition {
+                source: LinearBlendingFactor::One,
+                destination: LinearBlendingFactor::One,
+            },
+            alpha: BlendingFunction::Addition {
+                source: LinearBlendingFactor::One,
+                destination: LinearBlendingFactor::One,
+            },
+            constant_value: (0.0, 0.0, 0.0, 0.0),
+        };
+
+        let buffers = &self.buffers;
+        let mut framebuffer = SimpleFrameBuffer::new(&ctx.display, &buffers.light)?;
+        for (handle, light) in ctx.lights.iter() {
+            let (vertices, primitive, volume, backface_culling) = match self.light_volume(light) {
+                Some((vertices, model)) => (
+                    vertices,
+                    PrimitiveType::TrianglesList,
+                    persp_view * model,
+                    BackfaceCullingMode::CullCounterClockwise,
+                ),
+                None => (
+                    &self.quad,
+                    PrimitiveType::TriangleStrip,
+                    Mat4::identity(),
+                    BackfaceCullingMode::CullingDisabled,
+                ),
+            };
+            framebuffer.draw(
+                vertices,
+                NoIndices(primitive),
+                program,
+                &LightPassUniforms {
+                    uniforms: uniform! {
+                        volume: volume.into_col_arrays(),
+                        resolution: (width as f32, height as f32),
+                        inverse_persp_view: inverse_persp_view,
+                        camera_position: ctx.camera.position.into_array(),
+                        albedo_buffer: nearest(buffers.albedo.sampled()),
+                        normal_buffer: nearest(buffers.normal.sampled()),
+                        material_buffer: nearest(buffers.material.sampled()),
+                        depth_buffer: nearest(buffers.depth.sampled()),
+                    },
+                    light: LightUniform::new(
+                        light,
+                        shadow_map.and_then(|shadow_map| shadow_map.first_tile(handle)),
+                    ),
+                    shadows: shadow_map.map(|shadow_map| (shadow_map, ctx.lights.shadows)),
+                    camera_forward: ctx.camera.get_directions().0.into_array(),
+                },
+                &DrawParameters {
+                    blend: additive,
+                    backface_culling,
+                    ..Default::default()
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The mesh and model matrix that cover everything a light reaches, `None` for the whole screen
+    fn light_volume(&self, light: &Light) -> Option<(&VertexBuffer<Vertex>, Mat4<f32>)> {
+        // The meshes' flat faces sit inside the round shape they're made from, so they're scaled out
+        let sphere_scale =
+            1.0 / ((PI / SPHERE_RINGS as f32).cos() * (PI / SPHERE_SEGMENTS as f32).cos());
+        let sphere = |position: Vec3<f32>, range: f32| {
+            (
+                &self.sphere,
+                Mat4::<f32>::translation_3d(position) * Mat4::scaling_3d(range * sphere_scale),
+            )
+        };
+        match *light {
+            Light::Directional { .. } => None,
+            Light::Point {
+                position, range, ..
+            } => Some(sphere(position, range)),
+            Light::Spot {
+                position,
+                direction,
+                range,
+                outer_angle,
+                ..
+            } => {
+                if outer_angle >= MAX_CONE_ANGLE {
+                    return Some(sphere(position, range));
+                }
+                // The cone points down -z, turned so -z is the light's direction
+                let forward = direction.normalized();
+                let up = if forward.y.abs() > 0.99 {
+                    Vec3::unit_x()
+                } else {
+                    Vec3::unit_y()
+                };
+                let right = forward.cross(up).normalized();
+                let up = right.cross(forward);
+                let radius = range * outer_angle.tan() / (PI / CONE_SEGMENTS as f32).cos();
+                Some((
+                    &self.cone,
+                    Mat4::from_col_arrays([
+                        (right * radius).with_w(0.0).into_array(),
+                        (up * radius).with_w(0.0).into_array(),
+                        (-forward * range).with_w(0.0).into_array(),
+                        position.with_w(1.0).into_array(),
+                    ]),
+                ))
+            }
+        }
+    }
+}
+
+impl Resizable for GBuffer {
+    fn resize(&mut self, ctx: &Context, width: u32, height: u32) -> EngineResult {
+        GBuffer::resize(self, ctx, width, height)
+    }
+}
+
+impl Drawable for GBuffer {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        &self.quad
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TriangleStrip)
+    }
+    fn get_program(&self) -> String {
+        "deferred_composite".to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        DrawParameters {
+            depth: Depth {
+                test: DepthTest::Overwrite,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Draws into a `GBuffer` instead of the screen, it's cleared when this is made. `draw_pbr` and
+/// `draw_model` use the built-in "gbuffer" program, custom programs need to write `out_albedo`,
+/// `out_normal`, `out_material` and `out_light` like `shaders::GBUFFER_FS`
+pub struct GBufferRenderer<'a> {
+    framebuffer: MultiOutputFrameBuffer<'a>,
+}
+
+impl<'a> GBufferRenderer<'a> {
+    pub fn new(ctx: &Context, gbuffer: &'a GBuffer) -> EngineResult<Self> {
+        let buffers = &gbuffer.buffers;
+        let mut framebuffer = MultiOutputFrameBuffer::with_depth_buffer(
+            &ctx.display,
+            [
+                ("out_albedo", &buffers.albedo),
+                ("out_normal", &buffers.normal),
+                ("out_material", &buffers.material),
+                ("out_light", &buffers.light),
+            ],
+            &buffers.depth,
+        )?;
+        framebuffer.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+        Ok(Self { framebuffer })
+    }
+}
+
+impl Renderer for GBufferRenderer<'_> {
+    fn get_surface(&self) -> &impl Surface {
+        &self.framebuffer
+    }
+    fn get_surface_mut(&mut self) -> &mut impl Surface {
+        &mut self.framebuffer
+    }
+    fn draw<D, U>(&mut self, ctx: &mut Context, drawable: &D, uniforms: &U) -> EngineResult
+    where
+        D: Drawable,
+        U: Uniforms,
+    {
+        let program = if let Some(program) = ctx.get_program(drawable.get_program()) {
+            program
+        } else {
+            return Err(EngineError::Error(format!(
+                "Program {} not found in the context",
+                drawable.get_program()
+            )));
+        };
+
+        self.framebuffer.draw(
+            drawable.get_vbo(),
+            drawable.get_ibo(),
+            program,
+            uniforms,
+            &drawable.get_draw_params(),
+        )?;
+        Ok(())
+    }
+
+    /// Writes the model and its material into the G-buffer, it's lit later by `GBuffer::composite`
+    fn draw_pbr(
+        &mut self,
+        ctx: &mut Context,
+        model: &Model,
+        transform: Mat4<f32>,
+        material: &PbrMaterial,
+    ) -> EngineResult {
+        let uniforms = MaterialPassUniforms {
+            uniforms: uniform! {
+                persp: ctx.camera.get_perspective(),
+                view: ctx.camera.get_view(),
+                model: transform.into_col_arrays(),
+            },
+            material,
+        };
+        self.draw(
+            ctx,
+            &LitModel {
+                model,
+                program: "gbuffer",
+                draw_params: material.get_draw_params(),
+            },
+            &uniforms,
+        )
+    }
+
+    /// Same as `draw_pbr` with the closest `PbrMaterial` to the `Material`, the shininess becomes roughness
+    fn draw_model(
+        &mut self,
+        ctx: &mut Context,
+        model: &Model,
+        transform: Mat4<f32>,
+        material: &Material,
+    ) -> EngineResult {
+        let pbr_material = PbrMaterial {
+            base_color: material.color,
+            base_color_texture: material.texture.clone(),
+            metallic: 0.0,
+            roughness: (2.0 / (material.shininess + 2.0)).sqrt(),
+            emissive: material.emissive,
+            double_sided: material.double_sided,
+            ..PbrMaterial::default()
+        };
+        self.draw_pbr(ctx, model, transform, &pbr_material)
+    }
+}
+
+/// The game's uniforms and a material's, for the "gbuffer" program
+struct MaterialPassUniforms<'a, U: Uniforms> {
+    uniforms: U,
+    material: &'a dyn MaterialUniforms,
+}
+
+impl<U: Uniforms> Uniforms for MaterialPassUniforms<'_, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+        self.material.visit_uniforms(&mut output);
+    }
+}
+
+struct LightPassUniforms<'a, U: Uniforms> {
+    uniforms: U,
+    light: LightUniform,
+    shadows: Option<(&'a ShadowMap, ShadowSettings)>,
+    camera_forward: [f32; 3],
+}
+
+impl<U: Uniforms> Uniforms for LightPassUniforms<'_, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+        self.light.visit_uniforms("light", &mut output);
+        lighting::visit_shadow_uniforms(self.shadows, self.camera_forward, &mut output);
+    }
+}
+
+struct CompositeUniforms<U: Uniforms> {
+    uniforms: U,
+    environment: Option<(Rc<Environment>, f32)>,
+}
+
+impl<U: Uniforms> Uniforms for CompositeUniforms<U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+        output(
+            "has_environment",
+            UniformValue::Bool(self.environment.is_some()),
+        );
+        if let Some((environment, intensity)) = &self.environment {
+            environment.visit_uniforms(&mut output);
+            output("environment_intensity", UniformValue::Float(*intensity));
+        }
+    }
+}
+
+/// A sphere with a radius of about 1, wound counter clockwise from outside
+fn sphere_vertices() -> Vec<Vertex> {
+    let point = |ring: usize, segment: usize| {
+        let theta = PI * ring as f32 / SPHERE_RINGS as f32;
+        let phi = 2.0 * PI * segment as f32 / SPHERE_SEGMENTS as f32;
+        Vertex {
+            position: [
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            ],
+        }
+    };
+    let mut vertices = Vec::with_capacity(SPHERE_RINGS * SPHERE_SEGMENTS * 6);
+    for ring in 0..SPHERE_RINGS {
+        for segment in 0..SPHERE_SEGMENTS {
+            let a = point(ring, segment);
+            let b = point(ring + 1, segment);
+            let c = point(ring + 1, segment + 1);
+            let d = point(ring, segment + 1);
+            vertices.extend_from_slice(&[a, c, b, a, d, c]);
+        }
+    }
+    vertices
+}
+
+/// A cone with its tip at the origin and a base with a radius of about 1 at z = -1,
+/// wound counter clockwise from outside
+fn cone_vertices() -> Vec<Vertex> {
+    let point = |segment: usize| {
+        let phi = 2.0 * PI * segment as f32 / CONE_SEGMENTS as f32;
+        Vertex {
+            position: [phi.cos(), phi.sin(), -1.0],
+        }
+    };
+    let tip = Vertex {
+        position: [0.0, 0.0, 0.0],
+    };
+    let center = Vertex {
+        position: [0.0, 0.0, -1.0],
+    };
+    let mut vertices = Vec::with_capacity(CONE_SEGMENTS * 6);
+    for segment in 0..CONE_SEGMENTS {
+        let (a, b) = (point(segment), point(segment + 1));
+        vertices.extend_from_slice(&[tip, a, b, center, b, a]);
+    }
+    vertices
+}
@@ -0,0 +1,345 @@
+use crate::{
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::{Renderer, drawable::Drawable},
+};
+use glium::{
+    BlitMask, BlitTarget, DrawError, DrawParameters, Program, Rect, Surface,
+    framebuffer::{MultiOutputFrameBuffer, SimpleFrameBuffer},
+    index::IndicesSource,
+    uniforms::{MagnifySamplerFilter, UniformValue, Uniforms},
+    vertex::{MultiVerticesSource, VerticesSource},
+};
+
+/// A `Renderer` that can be passed around as one type no matter what it draws to, for trait objects
+/// like `State` that can't take `impl Renderer`. Only the surface is kept, so methods the wrapped
+/// `Renderer` overrides aren't used
+pub struct DynRenderer<'a> {
+    surface: ErasedSurface<'a>,
+}
+
+impl<'a> DynRenderer<'a> {
+    pub fn new(renderer: &'a mut impl Renderer) -> Self {
+        Self {
+            surface: ErasedSurface::Mut(renderer.get_surface_mut()),
+        }
+    }
+}
+
+impl Renderer for DynRenderer<'_> {
+    fn draw<D, U>(&mut self, ctx: &mut Context, drawable: &D, uniforms: &U) -> EngineResult
+    where
+        D: Drawable,
+        U: Uniforms,
+    {
+        let program = if let Some(program) = ctx.get_program(drawable.get_program()) {
+            program
+        } else {
+            return Err(EngineError::Error(format!(
+                "Program {} not found in the context",
+                drawable.get_program()
+            )));
+        };
+
+        self.surface.draw(
+            drawable.get_vbo(),
+            drawable.get_ibo(),
+            program,
+            uniforms,
+            &drawable.get_draw_params(),
+        )?;
+        Ok(())
+    }
+    fn get_surface(&self) -> &impl Surface {
+        &self.surface
+    }
+    fn get_surface_mut(&mut self) -> &mut impl Surface {
+        &mut self.surface
+    }
+}
+
+/// Object safe half of glium's `Surface`, the generic parts are turned into plain values first
+trait DynSurface {
+    fn clear_dyn(
+        &mut self,
+        rect: Option<&Rect>,
+        color: Option<(f32, f32, f32, f32)>,
+        color_srgb: bool,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    );
+    fn dimensions_dyn(&self) -> (u32, u32);
+    fn depth_buffer_bits_dyn(&self) -> Option<u16>;
+    fn stencil_buffer_bits_dyn(&self) -> Option<u16>;
+    fn draw_dyn(
+        &mut self,
+        vertices: Vec<VerticesSource<'_>>,
+        indices: IndicesSource<'_>,
+        program: &Program,
+        uniforms: &dyn DynUniforms,
+        draw_parameters: &DrawParameters<'_>,
+    ) -> Result<(), DrawError>;
+    fn blit_buffers_from_frame_dyn(
+        &self,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    );
+    fn blit_buffers_from_simple_framebuffer_dyn(
+        &self,
+        source: &SimpleFrameBuffer<'_>,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    );
+    fn blit_buffers_from_multioutput_framebuffer_dyn(
+        &self,
+        source: &MultiOutputFrameBuffer<'_>,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    );
+    fn blit_color_dyn(
+        &self,
+        source_rect: &Rect,
+        target: &dyn DynSurface,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    );
+}
+
+impl<S: Surface> DynSurface for S {
+    fn clear_dyn(
+        &mut self,
+        rect: Option<&Rect>,
+        color: Option<(f32, f32, f32, f32)>,
+        color_srgb: bool,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) {
+        self.clear(rect, color, color_srgb, depth, stencil);
+    }
+    fn dimensions_dyn(&self) -> (u32, u32) {
+        self.get_dimensions()
+    }
+    fn depth_buffer_bits_dyn(&self) -> Option<u16> {
+        self.get_depth_buffer_bits()
+    }
+    fn stencil_buffer_bits_dyn(&self) -> Option<u16> {
+        self.get_stencil_buffer_bits()
+    }
+    fn draw_dyn(
+        &mut self,
+        vertices: Vec<VerticesSource<'_>>,
+        indices: IndicesSource<'_>,
+        program: &Program,
+        uniforms: &dyn DynUniforms,
+        draw_parameters: &DrawParameters<'_>,
+    ) -> Result<(), DrawError> {
+        self.draw(
+            VertexList(vertices),
+            indices,
+            program,
+            &UniformsRef(uniforms),
+            draw_parameters,
+        )
+    }
+    fn blit_buffers_from_frame_dyn(
+        &self,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    ) {
+        self.blit_buffers_from_frame(source_rect, target_rect, filter, mask);
+    }
+    fn blit_buffers_from_simple_framebuffer_dyn(
+        &self,
+        source: &SimpleFrameBuffer<'_>,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    ) {
+        self.blit_buffers_from_simple_framebuffer(source, source_rect, target_rect, filter, mask);
+    }
+    fn blit_buffers_from_multioutput_framebuffer_dyn(
+        &self,
+        source: &MultiOutputFrameBuffer<'_>,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    ) {
+        self.blit_buffers_from_multioutput_framebuffer(
+            source,
+            source_rect,
+            target_rect,
+            filter,
+            mask,
+        );
+    }
+    fn blit_color_dyn(
+        &self,
+        source_rect: &Rect,
+        target: &dyn DynSurface,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    ) {
+        self.blit_color(
+            source_rect,
+            &ErasedSurface::Ref(target),
+            target_rect,
+            filter,
+        );
+    }
+}
+
+/// `DynRenderer`'s surface, or a surface being blitted to which is only ever borrowed
+enum ErasedSurface<'a> {
+    Mut(&'a mut dyn DynSurface),
+    Ref(&'a dyn DynSurface),
+}
+
+impl ErasedSurface<'_> {
+    fn get(&self) -> &dyn DynSurface {
+        match self {
+            Self::Mut(surface) => *surface,
+            Self::Ref(surface) => *surface,
+        }
+    }
+}
+
+impl Surface for ErasedSurface<'_> {
+    fn clear(
+        &mut self,
+        rect: Option<&Rect>,
+        color: Option<(f32, f32, f32, f32)>,
+        color_srgb: bool,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) {
+        // Glium only blits to a blit target, it's never cleared or drawn to
+        if let Self::Mut(surface) = self {
+            surface.clear_dyn(rect, color, color_srgb, depth, stencil);
+        }
+    }
+    fn get_dimensions(&self) -> (u32, u32) {
+        self.get().dimensions_dyn()
+    }
+    fn get_depth_buffer_bits(&self) -> Option<u16> {
+        self.get().depth_buffer_bits_dyn()
+    }
+    fn get_stencil_buffer_bits(&self) -> Option<u16> {
+        self.get().stencil_buffer_bits_dyn()
+    }
+    fn draw<'a, 'b, V, I, U>(
+        &mut self,
+        vertices: V,
+        indices: I,
+        program: &Program,
+        uniforms: &U,
+        draw_parameters: &DrawParameters<'_>,
+    ) -> Result<(), DrawError>
+    where
+        V: MultiVerticesSource<'b>,
+        I: Into<IndicesSource<'a>>,
+        U: Uniforms,
+    {
+        match self {
+            Self::Mut(surface) => surface.draw_dyn(
+                vertices.iter().collect(),
+                indices.into(),
+                program,
+                uniforms,
+                draw_parameters,
+            ),
+            Self::Ref(_) => Ok(()),
+        }
+    }
+    fn blit_buffers_from_frame(
+        &self,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    ) {
+        self.get()
+            .blit_buffers_from_frame_dyn(source_rect, target_rect, filter, mask);
+    }
+    fn blit_buffers_from_simple_framebuffer(
+        &self,
+        source: &SimpleFrameBuffer<'_>,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    ) {
+        self.get().blit_buffers_from_simple_framebuffer_dyn(
+            source,
+            source_rect,
+            target_rect,
+            filter,
+            mask,
+        );
+    }
+    fn blit_buffers_from_multioutput_framebuffer(
+        &self,
+        source: &MultiOutputFrameBuffer<'_>,
+        source_rect: &Rect,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+        mask: BlitMask,
+    ) {
+        self.get().blit_buffers_from_multioutput_framebuffer_dyn(
+            source,
+            source_rect,
+            target_rect,
+            filter,
+            mask,
+        );
+    }
+    fn blit_color<S>(
+        &self,
+        source_rect: &Rect,
+        target: &S,
+        target_rect: &BlitTarget,
+        filter: MagnifySamplerFilter,
+    ) where
+        S: Surface,
+    {
+        self.get()
+            .blit_color_dyn(source_rect, target, target_rect, filter);
+    }
+}
+
+struct VertexList<'a>(Vec<VerticesSource<'a>>);
+
+impl<'a> MultiVerticesSource<'a> for VertexList<'a> {
+    type Iterator = std::vec::IntoIter<VerticesSource<'a>>;
+    fn iter(self) -> Self::Iterator {
+        self.0.into_iter()
+    }
+}
+
+/// Object safe version of glium's `Uniforms`
+trait DynUniforms {
+    fn visit_dyn<'a>(&'a self, output: &mut dyn FnMut(&str, UniformValue<'a>));
+}
+
+impl<U: Uniforms> DynUniforms for U {
+    fn visit_dyn<'a>(&'a self, output: &mut dyn FnMut(&str, UniformValue<'a>)) {
+        self.visit_values(|name, value| output(name, value));
+    }
+}
+
+struct UniformsRef<'u>(&'u dyn DynUniforms);
+
+impl Uniforms for UniformsRef<'_> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.0.visit_dyn(&mut output);
+    }
+}
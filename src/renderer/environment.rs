@@ -0,0 +1,254 @@
+use crate::{
+    context::Context,
+    error::EngineResult,
+    renderer::skybox::Skybox,
+    shaders::{BRDF_LUT_FS, ENVIRONMENT_VS, IRRADIANCE_FS, PREFILTER_FS},
+};
+use glium::{
+    DrawParameters, IndexBuffer, Program, Surface, Texture2d, VertexBuffer,
+    framebuffer::SimpleFrameBuffer,
+    implement_vertex,
+    index::PrimitiveType,
+    texture::{CubeLayer, Cubemap, MipmapsOption, UncompressedFloatFormat},
+    uniform,
+    uniforms::{
+        EmptyUniforms, MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerBehavior,
+        SamplerWrapFunction, UniformValue, Uniforms,
+    },
+};
+use std::fmt::{self, Debug, Formatter};
+
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTER_SIZE: u32 = 128;
+/// Mip levels of the prefiltered map, from smooth at 0 to fully rough at the last one
+pub(crate) const PREFILTER_LEVELS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+const FACES: [CubeLayer; 6] = [
+    CubeLayer::PositiveX,
+    CubeLayer::NegativeX,
+    CubeLayer::PositiveY,
+    CubeLayer::NegativeY,
+    CubeLayer::PositiveZ,
+    CubeLayer::NegativeZ,
+];
+
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 2],
+}
+implement_vertex!(Vertex, position);
+
+/// Draws a fragment shader over each face of a cubemap, the vertex shader is `ENVIRONMENT_VS`
+pub(crate) struct CubeFaceRenderer {
+    quad: VertexBuffer<Vertex>,
+    indices: IndexBuffer<u16>,
+}
+
+impl CubeFaceRenderer {
+    pub(crate) fn new(ctx: &Context) -> EngineResult<Self> {
+        Ok(Self {
+            quad: VertexBuffer::new(
+                &ctx.display,
+                &[
+                    Vertex {
+                        position: [-1.0, -1.0],
+                    },
+                    Vertex {
+                        position: [1.0, -1.0],
+                    },
+                    Vertex {
+                        position: [1.0, 1.0],
+                    },
+                    Vertex {
+                        position: [-1.0, 1.0],
+                    },
+                ],
+            )?,
+            indices: IndexBuffer::new(
+                &ctx.display,
+                PrimitiveType::TriangleStrip,
+                &[1, 2, 0, 3u16],
+            )?,
+        })
+    }
+
+    /// Draws every face of one mip level, the program also gets the face's index as `face`
+    pub(crate) fn draw(
+        &self,
+        ctx: &Context,
+        target: &Cubemap,
+        level: u32,
+        program: &Program,
+        uniforms: &impl Uniforms,
+    ) -> EngineResult {
+        let Some(mipmap) = target.mipmap(level) else {
+            return Ok(());
+        };
+        for (face, layer) in FACES.into_iter().enumerate() {
+            let mut framebuffer = SimpleFrameBuffer::new(&ctx.display, mipmap.image(layer))?;
+            framebuffer.draw(
+                &self.quad,
+                &self.indices,
+                program,
+                &FaceUniforms {
+                    face: face as i32,
+                    uniforms,
+                },
+                &DrawParameters::default(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn draw_flat(&self, target: &Texture2d, program: &Program) -> EngineResult {
+        target.as_surface().draw(
+            &self.quad,
+            &self.indices,
+            program,
+            &EmptyUniforms,
+            &DrawParameters::default(),
+        )?;
+        Ok(())
+    }
+}
+
+struct FaceUniforms<'a, U: Uniforms> {
+    face: i32,
+    uniforms: &'a U,
+}
+
+impl<U: Uniforms> Uniforms for FaceUniforms<'_, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+        output("face", UniformValue::SignedInt(self.face));
+    }
+}
+
+/// Linear filtering and no wrapping, what the cubemaps are read with
+pub(crate) fn cubemap_sampler() -> SamplerBehavior {
+    SamplerBehavior {
+        magnify_filter: MagnifySamplerFilter::Linear,
+        minify_filter: MinifySamplerFilter::Linear,
+        wrap_function: (
+            SamplerWrapFunction::Clamp,
+            SamplerWrapFunction::Clamp,
+            SamplerWrapFunction::Clamp,
+        ),
+        ..Default::default()
+    }
+}
+
+/// Image based lighting made from a sky, lights `PbrMaterial`s from every direction.
+/// Put it in `ctx.lights.environment` to use it
+pub struct Environment {
+    /// Average light coming from each direction, for diffuse
+    irradiance: Cubemap,
+    /// The sky blurred more at each mip level, for specular at each roughness
+    prefiltered: Cubemap,
+    /// How much of the prefiltered light is reflected by angle and roughness
+    brdf_lut: Texture2d,
+}
+
+impl Debug for Environment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Environment").finish_non_exhaustive()
+    }
+}
+
+impl Environment {
+    /// Builds the maps from the skybox's cubemap, takes a moment so do it while loading.
+    /// Calls `Skybox::prepare_draw` first so the cubemap has the sky in it
+    pub fn from_skybox(ctx: &mut Context, skybox: &Skybox) -> EngineResult<Self> {
+        skybox.prepare_draw(ctx, MagnifySamplerFilter::Linear)?;
+        Self::from_cubemap(ctx, skybox.get_raw_cubemap(), !skybox.is_hdr())
+    }
+
+    /// Same as `from_skybox` for a cubemap that's already filled in, it's looked up upside down like
+    /// the skybox shader does. `srgb` is for cubemaps made from regular images rather than linear color
+    pub fn from_cubemap(ctx: &Context, sky: &Cubemap, srgb: bool) -> EngineResult<Self> {
+        let display = &ctx.display;
+        let faces = CubeFaceRenderer::new(ctx)?;
+
+        let irradiance_program =
+            Program::from_source(display, ENVIRONMENT_VS, IRRADIANCE_FS, None)?;
+        let irradiance = Cubemap::empty_with_format(
+            display,
+            UncompressedFloatFormat::F16F16F16,
+            MipmapsOption::NoMipmap,
+            IRRADIANCE_SIZE,
+        )?;
+        faces.draw(
+            ctx,
+            &irradiance,
+            0,
+            &irradiance_program,
+            &uniform! {
+                sky: Sampler(sky, cubemap_sampler()),
+                srgb: srgb,
+            },
+        )?;
+
+        let prefilter_program = Program::from_source(display, ENVIRONMENT_VS, PREFILTER_FS, None)?;
+        let prefiltered = Cubemap::empty_with_format(
+            display,
+            UncompressedFloatFormat::F16F16F16,
+            MipmapsOption::EmptyMipmapsMax(PREFILTER_LEVELS - 1),
+            PREFILTER_SIZE,
+        )?;
+        for level in 0..PREFILTER_LEVELS {
+            faces.draw(
+                ctx,
+                &prefiltered,
+                level,
+                &prefilter_program,
+                &uniform! {
+                    roughness: level as f32 / (PREFILTER_LEVELS - 1) as f32,
+                    sky: Sampler(sky, cubemap_sampler()),
+                    srgb: srgb,
+                },
+            )?;
+        }
+
+        let brdf_program = Program::from_source(display, ENVIRONMENT_VS, BRDF_LUT_FS, None)?;
+        let brdf_lut = Texture2d::empty_with_format(
+            display,
+            UncompressedFloatFormat::F16F16,
+            MipmapsOption::NoMipmap,
+            BRDF_LUT_SIZE,
+            BRDF_LUT_SIZE,
+        )?;
+        faces.draw_flat(&brdf_lut, &brdf_program)?;
+
+        Ok(Self {
+            irradiance,
+            prefiltered,
+            brdf_lut,
+        })
+    }
+
+    pub(crate) fn visit_uniforms<'a>(&'a self, output: &mut dyn FnMut(&str, UniformValue<'a>)) {
+        let linear = cubemap_sampler();
+        output(
+            "irradiance_map",
+            UniformValue::Cubemap(&self.irradiance, Some(linear)),
+        );
+        output(
+            "prefiltered_map",
+            UniformValue::Cubemap(
+                &self.prefiltered,
+                Some(SamplerBehavior {
+                    minify_filter: MinifySamplerFilter::LinearMipmapLinear,
+                    ..linear
+                }),
+            ),
+        );
+        output(
+            "prefiltered_levels",
+            UniformValue::Float(PREFILTER_LEVELS as f32),
+        );
+        output(
+            "brdf_lut",
+            UniformValue::Texture2d(&self.brdf_lut, Some(linear)),
+        );
+    }
+}
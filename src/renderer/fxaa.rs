@@ -1,7 +1,7 @@
 use crate::{
     context::Context,
     error::{EngineError, EngineResult},
-    renderer::{Drawable, Renderer},
+    renderer::{Drawable, Renderer, render_target::Resizable},
     shaders::{FXAA_FS, FXAA_VS},
 };
 use glium::{
@@ -55,6 +55,19 @@ impl FXAA {
         })
     }
 
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        self.color.dimensions()
+    }
+
+    /// Recreates the offscreen buffers, `Context::resize_with_window` can call it for you
+    pub fn resize(&mut self, ctx: &Context, width: u32, height: u32) -> EngineResult {
+        if self.get_dimensions() != (width, height) {
+            self.color = Texture2d::empty(&ctx.display, width, height)?;
+            self.depth = DepthRenderBuffer::new(&ctx.display, DepthFormat::I24, width, height)?;
+        }
+        Ok(())
+    }
+
     /// Sends a program call 'fxaa' to the Context
     pub fn send_program(ctx: &mut Context) -> EngineResult {
         ctx.add_program(
@@ -70,6 +83,12 @@ impl FXAA {
     }
 }
 
+impl Resizable for FXAA {
+    fn resize(&mut self, ctx: &Context, width: u32, height: u32) -> EngineResult {
+        FXAA::resize(self, ctx, width, height)
+    }
+}
+
 impl Drawable for FXAA {
     fn get_vbo(&self) -> impl MultiVerticesSource {
         &self.vbo
@@ -0,0 +1,166 @@
+use crate::{
+    asset::model::Model,
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::drawable::Drawable,
+    shaders::{INSTANCED_FS, INSTANCED_VS},
+};
+use glium::{
+    BackfaceCullingMode, Depth, DepthTest, DrawParameters, Vertex, VertexBuffer, implement_vertex,
+    index::{IndicesSource, NoIndices, PrimitiveType},
+    vertex::{MultiVerticesSource, VerticesSource},
+};
+use std::rc::Rc;
+use vek::{Mat4, Rgba};
+
+/// The per-instance data used by the built-in "instanced" program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    /// Where the instance is in the world, like `Transform::get_matrix`
+    pub transform: [[f32; 4]; 4],
+    /// Multiplied with the texture if there is one
+    pub color: [f32; 4],
+}
+implement_vertex!(Instance, transform, color);
+
+impl Instance {
+    pub fn new(transform: Mat4<f32>) -> Self {
+        Self {
+            transform: transform.into_col_arrays(),
+            color: [1.0; 4],
+        }
+    }
+    pub fn with_color(mut self, color: Rgba<f32>) -> Self {
+        self.color = color.into_array();
+        self
+    }
+}
+
+/// Data sent to the shader once per instance instead of once per vertex. `T` is any type made with
+/// `implement_vertex!` so there can be custom attributes, and the buffer grows when more instances are set
+/// than fit. It's persistently mapped when the driver supports it so it can be rewritten every frame
+pub struct InstanceBuffer<T: Vertex + Copy> {
+    buffer: VertexBuffer<T>,
+    len: usize,
+}
+
+impl<T: Vertex + Copy> InstanceBuffer<T> {
+    pub fn new(ctx: &Context) -> EngineResult<Self> {
+        Self::with_capacity(ctx, 64)
+    }
+    pub fn with_capacity(ctx: &Context, capacity: usize) -> EngineResult<Self> {
+        Ok(Self {
+            buffer: Self::create_buffer(ctx, capacity.max(1))?,
+            len: 0,
+        })
+    }
+
+    fn create_buffer(ctx: &Context, capacity: usize) -> EngineResult<VertexBuffer<T>> {
+        let buffer = match VertexBuffer::empty_persistent(&ctx.display, capacity) {
+            Ok(buffer) => buffer,
+            Err(_) => VertexBuffer::empty_dynamic(&ctx.display, capacity)?,
+        };
+        // Checked here since `per_instance` can't fail
+        if buffer.per_instance().is_err() {
+            return Err(EngineError::GliumError(
+                "Instancing isn't supported by this version of OpenGL".to_string(),
+            ));
+        }
+        Ok(buffer)
+    }
+
+    /// Replaces every instance, the buffer is remade twice as big as needed if they don't fit
+    pub fn set(&mut self, ctx: &Context, instances: &[T]) -> EngineResult {
+        if instances.len() > self.buffer.len() {
+            self.buffer = Self::create_buffer(ctx, instances.len().next_power_of_two())?;
+        }
+        if let Some(slice) = self.buffer.slice_mut(0..instances.len()) {
+            slice.write(instances);
+        }
+        self.len = instances.len();
+        Ok(())
+    }
+
+    /// How many instances were last set, only these are drawn
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// How many instances fit before the buffer has to grow
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Pass it with the vertices as a tuple in `Drawable::get_vbo`, like `(vbo, instances.per_instance())`.
+    /// Only the instances that were last set are drawn, not the whole buffer
+    pub fn per_instance(&self) -> VerticesSource<'_> {
+        // Made by hand since glium's `per_instance` borrows the slice it's called on
+        let slice = self
+            .buffer
+            .slice(0..self.len)
+            .expect("The length is never more than the capacity");
+        VerticesSource::VertexBuffer(slice.as_slice_any(), self.buffer.get_bindings(), true)
+    }
+}
+
+/// A `Model` drawn many times in one draw call, once for each instance in `instances`.
+/// The built-in "instanced" program takes `Instance`s and the `persp` and `view` uniforms, pass
+/// `has_texture: true` and `tex` to texture it. Other instance types need their own program
+pub struct InstancedModel<T: Vertex + Copy = Instance> {
+    pub model: Rc<Model>,
+    pub instances: InstanceBuffer<T>,
+    pub program: String,
+    pub draw_params: DrawParameters<'static>,
+}
+
+impl<T: Vertex + Copy> InstancedModel<T> {
+    pub fn new(ctx: &mut Context, model: Rc<Model>) -> EngineResult<Self> {
+        if ctx.get_program("instanced").is_none() {
+            ctx.new_program("instanced", INSTANCED_VS, INSTANCED_FS, None)?;
+        }
+        Ok(Self {
+            model,
+            instances: InstanceBuffer::new(ctx)?,
+            program: "instanced".to_string(),
+            draw_params: DrawParameters {
+                depth: Depth {
+                    test: DepthTest::IfLess,
+                    write: true,
+                    ..Default::default()
+                },
+                backface_culling: BackfaceCullingMode::CullClockwise,
+                ..Default::default()
+            },
+        })
+    }
+    pub fn with_program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+    pub fn with_draw_params(mut self, draw_params: DrawParameters<'static>) -> Self {
+        self.draw_params = draw_params;
+        self
+    }
+
+    /// Same as `InstanceBuffer::set`
+    pub fn set_instances(&mut self, ctx: &Context, instances: &[T]) -> EngineResult {
+        self.instances.set(ctx, instances)
+    }
+}
+
+impl<T: Vertex + Copy> Drawable for InstancedModel<T> {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        (self.model.get_vbo(), self.instances.per_instance())
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TrianglesList)
+    }
+    fn get_program(&self) -> String {
+        self.program.clone()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        self.draw_params.clone()
+    }
+}
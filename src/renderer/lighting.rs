@@ -0,0 +1,477 @@
+use crate::{
+    asset::model::Model,
+    context::Context,
+    error::EngineResult,
+    renderer::{
+        Renderer,
+        drawable::Drawable,
+        environment::Environment,
+        shadow::{CASCADES, ShadowMap, ShadowSettings},
+    },
+    shaders::{LIT_FS, LIT_VS},
+};
+use glium::{
+    DrawParameters,
+    index::{IndicesSource, NoIndices, PrimitiveType},
+    uniform,
+    uniforms::{UniformValue, Uniforms},
+    vertex::MultiVerticesSource,
+};
+use std::rc::Rc;
+use vek::{Mat4, Rgb, Vec3};
+
+/// Most lights a lit draw uses, the shaders are compiled with the same number
+pub const MAX_LIGHTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightHandle(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    /// Lights everything from the same direction, like the sun
+    Directional {
+        /// Where the light is pointing
+        direction: Vec3<f32>,
+        color: Rgb<f32>,
+        intensity: f32,
+    },
+    /// Shines every way from a position and fades out by `range`
+    Point {
+        position: Vec3<f32>,
+        color: Rgb<f32>,
+        intensity: f32,
+        range: f32,
+    },
+    /// A point light that only shines in a cone, the edge fades from `inner_angle` to `outer_angle`.
+    /// The angles are in radians from the middle of the cone
+    Spot {
+        position: Vec3<f32>,
+        direction: Vec3<f32>,
+        color: Rgb<f32>,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+impl Light {
+    pub fn directional(direction: Vec3<f32>, color: Rgb<f32>) -> Self {
+        Self::Directional {
+            direction,
+            color,
+            intensity: 1.0,
+        }
+    }
+    pub fn point(position: Vec3<f32>, color: Rgb<f32>, range: f32) -> Self {
+        Self::Point {
+            position,
+            color,
+            intensity: 1.0,
+            range,
+        }
+    }
+    /// `angle` is the outer angle, the inner one starts at 80% of it
+    pub fn spot(
+        position: Vec3<f32>,
+        direction: Vec3<f32>,
+        color: Rgb<f32>,
+        range: f32,
+        angle: f32,
+    ) -> Self {
+        Self::Spot {
+            position,
+            direction,
+            color,
+            intensity: 1.0,
+            range,
+            inner_angle: angle * 0.8,
+            outer_angle: angle,
+        }
+    }
+    pub fn with_intensity(mut self, new_intensity: f32) -> Self {
+        match &mut self {
+            Self::Directional { intensity, .. }
+            | Self::Point { intensity, .. }
+            | Self::Spot { intensity, .. } => *intensity = new_intensity,
+        }
+        self
+    }
+
+    pub fn get_position(&self) -> Option<Vec3<f32>> {
+        match self {
+            Self::Directional { .. } => None,
+            Self::Point { position, .. } | Self::Spot { position, .. } => Some(*position),
+        }
+    }
+
+    /// How far a light is from mattering at `point`, directional lights always matter
+    fn distance_to(&self, point: Vec3<f32>) -> f32 {
+        match self {
+            Self::Directional { .. } => f32::NEG_INFINITY,
+            Self::Point {
+                position, range, ..
+            }
+            | Self::Spot {
+                position, range, ..
+            } => position.distance(point) - range,
+        }
+    }
+}
+
+/// The lights in the scene, kept in `ctx.lights` and used by the built-in "lit" and "pbr" programs
+#[derive(Debug, Clone)]
+pub struct Lights {
+    /// Added to every lit surface so the unlit sides aren't black
+    pub ambient: Rgb<f32>,
+    /// Lights `PbrMaterial`s from the sky in place of `ambient`, make one with `Environment::from_skybox`
+    pub environment: Option<Rc<Environment>>,
+    /// Multiplied with the light from `environment`
+    pub environment_intensity: f32,
+    pub shadows: ShadowSettings,
+    lights: Vec<(LightHandle, Light)>,
+    casting_shadows: Vec<LightHandle>,
+    next_handle: u32,
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Self {
+            ambient: Rgb::broadcast(0.1),
+            environment: None,
+            environment_intensity: 1.0,
+            shadows: ShadowSettings::default(),
+            lights: Vec::new(),
+            casting_shadows: Vec::new(),
+            next_handle: 0,
+        }
+    }
+}
+
+impl Lights {
+    pub fn add(&mut self, light: Light) -> LightHandle {
+        let handle = LightHandle(self.next_handle);
+        self.next_handle += 1;
+        self.lights.push((handle, light));
+        handle
+    }
+    pub fn remove(&mut self, handle: LightHandle) -> Option<Light> {
+        let index = self.lights.iter().position(|(h, _)| *h == handle)?;
+        self.casting_shadows.retain(|h| *h != handle);
+        Some(self.lights.remove(index).1)
+    }
+    pub fn get(&self, handle: LightHandle) -> Option<&Light> {
+        self.lights
+            .iter()
+            .find_map(|(h, light)| (*h == handle).then_some(light))
+    }
+    pub fn get_mut(&mut self, handle: LightHandle) -> Option<&mut Light> {
+        self.lights
+            .iter_mut()
+            .find_map(|(h, light)| (*h == handle).then_some(light))
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (LightHandle, &Light)> {
+        self.lights.iter().map(|(handle, light)| (*handle, light))
+    }
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.lights.clear();
+        self.casting_shadows.clear();
+    }
+
+    /// Shadows are drawn by `draw_shadows`. Only the first directional light and `MAX_SPOT_SHADOWS`
+    /// spot lights get them, point lights never do
+    pub fn set_casts_shadows(&mut self, handle: LightHandle, casts_shadows: bool) {
+        self.casting_shadows.retain(|h| *h != handle);
+        if casts_shadows && self.get(handle).is_some() {
+            self.casting_shadows.push(handle);
+        }
+    }
+    pub fn casts_shadows(&self, handle: LightHandle) -> bool {
+        self.casting_shadows.contains(&handle)
+    }
+    pub fn any_casts_shadows(&self) -> bool {
+        !self.casting_shadows.is_empty()
+    }
+
+    /// Up to `MAX_LIGHTS` of the lights, directional ones first and then the closest to `point`
+    pub fn closest(&self, point: Vec3<f32>) -> Vec<(LightHandle, &Light)> {
+        let mut lights: Vec<(LightHandle, &Light)> = self.iter().collect();
+        if lights.len() > MAX_LIGHTS {
+            lights.sort_by(|(_, a), (_, b)| a.distance_to(point).total_cmp(&b.distance_to(point)));
+            lights.truncate(MAX_LIGHTS);
+        }
+        lights
+    }
+}
+
+/// Adds the "lit" program if the game hasn't added its own
+pub(crate) fn ensure_program(ctx: &mut Context) -> EngineResult {
+    if ctx.get_program("lit").is_none() {
+        ctx.new_program("lit", LIT_VS, LIT_FS, None)?;
+    }
+    Ok(())
+}
+
+/// A material that sets its own uniforms for a lit program, `Material` for "lit" and `PbrMaterial` for "pbr"
+pub(crate) trait MaterialUniforms {
+    fn visit_uniforms<'a>(&'a self, output: &mut dyn FnMut(&str, UniformValue<'a>));
+}
+
+/// Uniforms for one light in the shader's `lights` array
+pub(crate) struct LightUniform {
+    // w is 0 for directional lights
+    position: [f32; 4],
+    direction: [f32; 3],
+    color: [f32; 3],
+    range: f32,
+    // Cosines of the angles, below -1 for lights that aren't spots so the cone is everything
+    inner_cone: f32,
+    outer_cone: f32,
+    // First map in the shadow atlas, -1 for no shadows
+    shadow_tile: i32,
+}
+
+impl LightUniform {
+    pub(crate) fn new(light: &Light, shadow_tile: Option<usize>) -> Self {
+        let shadow_tile = shadow_tile.map_or(-1, |tile| tile as i32);
+        match *light {
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => Self {
+                position: [0.0; 4],
+                direction: direction.normalized().into_array(),
+                color: (color * intensity).into_array(),
+                range: 0.0,
+                inner_cone: -2.0,
+                outer_cone: -2.0,
+                shadow_tile,
+            },
+            Light::Point {
+                position,
+                color,
+                intensity,
+                range,
+            } => Self {
+                position: position.with_w(1.0).into_array(),
+                direction: [0.0, -1.0, 0.0],
+                color: (color * intensity).into_array(),
+                range,
+                inner_cone: -2.0,
+                outer_cone: -2.0,
+                shadow_tile,
+            },
+            Light::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                range,
+                inner_angle,
+                outer_angle,
+            } => Self {
+                position: position.with_w(1.0).into_array(),
+                direction: direction.normalized().into_array(),
+                color: (color * intensity).into_array(),
+                range,
+                inner_cone: inner_angle.cos(),
+                outer_cone: outer_angle.cos(),
+                shadow_tile,
+            },
+        }
+    }
+
+    /// Sends the fields as `<name>.position` and so on, `name` is like `lights[0]`
+    pub(crate) fn visit_uniforms<'a>(
+        &'a self,
+        name: &str,
+        output: &mut dyn FnMut(&str, UniformValue<'a>),
+    ) {
+        output(
+            &format!("{name}.position"),
+            UniformValue::Vec4(self.position),
+        );
+        output(
+            &format!("{name}.direction"),
+            UniformValue::Vec3(self.direction),
+        );
+        output(&format!("{name}.color"), UniformValue::Vec3(self.color));
+        output(&format!("{name}.range"), UniformValue::Float(self.range));
+        output(
+            &format!("{name}.inner_cone"),
+            UniformValue::Float(self.inner_cone),
+        );
+        output(
+            &format!("{name}.outer_cone"),
+            UniformValue::Float(self.outer_cone),
+        );
+        output(
+            &format!("{name}.shadow_tile"),
+            UniformValue::SignedInt(self.shadow_tile),
+        );
+    }
+}
+
+/// Everything the "lit" and "pbr" programs need on top of the game's own uniforms
+pub(crate) struct LitUniforms<'a, U: Uniforms> {
+    uniforms: U,
+    material: &'a dyn MaterialUniforms,
+    camera_position: [f32; 3],
+    ambient: [f32; 3],
+    lights: Vec<LightUniform>,
+    shadows: Option<(&'a ShadowMap, ShadowSettings)>,
+    camera_forward: [f32; 3],
+    environment: Option<(Rc<Environment>, f32)>,
+}
+
+impl<'a, U: Uniforms> LitUniforms<'a, U> {
+    /// Lights are picked by how close they are to `center`, usually the position of what's being drawn.
+    /// `shadow_map` is taken out of the `Context` first so it can be borrowed while drawing
+    pub(crate) fn new(
+        ctx: &Context,
+        uniforms: U,
+        material: &'a dyn MaterialUniforms,
+        center: Vec3<f32>,
+        shadow_map: Option<&'a ShadowMap>,
+    ) -> Self {
+        let shadow_map = shadow_map.filter(|shadow_map| !shadow_map.tiles.is_empty());
+        Self {
+            uniforms,
+            material,
+            camera_position: ctx.camera.position.into_array(),
+            ambient: ctx.lights.ambient.into_array(),
+            lights: ctx
+                .lights
+                .closest(center)
+                .into_iter()
+                .map(|(handle, light)| {
+                    LightUniform::new(
+                        light,
+                        shadow_map.and_then(|shadow_map| shadow_map.first_tile(handle)),
+                    )
+                })
+                .collect(),
+            shadows: shadow_map.map(|shadow_map| (shadow_map, ctx.lights.shadows)),
+            camera_forward: ctx.camera.get_directions().0.into_array(),
+            environment: ctx
+                .lights
+                .environment
+                .clone()
+                .map(|environment| (environment, ctx.lights.environment_intensity)),
+        }
+    }
+}
+
+impl<U: Uniforms> Uniforms for LitUniforms<'_, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+        self.material.visit_uniforms(&mut output);
+        output("camera_position", UniformValue::Vec3(self.camera_position));
+        output("ambient", UniformValue::Vec3(self.ambient));
+        output(
+            "has_environment",
+            UniformValue::Bool(self.environment.is_some()),
+        );
+        if let Some((environment, intensity)) = &self.environment {
+            environment.visit_uniforms(&mut output);
+            output("environment_intensity", UniformValue::Float(*intensity));
+        }
+        output(
+            "light_count",
+            UniformValue::SignedInt(self.lights.len() as i32),
+        );
+        for (i, light) in self.lights.iter().enumerate() {
+            light.visit_uniforms(&format!("lights[{i}]"), &mut output);
+        }
+        visit_shadow_uniforms(self.shadows, self.camera_forward, &mut output);
+    }
+}
+
+/// The uniforms `SHADOWS_GLSL` needs besides `camera_position`, `has_shadows` is false when `shadows` is `None`
+pub(crate) fn visit_shadow_uniforms<'a>(
+    shadows: Option<(&'a ShadowMap, ShadowSettings)>,
+    camera_forward: [f32; 3],
+    output: &mut dyn FnMut(&str, UniformValue<'a>),
+) {
+    output("has_shadows", UniformValue::Bool(shadows.is_some()));
+    let Some((shadow_map, settings)) = shadows else {
+        return;
+    };
+    output("shadow_map", shadow_map.uniform_value());
+    output("shadow_atlas", UniformValue::Vec2(shadow_map.atlas_size()));
+    for (i, tile) in shadow_map.tiles.iter().enumerate() {
+        output(
+            &format!("shadow_matrices[{i}]"),
+            UniformValue::Mat4(tile.matrix.into_col_arrays()),
+        );
+        output(
+            &format!("shadow_texel_scales[{i}]"),
+            UniformValue::Float(tile.texel_scale),
+        );
+    }
+    let mut cascade_splits = [0.0; 4];
+    cascade_splits[..CASCADES].copy_from_slice(&settings.cascade_distances);
+    output("cascade_splits", UniformValue::Vec4(cascade_splits));
+    output("cascade_count", UniformValue::SignedInt(CASCADES as i32));
+    output("camera_forward", UniformValue::Vec3(camera_forward));
+    output("shadow_bias", UniformValue::Float(settings.bias));
+    output(
+        "shadow_pcf_radius",
+        UniformValue::SignedInt(settings.pcf_radius as i32),
+    );
+}
+
+/// A `Model` drawn with a lit program by `Renderer::draw_model` and `Renderer::draw_pbr`
+pub(crate) struct LitModel<'a> {
+    pub(crate) model: &'a Model,
+    pub(crate) program: &'static str,
+    pub(crate) draw_params: DrawParameters<'static>,
+}
+
+impl Drawable for LitModel<'_> {
+    fn get_vbo(&self) -> impl MultiVerticesSource<'_> {
+        self.model.get_vbo()
+    }
+    fn get_ibo(&self) -> impl Into<IndicesSource<'_>> {
+        NoIndices(PrimitiveType::TrianglesList)
+    }
+    fn get_program(&self) -> String {
+        self.program.to_string()
+    }
+    fn get_draw_params(&self) -> DrawParameters<'_> {
+        self.draw_params.clone()
+    }
+}
+
+/// Draws `model` at `transform` with one of the lit programs, which has to be added already
+pub(crate) fn draw_lit(
+    renderer: &mut (impl Renderer + ?Sized),
+    ctx: &mut Context,
+    model: &LitModel<'_>,
+    transform: Mat4<f32>,
+    material: &dyn MaterialUniforms,
+) -> EngineResult {
+    // Taken out so the uniforms can borrow it while the context is borrowed to draw
+    let shadow_map = ctx.shadow_map.take();
+    let uniforms = LitUniforms::new(
+        ctx,
+        uniform! {
+            persp: ctx.camera.get_perspective(),
+            view: ctx.camera.get_view(),
+            model: transform.into_col_arrays(),
+        },
+        material,
+        transform.cols.w.xyz(),
+        shadow_map.as_ref(),
+    );
+    let result = renderer.draw(ctx, model, &uniforms);
+    ctx.shadow_map = shadow_map;
+    result
+}
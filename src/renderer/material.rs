@@ -0,0 +1,120 @@
+use crate::renderer::{lighting::MaterialUniforms, texture::Texture};
+use glium::{BackfaceCullingMode, Depth, DepthTest, DrawParameters, uniforms::UniformValue};
+use std::rc::Rc;
+use vek::{Rgb, Rgba};
+
+/// How the specular highlight is worked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shading {
+    /// Reflects the light around the normal, sharper highlights
+    Phong,
+    /// Uses the half vector between the light and the camera, softer and wider highlights
+    #[default]
+    Blinn,
+}
+
+/// How a surface looks under the lights in `ctx.lights`, used by the built-in "lit" program
+#[derive(Clone)]
+pub struct Material {
+    /// Multiplied with `texture`
+    pub color: Rgba<f32>,
+    pub texture: Option<Rc<Texture>>,
+    pub specular: Rgb<f32>,
+    /// Multiplied with `specular`, only the red channel is used
+    pub specular_texture: Option<Rc<Texture>>,
+    /// Higher is a smaller, sharper highlight
+    pub shininess: f32,
+    /// Light given off by the surface itself, not changed by the lights
+    pub emissive: Rgb<f32>,
+    pub shading: Shading,
+    /// Draws the back of faces too, with the normal flipped
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::new(Rgba::white())
+    }
+}
+
+impl Material {
+    pub fn new(color: Rgba<f32>) -> Self {
+        Self {
+            color,
+            texture: None,
+            specular: Rgb::broadcast(0.5),
+            specular_texture: None,
+            shininess: 32.0,
+            emissive: Rgb::zero(),
+            shading: Shading::default(),
+            double_sided: false,
+        }
+    }
+    pub fn with_texture(mut self, texture: Rc<Texture>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+    pub fn with_specular(mut self, specular: Rgb<f32>, shininess: f32) -> Self {
+        self.specular = specular;
+        self.shininess = shininess;
+        self
+    }
+    pub fn with_specular_texture(mut self, specular_texture: Rc<Texture>) -> Self {
+        self.specular_texture = Some(specular_texture);
+        self
+    }
+    pub fn with_emissive(mut self, emissive: Rgb<f32>) -> Self {
+        self.emissive = emissive;
+        self
+    }
+    pub fn with_shading(mut self, shading: Shading) -> Self {
+        self.shading = shading;
+        self
+    }
+    pub fn with_double_sided(mut self, double_sided: bool) -> Self {
+        self.double_sided = double_sided;
+        self
+    }
+
+    /// Depth tested, and culls the back faces unless it's double sided
+    pub fn get_draw_params(&self) -> DrawParameters<'static> {
+        DrawParameters {
+            depth: Depth {
+                test: DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            backface_culling: if self.double_sided {
+                BackfaceCullingMode::CullingDisabled
+            } else {
+                BackfaceCullingMode::CullClockwise
+            },
+            ..Default::default()
+        }
+    }
+}
+
+impl MaterialUniforms for Material {
+    fn visit_uniforms<'a>(&'a self, output: &mut dyn FnMut(&str, UniformValue<'a>)) {
+        output("color", UniformValue::Vec4(self.color.into_array()));
+        output("specular", UniformValue::Vec3(self.specular.into_array()));
+        output("shininess", UniformValue::Float(self.shininess));
+        output("emissive", UniformValue::Vec3(self.emissive.into_array()));
+        output("blinn", UniformValue::Bool(self.shading == Shading::Blinn));
+        output("double_sided", UniformValue::Bool(self.double_sided));
+        output("has_texture", UniformValue::Bool(self.texture.is_some()));
+        if let Some(texture) = &self.texture {
+            output("tex", UniformValue::Texture2d(texture.get_texture(), None));
+        }
+        output(
+            "has_specular_texture",
+            UniformValue::Bool(self.specular_texture.is_some()),
+        );
+        if let Some(specular_texture) = &self.specular_texture {
+            output(
+                "specular_tex",
+                UniformValue::Texture2d(specular_texture.get_texture(), None),
+            );
+        }
+    }
+}
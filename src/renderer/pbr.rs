@@ -0,0 +1,159 @@
+use crate::{
+    context::Context,
+    error::EngineResult,
+    renderer::{lighting::MaterialUniforms, texture::Texture},
+    shaders::{LIT_VS, PBR_FS},
+};
+use glium::{BackfaceCullingMode, Depth, DepthTest, DrawParameters, uniforms::UniformValue};
+use std::rc::Rc;
+use vek::{Rgb, Rgba};
+
+/// Metallic-roughness material like glTF's, drawn by the built-in "pbr" program. Lit by `ctx.lights`,
+/// and by `ctx.lights.environment` instead of the flat ambient when there is one
+#[derive(Clone)]
+pub struct PbrMaterial {
+    /// Multiplied with `base_color_texture`, in linear color
+    pub base_color: Rgba<f32>,
+    /// sRGB like most images, it's converted in the shader
+    pub base_color_texture: Option<Rc<Texture>>,
+    /// 0 is plastic, wood and the like, 1 is metal
+    pub metallic: f32,
+    /// 0 is a mirror, 1 is completely matte
+    pub roughness: f32,
+    /// Roughness in green and metallic in blue, multiplied with `roughness` and `metallic`
+    pub metallic_roughness_texture: Option<Rc<Texture>>,
+    /// Tangent space normals, the tangents are worked out in the shader so models don't need them
+    pub normal_texture: Option<Rc<Texture>>,
+    /// How much `normal_texture` bends the normals
+    pub normal_scale: f32,
+    /// Darkens the environment and ambient light in cracks, only the red channel is used
+    pub occlusion_texture: Option<Rc<Texture>>,
+    /// 0 ignores `occlusion_texture`, 1 uses all of it
+    pub occlusion_strength: f32,
+    /// Light given off by the surface itself, multiplied with `emissive_texture`
+    pub emissive: Rgb<f32>,
+    pub emissive_texture: Option<Rc<Texture>>,
+    /// Draws the back of faces too, with the normal flipped
+    pub double_sided: bool,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self::new(Rgba::white())
+    }
+}
+
+impl PbrMaterial {
+    pub fn new(base_color: Rgba<f32>) -> Self {
+        Self {
+            base_color,
+            base_color_texture: None,
+            metallic: 0.0,
+            roughness: 0.5,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: Rgb::zero(),
+            emissive_texture: None,
+            double_sided: false,
+        }
+    }
+    pub fn with_base_color_texture(mut self, texture: Rc<Texture>) -> Self {
+        self.base_color_texture = Some(texture);
+        self
+    }
+    pub fn with_metallic_roughness(mut self, metallic: f32, roughness: f32) -> Self {
+        self.metallic = metallic;
+        self.roughness = roughness;
+        self
+    }
+    pub fn with_metallic_roughness_texture(mut self, texture: Rc<Texture>) -> Self {
+        self.metallic_roughness_texture = Some(texture);
+        self
+    }
+    pub fn with_normal_texture(mut self, texture: Rc<Texture>, scale: f32) -> Self {
+        self.normal_texture = Some(texture);
+        self.normal_scale = scale;
+        self
+    }
+    pub fn with_occlusion_texture(mut self, texture: Rc<Texture>, strength: f32) -> Self {
+        self.occlusion_texture = Some(texture);
+        self.occlusion_strength = strength;
+        self
+    }
+    pub fn with_emissive(mut self, emissive: Rgb<f32>) -> Self {
+        self.emissive = emissive;
+        self
+    }
+    pub fn with_emissive_texture(mut self, texture: Rc<Texture>) -> Self {
+        self.emissive_texture = Some(texture);
+        self
+    }
+    pub fn with_double_sided(mut self, double_sided: bool) -> Self {
+        self.double_sided = double_sided;
+        self
+    }
+
+    /// Depth tested, and culls the back faces unless it's double sided
+    pub fn get_draw_params(&self) -> DrawParameters<'static> {
+        DrawParameters {
+            depth: Depth {
+                test: DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            backface_culling: if self.double_sided {
+                BackfaceCullingMode::CullingDisabled
+            } else {
+                BackfaceCullingMode::CullClockwise
+            },
+            ..Default::default()
+        }
+    }
+}
+
+impl MaterialUniforms for PbrMaterial {
+    fn visit_uniforms<'a>(&'a self, output: &mut dyn FnMut(&str, UniformValue<'a>)) {
+        output(
+            "base_color",
+            UniformValue::Vec4(self.base_color.into_array()),
+        );
+        output("metallic", UniformValue::Float(self.metallic));
+        output("roughness", UniformValue::Float(self.roughness));
+        output("normal_scale", UniformValue::Float(self.normal_scale));
+        output(
+            "occlusion_strength",
+            UniformValue::Float(self.occlusion_strength),
+        );
+        output("emissive", UniformValue::Vec3(self.emissive.into_array()));
+        output("double_sided", UniformValue::Bool(self.double_sided));
+        for (name, texture) in [
+            ("base_color_texture", &self.base_color_texture),
+            (
+                "metallic_roughness_texture",
+                &self.metallic_roughness_texture,
+            ),
+            ("normal_texture", &self.normal_texture),
+            ("occlusion_texture", &self.occlusion_texture),
+            ("emissive_texture", &self.emissive_texture),
+        ] {
+            output(
+                &format!("has_{name}"),
+                UniformValue::Bool(texture.is_some()),
+            );
+            if let Some(texture) = texture {
+                output(name, UniformValue::Texture2d(texture.get_texture(), None));
+            }
+        }
+    }
+}
+
+/// Adds the "pbr" program if the game hasn't added its own. It tone maps and writes sRGB itself
+pub(crate) fn ensure_program(ctx: &mut Context) -> EngineResult {
+    if ctx.get_program("pbr").is_none() {
+        ctx.new_srgb_program("pbr", LIT_VS, PBR_FS)?;
+    }
+    Ok(())
+}
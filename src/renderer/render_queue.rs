@@ -0,0 +1,130 @@
+use crate::{
+    context::Context,
+    error::EngineResult,
+    renderer::{Renderer, drawable::Drawable, dyn_renderer::DynRenderer},
+};
+use glium::uniforms::Uniforms;
+use std::{cmp::Ordering, ops::Deref};
+use vek::Vec3;
+
+/// Which group a queued draw goes in, they're drawn in this order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    /// Sorted by program then front to back, so the depth test throws away hidden pixels early
+    #[default]
+    Opaque,
+    /// Same as `Opaque`, for things that `discard` in their shader like leaves and fences
+    AlphaTest,
+    /// Sorted back to front so blending comes out right, only by program when the distance is the same
+    Transparent,
+    /// Drawn last in the order they were queued, for things like UI and gizmos
+    Overlay,
+}
+
+type QueuedFn<'a> = Box<dyn FnOnce(&mut Context, &mut DynRenderer) -> EngineResult + 'a>;
+
+struct QueuedDraw<'a> {
+    layer: RenderLayer,
+    position: Vec3<f32>,
+    program: String,
+    draw: QueuedFn<'a>,
+}
+
+/// Collects draws and sorts them before drawing, so transparent things don't have to be drawn by hand
+/// in the right order. `ctx.render_queue` is flushed to the renderer given to `GameState::draw` right
+/// after `draw` returns, so what's queued there has to own what it draws, like an `Rc<Model>`.
+/// Make your own to draw borrowed things or to draw before something else like UI, and `flush` it yourself.
+/// Draws go through a `DynRenderer`, so `Renderer` methods a renderer overrides aren't used
+pub struct RenderQueue<'a> {
+    draws: Vec<QueuedDraw<'a>>,
+}
+
+impl Default for RenderQueue<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        Self { draws: Vec::new() }
+    }
+
+    /// Queues a `Renderer::draw`. `position` is where it is in the world, used to sort by distance
+    /// to the camera. `drawable` is a reference or something that owns it like an `Rc`
+    pub fn push<D, U>(
+        &mut self,
+        layer: RenderLayer,
+        position: Vec3<f32>,
+        drawable: impl Deref<Target = D> + 'a,
+        uniforms: U,
+    ) where
+        D: Drawable,
+        U: Uniforms + 'a,
+    {
+        self.draws.push(QueuedDraw {
+            layer,
+            position,
+            program: drawable.get_program(),
+            draw: Box::new(move |ctx: &mut Context, renderer: &mut DynRenderer| {
+                renderer.draw(ctx, &*drawable, &uniforms)
+            }),
+        });
+    }
+
+    /// Queues anything that draws with a single program, like `Renderer::draw_model` with "lit"
+    pub fn push_with(
+        &mut self,
+        layer: RenderLayer,
+        position: Vec3<f32>,
+        program: impl Into<String>,
+        draw: impl FnOnce(&mut Context, &mut DynRenderer) -> EngineResult + 'a,
+    ) {
+        self.draws.push(QueuedDraw {
+            layer,
+            position,
+            program: program.into(),
+            draw: Box::new(draw),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+    /// Throws away everything queued without drawing it
+    pub fn clear(&mut self) {
+        self.draws.clear();
+    }
+
+    /// Sorts and draws everything queued, distances are from where `ctx.camera` is now.
+    /// The queue is empty afterwards, even if a draw fails
+    pub fn flush(&mut self, ctx: &mut Context, renderer: &mut impl Renderer) -> EngineResult {
+        let camera = ctx.camera.position;
+        let mut draws: Vec<_> = self
+            .draws
+            .drain(..)
+            .map(|draw| (draw.position.distance_squared(camera), draw))
+            .collect();
+        // Stable, so `Overlay` and anything else that's equal stays in the order it was queued
+        draws.sort_by(|(a_distance, a), (b_distance, b)| {
+            a.layer.cmp(&b.layer).then_with(|| match a.layer {
+                RenderLayer::Opaque | RenderLayer::AlphaTest => a
+                    .program
+                    .cmp(&b.program)
+                    .then(a_distance.total_cmp(b_distance)),
+                RenderLayer::Transparent => b_distance
+                    .total_cmp(a_distance)
+                    .then_with(|| a.program.cmp(&b.program)),
+                RenderLayer::Overlay => Ordering::Equal,
+            })
+        });
+        let mut renderer = DynRenderer::new(renderer);
+        for (_, draw) in draws {
+            (draw.draw)(ctx, &mut renderer)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    context::Context,
+    error::{EngineError, EngineResult},
+    renderer::{Drawable, Renderer, capture},
+};
+use glium::{
+    Surface, Texture2d,
+    backend::Facade,
+    framebuffer::{DepthRenderBuffer, SimpleFrameBuffer},
+    texture::DepthFormat,
+    uniforms::Uniforms,
+};
+use image::RgbaImage;
+
+/// Color and depth buffers that can be drawn into instead of the window
+/// Create a TargetRenderer to draw into it
+pub struct RenderTarget {
+    color: Texture2d,
+    depth: DepthRenderBuffer,
+}
+
+impl RenderTarget {
+    pub fn new(facade: &impl Facade, width: u32, height: u32) -> EngineResult<Self> {
+        Ok(Self {
+            color: Texture2d::empty(facade, width, height)?,
+            depth: DepthRenderBuffer::new(facade, DepthFormat::I24, width, height)?,
+        })
+    }
+
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        self.color.dimensions()
+    }
+
+    /// Recreates the buffers at the new size, anything drawn into them is lost. Does nothing if the size is the same
+    pub fn resize(&mut self, facade: &impl Facade, width: u32, height: u32) -> EngineResult {
+        if self.get_dimensions() != (width, height) {
+            *self = Self::new(facade, width, height)?;
+        }
+        Ok(())
+    }
+
+    /// The color buffer, can be used as a texture once drawing is done
+    pub fn get_texture(&self) -> &Texture2d {
+        &self.color
+    }
+
+    /// Reads the color buffer back from the GPU, the top row of the image is the top of the screen
+    pub fn read_image(&self) -> EngineResult<RgbaImage> {
+        capture::texture_to_image(&self.color)
+    }
+}
+
+/// Offscreen buffers `Context::resize_with_window` can keep the size of the window
+pub trait Resizable {
+    /// Should do nothing if the size is the same
+    fn resize(&mut self, ctx: &Context, width: u32, height: u32) -> EngineResult;
+}
+
+impl Resizable for RenderTarget {
+    fn resize(&mut self, ctx: &Context, width: u32, height: u32) -> EngineResult {
+        RenderTarget::resize(self, &ctx.display, width, height)
+    }
+}
+
+pub struct TargetRenderer<'a> {
+    framebuffer: SimpleFrameBuffer<'a>,
+}
+
+impl<'a> TargetRenderer<'a> {
+    pub fn new(ctx: &Context, target: &'a RenderTarget) -> EngineResult<Self> {
+        Ok(Self {
+            framebuffer: SimpleFrameBuffer::with_depth_buffer(
+                &ctx.display,
+                &target.color,
+                &target.depth,
+            )?,
+        })
+    }
+}
+
+impl Renderer for TargetRenderer<'_> {
+    fn get_surface(&self) -> &impl Surface {
+        &self.framebuffer
+    }
+    fn get_surface_mut(&mut self) -> &mut impl Surface {
+        &mut self.framebuffer
+    }
+    fn draw<D, U>(&mut self, ctx: &mut Context, drawable: &D, uniforms: &U) -> EngineResult
+    where
+        D: Drawable,
+        U: Uniforms,
+    {
+        let program = if let Some(program) = ctx.get_program(drawable.get_program()) {
+            program
+        } else {
+            return Err(EngineError::Error(format!(
+                "Program {} not found in the context",
+                drawable.get_program()
+            )));
+        };
+
+        self.framebuffer.draw(
+            drawable.get_vbo(),
+            drawable.get_ibo(),
+            program,
+            uniforms,
+            &drawable.get_draw_params(),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    asset::model::Model,
+    context::{Context, camera::CameraState},
+    error::EngineResult,
+    renderer::{
+        drawable::Drawable,
+        lighting::{Light, LightHandle, LitModel},
+    },
+    shaders::{SHADOW_FS, SHADOW_VS},
+};
+use glium::{
+    Depth, DepthTest, DrawParameters, Program, Rect, Surface,
+    backend::Facade,
+    framebuffer::SimpleFrameBuffer,
+    texture::DepthTexture2d,
+    uniform,
+    uniforms::{
+        MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction,
+        UniformValue,
+    },
+};
+use vek::{FrustumPlanes, Mat4, Vec3};
+
+/// How many pieces the view is split into for a directional light's shadows, closer ones are sharper
+pub const CASCADES: usize = 3;
+/// Most spot lights with shadows at once
+pub const MAX_SPOT_SHADOWS: usize = 2;
+/// Maps in the shadow atlas, the shaders are compiled with the same number
+pub(crate) const MAX_SHADOW_TILES: usize = CASCADES + MAX_SPOT_SHADOWS;
+// Maps are laid out in a grid this many wide so the atlas doesn't get too wide for the GPU
+const ATLAS_COLUMNS: u32 = 3;
+// Closest a spot light's shadows start
+const SPOT_NEAR: f32 = 0.05;
+
+/// How shadows are drawn, kept in `ctx.lights.shadows`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// Width and height of each shadow map in pixels
+    pub resolution: u32,
+    /// How far from the camera each cascade of a directional light reaches, there are no shadows past the last one
+    pub cascade_distances: [f32; CASCADES],
+    /// How far past the view things can be and still cast shadows into it from a directional light
+    pub caster_distance: f32,
+    /// Stops surfaces from shadowing themselves, raise it if there are stripes on lit surfaces
+    pub bias: f32,
+    /// How blurry the edges are, 0 is hard edges, 1 averages 3x3 samples and 2 averages 5x5
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            cascade_distances: [8.0, 25.0, 80.0],
+            caster_distance: 50.0,
+            bias: 0.0005,
+            pcf_radius: 1,
+        }
+    }
+}
+
+/// One map in the atlas, a directional light has `CASCADES` of them in a row
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ShadowTile {
+    pub(crate) light: LightHandle,
+    pub(crate) matrix: Mat4<f32>,
+    // Size of a texel in world units, multiplied by the distance for spot lights
+    pub(crate) texel_scale: f32,
+}
+
+/// Depth from every light with shadows, drawn by `draw_shadows` and kept in the `Context`
+pub(crate) struct ShadowMap {
+    texture: DepthTexture2d,
+    resolution: u32,
+    pub(crate) tiles: Vec<ShadowTile>,
+}
+
+impl ShadowMap {
+    fn new(facade: &impl Facade, resolution: u32) -> EngineResult<Self> {
+        let rows = (MAX_SHADOW_TILES as u32).div_ceil(ATLAS_COLUMNS);
+        Ok(Self {
+            texture: DepthTexture2d::empty(facade, resolution * ATLAS_COLUMNS, resolution * rows)?,
+            resolution,
+            tiles: Vec::new(),
+        })
+    }
+
+    /// Nearest sampling, the shader does its own filtering
+    pub(crate) fn uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::DepthTexture2d(
+            &self.texture,
+            Some(SamplerBehavior {
+                magnify_filter: MagnifySamplerFilter::Nearest,
+                minify_filter: MinifySamplerFilter::Nearest,
+                wrap_function: (
+                    SamplerWrapFunction::Clamp,
+                    SamplerWrapFunction::Clamp,
+                    SamplerWrapFunction::Clamp,
+                ),
+                ..Default::default()
+            }),
+        )
+    }
+
+    /// Columns and rows of maps in the atlas
+    pub(crate) fn atlas_size(&self) -> [f32; 2] {
+        let (width, height) = self.texture.dimensions();
+        [
+            (width / self.resolution) as f32,
+            (height / self.resolution) as f32,
+        ]
+    }
+
+    /// First tile of a light, `None` if it doesn't have shadows this frame
+    pub(crate) fn first_tile(&self, light: LightHandle) -> Option<usize> {
+        self.tiles.iter().position(|tile| tile.light == light)
+    }
+
+    fn render(
+        &self,
+        ctx: &Context,
+        draw_casters: &mut impl FnMut(&mut ShadowCaster<'_, '_>) -> EngineResult,
+    ) -> EngineResult {
+        let program = ctx.get_program("shadow").unwrap();
+        let mut framebuffer = SimpleFrameBuffer::depth_only(&ctx.display, &self.texture)?;
+        framebuffer.clear_depth(1.0);
+        for (index, tile) in self.tiles.iter().enumerate() {
+            draw_casters(&mut ShadowCaster {
+                framebuffer: &mut framebuffer,
+                program,
+                light_matrix: tile.matrix,
+                viewport: self.tile_rect(index),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn tile_rect(&self, index: usize) -> Rect {
+        Rect {
+            left: index as u32 % ATLAS_COLUMNS * self.resolution,
+            bottom: index as u32 / ATLAS_COLUMNS * self.resolution,
+            width: self.resolution,
+            height: self.resolution,
+        }
+    }
+}
+
+/// Draws depth into one of the shadow maps, given to the closure passed to `draw_shadows`
+pub struct ShadowCaster<'a, 'b> {
+    framebuffer: &'a mut SimpleFrameBuffer<'b>,
+    program: &'a Program,
+    light_matrix: Mat4<f32>,
+    viewport: Rect,
+}
+
+impl ShadowCaster<'_, '_> {
+    pub fn draw_model(&mut self, model: &Model, transform: Mat4<f32>) -> EngineResult {
+        self.draw(
+            &LitModel {
+                model,
+                program: "lit",
+                draw_params: DrawParameters::default(),
+            },
+            transform,
+        )
+    }
+
+    /// Only the `position` attribute is used, the drawable's program and draw parameters are ignored
+    pub fn draw(&mut self, drawable: &impl Drawable, transform: Mat4<f32>) -> EngineResult {
+        self.framebuffer.draw(
+            drawable.get_vbo(),
+            drawable.get_ibo(),
+            self.program,
+            &uniform! {
+                light_matrix: self.light_matrix.into_col_arrays(),
+                model: transform.into_col_arrays(),
+            },
+            &DrawParameters {
+                depth: Depth {
+                    test: DepthTest::IfLess,
+                    write: true,
+                    ..Default::default()
+                },
+                viewport: Some(self.viewport),
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// Draws the shadow maps for the lights in `ctx.lights` that cast shadows, call it every frame before
+/// drawing anything lit. `draw_casters` is called once for each map and should draw everything that casts
+/// shadows. `World::draw` already does this for its `MeshRenderer`s
+pub fn draw_shadows(
+    ctx: &mut Context,
+    mut draw_casters: impl FnMut(&mut ShadowCaster<'_, '_>) -> EngineResult,
+) -> EngineResult {
+    if ctx.get_program("shadow").is_none() {
+        ctx.new_program("shadow", SHADOW_VS, SHADOW_FS, None)?;
+    }
+    let resolution = ctx.lights.shadows.resolution;
+    let mut shadow_map = match ctx.shadow_map.take() {
+        Some(shadow_map) if shadow_map.resolution == resolution => shadow_map,
+        _ => ShadowMap::new(&ctx.display, resolution)?,
+    };
+    shadow_map.tiles = place_tiles(ctx);
+
+    let result = shadow_map.render(ctx, &mut draw_casters);
+    ctx.shadow_map = Some(shadow_map);
+    result
+}
+
+/// The first directional light with shadows gets the cascades, then spot lights get the rest
+fn place_tiles(ctx: &Context) -> Vec<ShadowTile> {
+    let lights = &ctx.lights;
+    let settings = &lights.shadows;
+    let mut tiles = Vec::new();
+
+    let sun = lights.iter().find_map(|(handle, light)| match light {
+        Light::Directional { direction, .. } if lights.casts_shadows(handle) => {
+            Some((handle, *direction))
+        }
+        _ => None,
+    });
+    if let Some((handle, direction)) = sun {
+        let mut near = ctx.camera.near;
+        for distance in settings.cascade_distances {
+            let far = distance.min(ctx.camera.far);
+            let (matrix, texel_scale) = cascade_matrix(&ctx.camera, direction, near, far, settings);
+            tiles.push(ShadowTile {
+                light: handle,
+                matrix,
+                texel_scale,
+            });
+            near = far;
+        }
+    }
+
+    let spots = lights.iter().filter_map(|(handle, light)| match light {
+        Light::Spot {
+            position,
+            direction,
+            range,
+            outer_angle,
+            ..
+        } if lights.casts_shadows(handle) => {
+            Some((handle, *position, *direction, *range, *outer_angle))
+        }
+        _ => None,
+    });
+    for (handle, position, direction, range, outer_angle) in spots.take(MAX_SPOT_SHADOWS) {
+        let view = Mat4::look_at_rh(position, position + direction, up_for(direction));
+        let persp = Mat4::perspective_rh_no(
+            outer_angle * 2.0,
+            1.0,
+            SPOT_NEAR,
+            range.max(SPOT_NEAR * 2.0),
+        );
+        tiles.push(ShadowTile {
+            light: handle,
+            matrix: persp * view,
+            texel_scale: 2.0 * outer_angle.tan() / settings.resolution as f32,
+        });
+    }
+    tiles
+}
+
+/// Orthographic box around a slice of the camera's view. The box is sized by a sphere around the slice
+/// and moved in whole texels so the shadows don't shimmer as the camera moves
+fn cascade_matrix(
+    camera: &CameraState,
+    direction: Vec3<f32>,
+    near: f32,
+    far: f32,
+    settings: &ShadowSettings,
+) -> (Mat4<f32>, f32) {
+    let (forward, left, up) = camera.get_directions();
+    let center = camera.position + forward * (near + far) / 2.0;
+    let tan = (camera.fov / 2.0).tan();
+    let mut radius: f32 = 0.0;
+    for distance in [near, far] {
+        let half_height = distance * tan;
+        let half_width = half_height * camera.aspect_ratio;
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            let corner =
+                camera.position + forward * distance + left * half_width * x + up * half_height * y;
+            radius = radius.max(corner.distance(center));
+        }
+    }
+    // Rounded up so the texel size only changes when the camera's settings do
+    let radius = radius.ceil();
+
+    let direction = direction.normalized();
+    let view = Mat4::<f32>::look_at_rh(Vec3::zero(), direction, up_for(direction));
+    let mut light_center = (view * center.with_w(1.0)).xyz();
+    let texel = radius * 2.0 / settings.resolution as f32;
+    light_center.x = (light_center.x / texel).floor() * texel;
+    light_center.y = (light_center.y / texel).floor() * texel;
+
+    let ortho = Mat4::orthographic_rh_no(FrustumPlanes {
+        left: light_center.x - radius,
+        right: light_center.x + radius,
+        bottom: light_center.y - radius,
+        top: light_center.y + radius,
+        near: -light_center.z - radius - settings.caster_distance,
+        far: -light_center.z + radius,
+    });
+    (ortho * view, texel)
+}
+
+/// Any up that isn't the same as `direction`
+fn up_for(direction: Vec3<f32>) -> Vec3<f32> {
+    if direction.normalized().y.abs() > 0.99 {
+        Vec3::unit_z()
+    } else {
+        Vec3::unit_y()
+    }
+}
@@ -7,21 +7,26 @@
 use crate::{
     context::Context,
     error::EngineResult,
-    renderer::{Drawable, texture::Texture},
-    shaders::{SKYBOX_FS, SKYBOX_VS},
+    renderer::{Drawable, environment::CubeFaceRenderer, texture::Texture},
+    shaders::{
+        ENVIRONMENT_VS, EQUIRECTANGULAR_FS, PROCEDURAL_SKY_FS, SKYBOX_FS, SKYBOX_HDR_FS, SKYBOX_VS,
+    },
 };
 use glium::{
-    BackfaceCullingMode, BlitTarget, Depth, DepthTest, DrawParameters, IndexBuffer, Surface,
-    VertexBuffer,
+    BackfaceCullingMode, BlitTarget, Depth, DepthTest, DrawParameters, IndexBuffer, Program,
+    Surface, Texture2d, VertexBuffer,
     framebuffer::SimpleFrameBuffer,
     implement_vertex,
     index::{IndicesSource, PrimitiveType},
     program,
-    texture::{self, Cubemap},
-    uniforms::{MagnifySamplerFilter, Sampler},
+    texture::{self, CubeLayer, Cubemap, MipmapsOption, RawImage2d, UncompressedFloatFormat},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction},
     vertex::MultiVerticesSource,
 };
+use image::DynamicImage;
 use std::path::Path;
+use vek::{Rgb, Vec3};
 
 #[derive(Clone, Copy)]
 struct Vertex {
@@ -29,16 +34,46 @@ struct Vertex {
 }
 implement_vertex!(Vertex, position);
 
+/// A sky made in a shader by `Skybox::procedural`, colors are in linear light like `PbrMaterial`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProceduralSky {
+    /// Blends from `horizon` up to `zenith`, and down to `ground` just under the horizon
+    Gradient {
+        zenith: Rgb<f32>,
+        horizon: Rgb<f32>,
+        ground: Rgb<f32>,
+    },
+    /// Sunlight scattering through the air, blue in the day and orange when the sun is low
+    Atmosphere {
+        /// Where the sunlight is pointing, the same as the sun's `Light::Directional`
+        sun_direction: Vec3<f32>,
+        sun_intensity: f32,
+    },
+}
+
+impl ProceduralSky {
+    pub fn gradient(zenith: Rgb<f32>, horizon: Rgb<f32>, ground: Rgb<f32>) -> Self {
+        Self::Gradient {
+            zenith,
+            horizon,
+            ground,
+        }
+    }
+    pub fn atmosphere(sun_direction: Vec3<f32>) -> Self {
+        Self::Atmosphere {
+            sun_direction,
+            sun_intensity: 22.0,
+        }
+    }
+}
+
 /// Skybox program must be sent to the Context before rendering
 pub struct Skybox {
     cubemap: texture::Cubemap,
     resolution: u32,
-    right: Texture,
-    left: Texture,
-    top: Texture,
-    bottom: Texture,
-    front: Texture,
-    back: Texture,
+    /// [right, left, top, bottom, front, back] for skyboxes made with `new`, blitted in by `prepare_draw`.
+    /// The others are drawn into a floating point cubemap once when they're made
+    faces: Option<[Texture; 6]>,
     vbo: VertexBuffer<Vertex>,
     ibo: IndexBuffer<u16>,
 }
@@ -56,17 +91,119 @@ impl Skybox {
         // bottom: impl AsRef<Path>,
         // front: impl AsRef<Path>,
         // back: impl AsRef<Path>,
+    ) -> EngineResult<Self> {
+        let faces = [
+            Texture::new(ctx, paths[0].clone())?,
+            Texture::new(ctx, paths[1].clone())?,
+            Texture::new(ctx, paths[2].clone())?,
+            Texture::new(ctx, paths[3].clone())?,
+            Texture::new(ctx, paths[4].clone())?,
+            Texture::new(ctx, paths[5].clone())?,
+        ];
+        Self::with_cubemap(
+            ctx,
+            texture::Cubemap::empty(&ctx.display, resolution)?,
+            resolution,
+            Some(faces),
+        )
+    }
+
+    /// Loads a panorama that wraps all the way around, like most .hdr skies. `resolution` is the size
+    /// of each cubemap face, about a quarter of the image's width keeps all the detail.
+    /// .hdr and .exr files keep their full brightness, other images are treated as sRGB
+    pub fn from_equirectangular(
+        ctx: &Context,
+        resolution: u32,
+        path: impl AsRef<Path>,
+    ) -> EngineResult<Self> {
+        let image = image::open(path)?;
+        let srgb = !matches!(
+            image,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        );
+        let image = image.into_rgb32f();
+        let dimensions = image.dimensions();
+        let panorama = Texture2d::with_format(
+            &ctx.display,
+            RawImage2d::from_raw_rgb(image.into_raw(), dimensions),
+            UncompressedFloatFormat::F32F32F32,
+            MipmapsOption::NoMipmap,
+        )?;
+
+        let program = Program::from_source(&ctx.display, ENVIRONMENT_VS, EQUIRECTANGULAR_FS, None)?;
+        let cubemap = Self::hdr_cubemap(ctx, resolution)?;
+        CubeFaceRenderer::new(ctx)?.draw(
+            ctx,
+            &cubemap,
+            0,
+            &program,
+            &uniform! {
+                panorama: panorama.sampled()
+                    .magnify_filter(MagnifySamplerFilter::Linear)
+                    .minify_filter(MinifySamplerFilter::Linear)
+                    .wrap_function(SamplerWrapFunction::Repeat),
+                srgb: srgb,
+            },
+        )?;
+        Self::with_cubemap(ctx, cubemap, resolution, None)
+    }
+
+    /// A sky made in a shader for scenes with no sky art yet
+    pub fn procedural(ctx: &Context, resolution: u32, sky: ProceduralSky) -> EngineResult<Self> {
+        let program = Program::from_source(&ctx.display, ENVIRONMENT_VS, PROCEDURAL_SKY_FS, None)?;
+        let cubemap = Self::hdr_cubemap(ctx, resolution)?;
+        let (zenith, horizon, ground) = match sky {
+            ProceduralSky::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => (zenith, horizon, ground),
+            ProceduralSky::Atmosphere { .. } => (Rgb::zero(), Rgb::zero(), Rgb::zero()),
+        };
+        let (to_sun, sun_intensity) = match sky {
+            ProceduralSky::Atmosphere {
+                sun_direction,
+                sun_intensity,
+            } => (-sun_direction.normalized(), sun_intensity),
+            ProceduralSky::Gradient { .. } => (Vec3::unit_y(), 0.0),
+        };
+        CubeFaceRenderer::new(ctx)?.draw(
+            ctx,
+            &cubemap,
+            0,
+            &program,
+            &uniform! {
+                atmosphere: matches!(sky, ProceduralSky::Atmosphere { .. }),
+                zenith: zenith.into_array(),
+                horizon: horizon.into_array(),
+                ground: ground.into_array(),
+                to_sun: to_sun.into_array(),
+                sun_intensity: sun_intensity,
+            },
+        )?;
+        Self::with_cubemap(ctx, cubemap, resolution, None)
+    }
+
+    fn hdr_cubemap(ctx: &Context, resolution: u32) -> EngineResult<Cubemap> {
+        Ok(Cubemap::empty_with_format(
+            &ctx.display,
+            UncompressedFloatFormat::F16F16F16,
+            MipmapsOption::NoMipmap,
+            resolution,
+        )?)
+    }
+
+    fn with_cubemap(
+        ctx: &Context,
+        cubemap: Cubemap,
+        resolution: u32,
+        faces: Option<[Texture; 6]>,
     ) -> EngineResult<Self> {
         let scale = 25.0;
         Ok(Self {
-            cubemap: texture::Cubemap::empty(&ctx.display, resolution)?,
+            cubemap,
             resolution,
-            right: Texture::new(ctx, paths[0].clone())?,
-            left: Texture::new(ctx, paths[1].clone())?,
-            top: Texture::new(ctx, paths[2].clone())?,
-            bottom: Texture::new(ctx, paths[3].clone())?,
-            front: Texture::new(ctx, paths[4].clone())?,
-            back: Texture::new(ctx, paths[5].clone())?,
+            faces,
             vbo: VertexBuffer::new(
                 &ctx.display,
                 &[
@@ -168,7 +305,7 @@ impl Skybox {
         })
     }
 
-    /// Sends a program called 'skybox' to the Context
+    /// Sends programs called 'skybox' and 'skybox_hdr' to the Context
     pub fn send_program(ctx: &mut Context) -> EngineResult {
         ctx.add_program(
             "skybox",
@@ -177,94 +314,59 @@ impl Skybox {
                 fragment: SKYBOX_FS,
             })?,
         );
+        ctx.new_srgb_program("skybox_hdr", SKYBOX_VS, SKYBOX_HDR_FS)?;
         Ok(())
     }
 
+    /// The cubemap is in linear floating point color, true for everything not made with `new`.
+    /// These are drawn with the 'skybox_hdr' program, which tone maps them
+    pub fn is_hdr(&self) -> bool {
+        self.faces.is_none()
+    }
+
     pub fn get_cubemap(&self) -> Sampler<'_, Cubemap> {
         self.cubemap
             .sampled()
             .magnify_filter(MagnifySamplerFilter::Linear)
     }
 
+    pub(crate) fn get_raw_cubemap(&self) -> &Cubemap {
+        &self.cubemap
+    }
+
+    /// Copies the six images into the cubemap, does nothing for skyboxes not made with `new`
     pub fn prepare_draw(
         &self,
         ctx: &mut Context,
         magnify_filter: MagnifySamplerFilter,
     ) -> EngineResult {
+        let Some(faces) = &self.faces else {
+            return Ok(());
+        };
         let blit_target = BlitTarget {
             left: 0,
             bottom: 0,
             width: self.resolution as i32,
             height: self.resolution as i32,
         };
-
-        let framebuffer1 = SimpleFrameBuffer::new(
-            &ctx.display,
-            self.cubemap
-                .main_level()
-                .image(glium::texture::CubeLayer::PositiveX),
-        )?;
-        let framebuffer2 = SimpleFrameBuffer::new(
-            &ctx.display,
-            self.cubemap
-                .main_level()
-                .image(glium::texture::CubeLayer::NegativeX),
-        )?;
-        let framebuffer3 = SimpleFrameBuffer::new(
-            &ctx.display,
-            self.cubemap
-                .main_level()
-                .image(glium::texture::CubeLayer::PositiveY),
-        )?;
-        let framebuffer4 = SimpleFrameBuffer::new(
-            &ctx.display,
-            self.cubemap
-                .main_level()
-                .image(glium::texture::CubeLayer::NegativeY),
-        )?;
-        let framebuffer5 = SimpleFrameBuffer::new(
-            &ctx.display,
-            self.cubemap
-                .main_level()
-                .image(glium::texture::CubeLayer::PositiveZ),
-        )?;
-        let framebuffer6 = SimpleFrameBuffer::new(
-            &ctx.display,
-            self.cubemap
-                .main_level()
-                .image(glium::texture::CubeLayer::NegativeZ),
-        )?;
-
-        self.right.get_texture().as_surface().blit_whole_color_to(
-            &framebuffer1,
-            &blit_target,
-            magnify_filter,
-        );
-        self.left.get_texture().as_surface().blit_whole_color_to(
-            &framebuffer2,
-            &blit_target,
-            magnify_filter,
-        );
-        self.bottom.get_texture().as_surface().blit_whole_color_to(
-            &framebuffer3,
-            &blit_target,
-            magnify_filter,
-        );
-        self.top.get_texture().as_surface().blit_whole_color_to(
-            &framebuffer4,
-            &blit_target,
-            magnify_filter,
-        );
-        self.front.get_texture().as_surface().blit_whole_color_to(
-            &framebuffer5,
-            &blit_target,
-            magnify_filter,
-        );
-        self.back.get_texture().as_surface().blit_whole_color_to(
-            &framebuffer6,
-            &blit_target,
-            magnify_filter,
-        );
+        // The cubemap is looked up upside down so the top and bottom trade places
+        let layers = [
+            CubeLayer::PositiveX,
+            CubeLayer::NegativeX,
+            CubeLayer::NegativeY,
+            CubeLayer::PositiveY,
+            CubeLayer::PositiveZ,
+            CubeLayer::NegativeZ,
+        ];
+        for (face, layer) in faces.iter().zip(layers) {
+            let framebuffer =
+                SimpleFrameBuffer::new(&ctx.display, self.cubemap.main_level().image(layer))?;
+            face.get_texture().as_surface().blit_whole_color_to(
+                &framebuffer,
+                &blit_target,
+                magnify_filter,
+            );
+        }
         Ok(())
     }
 }
@@ -277,7 +379,11 @@ impl Drawable for Skybox {
         &self.ibo
     }
     fn get_program(&self) -> String {
-        "skybox".to_string()
+        if self.is_hdr() {
+            "skybox_hdr".to_string()
+        } else {
+            "skybox".to_string()
+        }
     }
     fn get_draw_params(&self) -> DrawParameters {
         DrawParameters {
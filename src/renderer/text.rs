@@ -23,6 +23,8 @@ pub struct Text {
     ibo: IndexBuffer<u16>,
     text: String,
     font_name: String,
+    scale: f32,
+    wrapping: Option<(f32, f32)>,
 }
 
 impl Text {
@@ -125,9 +127,24 @@ impl Text {
             ibo,
             text,
             font_name: name,
+            scale,
+            wrapping,
         })
     }
 
+    /// Lays the text out again, needed after the font's scale factor changes
+    pub fn rebuild(&mut self, ctx: &Context) -> EngineResult {
+        *self = Self::new(
+            ctx,
+            self.pos,
+            self.scale,
+            self.wrapping,
+            self.text.clone(),
+            self.font_name.clone(),
+        )?;
+        Ok(())
+    }
+
     pub fn draw(&self, ctx: &Context, renderer: &mut impl Renderer) -> EngineResult {
         let program = ctx.get_program("text").unwrap();
         let surface = renderer.get_surface_mut();
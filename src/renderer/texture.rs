@@ -1,7 +1,7 @@
 use crate::{context::Context, error::EngineResult};
 use glium::{
-    Display, Texture2d,
-    glutin::surface::WindowSurface,
+    Texture2d,
+    backend::Facade,
     texture::RawImage2d,
     uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler},
 };
@@ -25,7 +25,7 @@ impl Texture {
     }
 
     pub fn from_rgb_bytes(
-        display: &Display<WindowSurface>,
+        display: &impl Facade,
         bytes: &[u8],
         dimensions: (u32, u32),
     ) -> EngineResult<Self> {
@@ -35,7 +35,7 @@ impl Texture {
         })
     }
     pub fn from_rgba_bytes(
-        display: &Display<WindowSurface>,
+        display: &impl Facade,
         bytes: &[u8],
         dimensions: (u32, u32),
     ) -> EngineResult<Self> {
@@ -1,3 +1,16 @@
+/// Replaces `#include <name>` lines with the engine's GLSL snippets, `Context::new_program` does this for you.
+/// There's only `#include <shadows>` for now, see `SHADOWS_GLSL`
+pub fn resolve_includes(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| match line.trim() {
+            "#include <shadows>" => SHADOWS_GLSL,
+            _ => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub const TEXT_VS: &str = r"
 #version 140
 
@@ -151,3 +164,1193 @@ void main() {
     color = texture(u_skybox, vec3(v_tex_coords.x, -v_tex_coords.y, v_tex_coords.z));
 }
 ";
+
+/// For skyboxes in linear floating point color, tone mapped the same as the "pbr" program
+pub const SKYBOX_HDR_FS: &str = r"
+#version 140
+
+in vec3 v_tex_coords;
+
+uniform samplerCube u_skybox;
+
+out vec4 color;
+
+void main() {
+    vec3 sky = texture(u_skybox, vec3(v_tex_coords.x, -v_tex_coords.y, v_tex_coords.z)).rgb;
+    sky = sky / (sky + 1.0);
+    color = vec4(pow(sky, vec3(1.0 / 2.2)), 1.0);
+}
+";
+
+pub const FADE_VS: &str = r"
+#version 140
+
+in vec2 position;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+pub const FADE_FS: &str = r"
+#version 140
+
+uniform vec4 fade_color;
+
+out vec4 color;
+
+void main() {
+    color = fade_color;
+}
+";
+
+pub const VIEWPORT_VS: &str = r"
+#version 140
+
+in vec2 position;
+
+out vec2 v_tex_coords;
+
+void main() {
+    v_tex_coords = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+pub const VIEWPORT_FS: &str = r"
+#version 140
+
+in vec2 v_tex_coords;
+
+out vec4 color;
+
+uniform sampler2D tex;
+
+void main() {
+    color = texture(tex, v_tex_coords);
+}
+";
+
+pub const SPRITE_VS: &str = r"
+#version 140
+
+in vec2 position;
+in vec2 tex_coords;
+in vec4 color;
+
+out vec2 v_tex_coords;
+out vec4 v_color;
+
+void main() {
+    v_tex_coords = tex_coords;
+    v_color = color;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+pub const SPRITE_FS: &str = r"
+#version 140
+
+in vec2 v_tex_coords;
+in vec4 v_color;
+
+out vec4 color;
+
+uniform sampler2D tex;
+
+void main() {
+    color = texture(tex, v_tex_coords) * v_color;
+}
+";
+
+pub const MESH_VS: &str = r"
+#version 140
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+
+out vec3 v_normal;
+out vec2 v_tex_coords;
+
+uniform mat4 persp;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    v_normal = mat3(model) * normal;
+    v_tex_coords = tex_coords;
+    gl_Position = persp * view * model * vec4(position, 1.0);
+}
+";
+
+pub const MESH_FS: &str = r"
+#version 140
+
+in vec3 v_normal;
+in vec2 v_tex_coords;
+
+out vec4 out_color;
+
+uniform vec4 color;
+uniform sampler2D tex;
+
+// Not real lighting, just enough shading to see the shape
+const vec3 shade_direction = vec3(0.3, 1.0, 0.5);
+
+void main() {
+    float shade = 0.6 + 0.4 * max(dot(normalize(v_normal), normalize(shade_direction)), 0.0);
+    vec4 base = texture(tex, v_tex_coords) * color;
+    out_color = vec4(base.rgb * shade, base.a);
+}
+";
+
+/// `MESH_VS` with the transform and color coming from each instance of an `InstancedModel`
+pub const INSTANCED_VS: &str = r"
+#version 140
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in mat4 transform;
+in vec4 color;
+
+out vec3 v_normal;
+out vec2 v_tex_coords;
+out vec4 v_color;
+
+uniform mat4 persp;
+uniform mat4 view;
+
+void main() {
+    v_normal = mat3(transform) * normal;
+    v_tex_coords = tex_coords;
+    v_color = color;
+    gl_Position = persp * view * transform * vec4(position, 1.0);
+}
+";
+pub const INSTANCED_FS: &str = r"
+#version 140
+
+in vec3 v_normal;
+in vec2 v_tex_coords;
+in vec4 v_color;
+
+out vec4 out_color;
+
+uniform bool has_texture;
+uniform sampler2D tex;
+
+const vec3 shade_direction = vec3(0.3, 1.0, 0.5);
+
+void main() {
+    float shade = 0.6 + 0.4 * max(dot(normalize(v_normal), normalize(shade_direction)), 0.0);
+    vec4 base = v_color;
+    if (has_texture) {
+        base *= texture(tex, v_tex_coords);
+    }
+    out_color = vec4(base.rgb * shade, base.a);
+}
+";
+
+pub const LIT_VS: &str = r"
+#version 140
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+
+out vec3 v_position;
+out vec3 v_normal;
+out vec2 v_tex_coords;
+
+uniform mat4 persp;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    vec4 world_position = model * vec4(position, 1.0);
+    v_position = world_position.xyz;
+    // Inverse transpose so normals stay right with non uniform scale
+    v_normal = transpose(inverse(mat3(model))) * normal;
+    v_tex_coords = tex_coords;
+    gl_Position = persp * view * world_position;
+}
+";
+
+pub const LIT_FS: &str = r"
+#version 140
+
+#define MAX_LIGHTS 8
+
+struct Light {
+    // w is 0 for directional lights
+    vec4 position;
+    vec3 direction;
+    vec3 color;
+    float range;
+    float inner_cone;
+    float outer_cone;
+    int shadow_tile;
+};
+
+#include <shadows>
+
+in vec3 v_position;
+in vec3 v_normal;
+in vec2 v_tex_coords;
+
+out vec4 out_color;
+
+uniform Light lights[MAX_LIGHTS];
+uniform int light_count;
+uniform vec3 ambient;
+
+uniform vec4 color;
+uniform vec3 specular;
+uniform float shininess;
+uniform vec3 emissive;
+uniform bool blinn;
+uniform bool double_sided;
+uniform bool has_texture;
+uniform sampler2D tex;
+uniform bool has_specular_texture;
+uniform sampler2D specular_tex;
+
+void main() {
+    vec4 base = color;
+    if (has_texture) {
+        base *= texture(tex, v_tex_coords);
+    }
+    vec3 specular_color = specular;
+    if (has_specular_texture) {
+        specular_color *= texture(specular_tex, v_tex_coords).r;
+    }
+
+    vec3 normal = normalize(v_normal);
+    if (double_sided && !gl_FrontFacing) {
+        normal = -normal;
+    }
+    vec3 to_camera = normalize(camera_position - v_position);
+
+    vec3 lit = ambient * base.rgb + emissive;
+    for (int i = 0; i < light_count; i++) {
+        Light light = lights[i];
+        vec3 to_light;
+        float strength = 1.0;
+        if (light.position.w == 0.0) {
+            to_light = -light.direction;
+        } else {
+            vec3 offset = light.position.xyz - v_position;
+            float distance = length(offset);
+            to_light = offset / distance;
+            // Falls off with the square of the distance and reaches 0 at the range
+            float fade = clamp(1.0 - pow(distance / light.range, 4.0), 0.0, 1.0);
+            strength = fade * fade / (distance * distance + 1.0);
+            float cone = dot(-to_light, light.direction);
+            strength *= smoothstep(light.outer_cone, max(light.inner_cone, light.outer_cone + 0.0001), cone);
+        }
+
+        float diffuse = max(dot(normal, to_light), 0.0);
+        if (diffuse > 0.0) {
+            strength *= shadow(light.shadow_tile, light.position.w == 0.0, v_position, normal, to_light);
+        }
+        float highlight = 0.0;
+        if (diffuse > 0.0) {
+            if (blinn) {
+                vec3 half_direction = normalize(to_light + to_camera);
+                highlight = pow(max(dot(normal, half_direction), 0.0), shininess);
+            } else {
+                vec3 reflected = reflect(-to_light, normal);
+                highlight = pow(max(dot(reflected, to_camera), 0.0), shininess);
+            }
+        }
+        lit += light.color * strength * (diffuse * base.rgb + highlight * specular_color);
+    }
+    out_color = vec4(lit, base.a);
+}
+";
+
+/// Declares the shadow uniforms the engine sets for lit draws and `camera_position`, use it with
+/// `#include <shadows>`. `shadow` gives 0.0 in full shadow up to 1.0 fully lit, pass the light's
+/// `shadow_tile` and whether it's a directional light so it picks a cascade
+pub const SHADOWS_GLSL: &str = r"
+#define MAX_SHADOW_TILES 5
+
+uniform vec3 camera_position;
+uniform vec3 camera_forward;
+uniform bool has_shadows;
+uniform sampler2D shadow_map;
+// Columns and rows of maps in the atlas
+uniform vec2 shadow_atlas;
+uniform mat4 shadow_matrices[MAX_SHADOW_TILES];
+// Size of a texel in world units, for spot lights it's at a distance of 1
+uniform float shadow_texel_scales[MAX_SHADOW_TILES];
+uniform vec4 cascade_splits;
+uniform int cascade_count;
+uniform float shadow_bias;
+uniform int shadow_pcf_radius;
+
+float shadow(int tile, bool cascaded, vec3 world_position, vec3 normal, vec3 to_light) {
+    if (!has_shadows || tile < 0) {
+        return 1.0;
+    }
+    if (cascaded) {
+        float depth = dot(world_position - camera_position, camera_forward);
+        int cascade = 0;
+        while (cascade < cascade_count - 1 && depth > cascade_splits[cascade]) {
+            cascade++;
+        }
+        if (depth > cascade_splits[cascade]) {
+            return 1.0;
+        }
+        tile += cascade;
+    }
+
+    // Moved off the surface by about a texel, more when the light hits at an angle, to stop acne
+    float w = (shadow_matrices[tile] * vec4(world_position, 1.0)).w;
+    float slope = 1.0 - max(dot(normal, to_light), 0.0);
+    vec3 offset = normal * shadow_texel_scales[tile] * w * (1.0 + 2.0 * slope);
+    vec4 light_space = shadow_matrices[tile] * vec4(world_position + offset, 1.0);
+    vec3 coords = light_space.xyz / light_space.w * 0.5 + 0.5;
+    if (coords.z > 1.0 || any(lessThan(coords.xy, vec2(0.0))) || any(greaterThan(coords.xy, vec2(1.0)))) {
+        return 1.0;
+    }
+
+    vec2 tile_size = 1.0 / shadow_atlas;
+    vec2 tile_origin = vec2(mod(float(tile), shadow_atlas.x), floor(float(tile) / shadow_atlas.x)) * tile_size;
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    // Kept inside the tile so the filter doesn't read the maps next to it
+    vec2 low = tile_origin + texel * 0.5;
+    vec2 high = tile_origin + tile_size - texel * 0.5;
+    vec2 center = tile_origin + coords.xy * tile_size;
+
+    float lit = 0.0;
+    float samples = 0.0;
+    for (int x = -shadow_pcf_radius; x <= shadow_pcf_radius; x++) {
+        for (int y = -shadow_pcf_radius; y <= shadow_pcf_radius; y++) {
+            vec2 uv = clamp(center + vec2(x, y) * texel, low, high);
+            float closest = texture(shadow_map, uv).r;
+            lit += coords.z - shadow_bias > closest ? 0.0 : 1.0;
+            samples += 1.0;
+        }
+    }
+    return lit / samples;
+}
+";
+
+pub const SHADOW_VS: &str = r"
+#version 140
+
+in vec3 position;
+
+uniform mat4 light_matrix;
+uniform mat4 model;
+
+void main() {
+    gl_Position = light_matrix * model * vec4(position, 1.0);
+}
+";
+
+pub const SHADOW_FS: &str = r"
+#version 140
+
+// Only the depth is written
+void main() {}
+";
+
+pub const PBR_FS: &str = r"
+#version 140
+
+#define MAX_LIGHTS 8
+#define PI 3.14159265359
+
+struct Light {
+    // w is 0 for directional lights
+    vec4 position;
+    vec3 direction;
+    vec3 color;
+    float range;
+    float inner_cone;
+    float outer_cone;
+    int shadow_tile;
+};
+
+#include <shadows>
+
+in vec3 v_position;
+in vec3 v_normal;
+in vec2 v_tex_coords;
+
+out vec4 out_color;
+
+uniform Light lights[MAX_LIGHTS];
+uniform int light_count;
+uniform vec3 ambient;
+
+uniform vec4 base_color;
+uniform bool has_base_color_texture;
+uniform sampler2D base_color_texture;
+uniform float metallic;
+uniform float roughness;
+uniform bool has_metallic_roughness_texture;
+uniform sampler2D metallic_roughness_texture;
+uniform bool has_normal_texture;
+uniform sampler2D normal_texture;
+uniform float normal_scale;
+uniform bool has_occlusion_texture;
+uniform sampler2D occlusion_texture;
+uniform float occlusion_strength;
+uniform vec3 emissive;
+uniform bool has_emissive_texture;
+uniform sampler2D emissive_texture;
+uniform bool double_sided;
+
+uniform bool has_environment;
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform float prefiltered_levels;
+uniform sampler2D brdf_lut;
+uniform float environment_intensity;
+
+vec3 to_linear(vec3 color) {
+    return pow(color, vec3(2.2));
+}
+
+// Tangent space from the screen space derivatives, so models don't need tangents
+vec3 perturb_normal(vec3 normal) {
+    vec3 dp1 = dFdx(v_position);
+    vec3 dp2 = dFdy(v_position);
+    vec2 duv1 = dFdx(v_tex_coords);
+    vec2 duv2 = dFdy(v_tex_coords);
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    float scale = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    mat3 tbn = mat3(tangent * scale, bitangent * scale, normal);
+    vec3 sampled = texture(normal_texture, v_tex_coords).xyz * 2.0 - 1.0;
+    sampled.xy *= normal_scale;
+    return normalize(tbn * sampled);
+}
+
+float distribution_ggx(float n_dot_h, float rough) {
+    float a = rough * rough;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float rough) {
+    float k = (rough + 1.0) * (rough + 1.0) / 8.0;
+    float gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return gv * gl;
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float rough) {
+    return f0 + (max(vec3(1.0 - rough), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    vec4 base = base_color;
+    if (has_base_color_texture) {
+        vec4 sampled = texture(base_color_texture, v_tex_coords);
+        base *= vec4(to_linear(sampled.rgb), sampled.a);
+    }
+    float metal = metallic;
+    float rough = roughness;
+    if (has_metallic_roughness_texture) {
+        vec4 sampled = texture(metallic_roughness_texture, v_tex_coords);
+        rough *= sampled.g;
+        metal *= sampled.b;
+    }
+    rough = clamp(rough, 0.04, 1.0);
+    metal = clamp(metal, 0.0, 1.0);
+
+    vec3 normal = normalize(v_normal);
+    if (double_sided && !gl_FrontFacing) {
+        normal = -normal;
+    }
+    if (has_normal_texture) {
+        normal = perturb_normal(normal);
+    }
+    vec3 to_camera = normalize(camera_position - v_position);
+    float n_dot_v = max(dot(normal, to_camera), 0.0001);
+    vec3 f0 = mix(vec3(0.04), base.rgb, metal);
+
+    vec3 lit = vec3(0.0);
+    for (int i = 0; i < light_count; i++) {
+        Light light = lights[i];
+        vec3 to_light;
+        float strength = 1.0;
+        if (light.position.w == 0.0) {
+            to_light = -light.direction;
+        } else {
+            vec3 offset = light.position.xyz - v_position;
+            float distance = length(offset);
+            to_light = offset / distance;
+            float fade = clamp(1.0 - pow(distance / light.range, 4.0), 0.0, 1.0);
+            strength = fade * fade / (distance * distance + 1.0);
+            float cone = dot(-to_light, light.direction);
+            strength *= smoothstep(light.outer_cone, max(light.inner_cone, light.outer_cone + 0.0001), cone);
+        }
+
+        float n_dot_l = dot(normal, to_light);
+        if (n_dot_l <= 0.0 || strength <= 0.0) {
+            continue;
+        }
+        strength *= shadow(light.shadow_tile, light.position.w == 0.0, v_position, normal, to_light);
+
+        vec3 half_direction = normalize(to_light + to_camera);
+        float n_dot_h = max(dot(normal, half_direction), 0.0);
+        vec3 fresnel = fresnel_schlick(max(dot(half_direction, to_camera), 0.0), f0);
+        vec3 specular = distribution_ggx(n_dot_h, rough) * geometry_smith(n_dot_v, n_dot_l, rough) * fresnel
+            / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - fresnel) * (1.0 - metal) * base.rgb / PI;
+        lit += (diffuse + specular) * light.color * strength * n_dot_l;
+    }
+
+    vec3 ambient_light;
+    if (has_environment) {
+        vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, rough);
+        vec3 diffuse = texture(irradiance_map, normal).rgb * base.rgb * (1.0 - fresnel) * (1.0 - metal);
+        vec3 reflected = reflect(-to_camera, normal);
+        vec3 prefiltered = textureLod(prefiltered_map, reflected, rough * (prefiltered_levels - 1.0)).rgb;
+        vec2 brdf = texture(brdf_lut, vec2(n_dot_v, rough)).rg;
+        vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+        ambient_light = (diffuse + specular) * environment_intensity;
+    } else {
+        ambient_light = ambient * base.rgb;
+    }
+    if (has_occlusion_texture) {
+        ambient_light *= mix(1.0, texture(occlusion_texture, v_tex_coords).r, occlusion_strength);
+    }
+    lit += ambient_light;
+
+    vec3 glow = emissive;
+    if (has_emissive_texture) {
+        glow *= to_linear(texture(emissive_texture, v_tex_coords).rgb);
+    }
+    lit += glow;
+
+    // Reinhard tone mapping then back to sRGB
+    lit = lit / (lit + 1.0);
+    out_color = vec4(pow(lit, vec3(1.0 / 2.2)), base.a);
+}
+";
+
+/// Writes `PbrMaterial`s into the G-buffer instead of lighting them, used with `LIT_VS`.
+/// Transparent pixels are cut out since there's only room for one surface per pixel
+pub const GBUFFER_FS: &str = r"
+#version 140
+
+in vec3 v_position;
+in vec3 v_normal;
+in vec2 v_tex_coords;
+
+out vec4 out_albedo;
+out vec4 out_normal;
+out vec4 out_material;
+out vec4 out_light;
+
+uniform vec4 base_color;
+uniform bool has_base_color_texture;
+uniform sampler2D base_color_texture;
+uniform float metallic;
+uniform float roughness;
+uniform bool has_metallic_roughness_texture;
+uniform sampler2D metallic_roughness_texture;
+uniform bool has_normal_texture;
+uniform sampler2D normal_texture;
+uniform float normal_scale;
+uniform bool has_occlusion_texture;
+uniform sampler2D occlusion_texture;
+uniform float occlusion_strength;
+uniform vec3 emissive;
+uniform bool has_emissive_texture;
+uniform sampler2D emissive_texture;
+uniform bool double_sided;
+
+vec3 to_linear(vec3 color) {
+    return pow(color, vec3(2.2));
+}
+
+vec3 perturb_normal(vec3 normal) {
+    vec3 dp1 = dFdx(v_position);
+    vec3 dp2 = dFdy(v_position);
+    vec2 duv1 = dFdx(v_tex_coords);
+    vec2 duv2 = dFdy(v_tex_coords);
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    float scale = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    mat3 tbn = mat3(tangent * scale, bitangent * scale, normal);
+    vec3 sampled = texture(normal_texture, v_tex_coords).xyz * 2.0 - 1.0;
+    sampled.xy *= normal_scale;
+    return normalize(tbn * sampled);
+}
+
+void main() {
+    vec4 base = base_color;
+    if (has_base_color_texture) {
+        vec4 sampled = texture(base_color_texture, v_tex_coords);
+        base *= vec4(to_linear(sampled.rgb), sampled.a);
+    }
+    if (base.a < 0.5) {
+        discard;
+    }
+    float metal = metallic;
+    float rough = roughness;
+    if (has_metallic_roughness_texture) {
+        vec4 sampled = texture(metallic_roughness_texture, v_tex_coords);
+        rough *= sampled.g;
+        metal *= sampled.b;
+    }
+
+    vec3 normal = normalize(v_normal);
+    if (double_sided && !gl_FrontFacing) {
+        normal = -normal;
+    }
+    if (has_normal_texture) {
+        normal = perturb_normal(normal);
+    }
+    float occlusion = 1.0;
+    if (has_occlusion_texture) {
+        occlusion = mix(1.0, texture(occlusion_texture, v_tex_coords).r, occlusion_strength);
+    }
+    vec3 glow = emissive;
+    if (has_emissive_texture) {
+        glow *= to_linear(texture(emissive_texture, v_tex_coords).rgb);
+    }
+
+    // Back to sRGB so the 8 bits go further in the darks
+    out_albedo = vec4(pow(base.rgb, vec3(1.0 / 2.2)), 1.0);
+    // w says something was drawn here
+    out_normal = vec4(normal, 1.0);
+    out_material = vec4(clamp(metal, 0.0, 1.0), clamp(rough, 0.04, 1.0), occlusion, 1.0);
+    // The lights are added on top of this
+    out_light = vec4(glow, 1.0);
+}
+";
+
+/// Draws a light's volume in clip space, `volume` is the identity for a quad over the whole screen
+pub const DEFERRED_LIGHT_VS: &str = r"
+#version 140
+
+in vec3 position;
+
+uniform mat4 volume;
+
+void main() {
+    gl_Position = volume * vec4(position, 1.0);
+}
+";
+
+/// Adds one light to the light buffer from what's in the G-buffer, same lighting as `PBR_FS`
+pub const DEFERRED_LIGHT_FS: &str = r"
+#version 140
+
+#define PI 3.14159265359
+
+struct Light {
+    // w is 0 for directional lights
+    vec4 position;
+    vec3 direction;
+    vec3 color;
+    float range;
+    float inner_cone;
+    float outer_cone;
+    int shadow_tile;
+};
+
+#include <shadows>
+
+out vec4 out_light;
+
+uniform Light light;
+uniform vec2 resolution;
+uniform mat4 inverse_persp_view;
+uniform sampler2D albedo_buffer;
+uniform sampler2D normal_buffer;
+uniform sampler2D material_buffer;
+uniform sampler2D depth_buffer;
+
+float distribution_ggx(float n_dot_h, float rough) {
+    float a = rough * rough;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float rough) {
+    float k = (rough + 1.0) * (rough + 1.0) / 8.0;
+    float gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return gv * gl;
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / resolution;
+    vec4 normal_sample = texture(normal_buffer, uv);
+    if (normal_sample.w == 0.0) {
+        discard;
+    }
+    vec4 position = inverse_persp_view * vec4(vec3(uv, texture(depth_buffer, uv).r) * 2.0 - 1.0, 1.0);
+    position /= position.w;
+    vec3 normal = normalize(normal_sample.xyz);
+    vec3 base = pow(texture(albedo_buffer, uv).rgb, vec3(2.2));
+    vec3 material = texture(material_buffer, uv).rgb;
+    float metal = material.r;
+    float rough = material.g;
+
+    vec3 to_light;
+    float strength = 1.0;
+    if (light.position.w == 0.0) {
+        to_light = -light.direction;
+    } else {
+        vec3 offset = light.position.xyz - position.xyz;
+        float distance = length(offset);
+        to_light = offset / distance;
+        float fade = clamp(1.0 - pow(distance / light.range, 4.0), 0.0, 1.0);
+        strength = fade * fade / (distance * distance + 1.0);
+        float cone = dot(-to_light, light.direction);
+        strength *= smoothstep(light.outer_cone, max(light.inner_cone, light.outer_cone + 0.0001), cone);
+    }
+    float n_dot_l = dot(normal, to_light);
+    if (n_dot_l <= 0.0 || strength <= 0.0) {
+        discard;
+    }
+    strength *= shadow(light.shadow_tile, light.position.w == 0.0, position.xyz, normal, to_light);
+
+    vec3 to_camera = normalize(camera_position - position.xyz);
+    float n_dot_v = max(dot(normal, to_camera), 0.0001);
+    vec3 f0 = mix(vec3(0.04), base, metal);
+    vec3 half_direction = normalize(to_light + to_camera);
+    float n_dot_h = max(dot(normal, half_direction), 0.0);
+    vec3 fresnel = fresnel_schlick(max(dot(half_direction, to_camera), 0.0), f0);
+    vec3 specular = distribution_ggx(n_dot_h, rough) * geometry_smith(n_dot_v, n_dot_l, rough) * fresnel
+        / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metal) * base / PI;
+    out_light = vec4((diffuse + specular) * light.color * strength * n_dot_l, 1.0);
+}
+";
+
+pub const DEFERRED_COMPOSITE_VS: &str = r"
+#version 140
+
+in vec3 position;
+
+out vec2 v_uv;
+
+void main() {
+    v_uv = position.xy * 0.5 + 0.5;
+    gl_Position = vec4(position, 1.0);
+}
+";
+
+/// Adds the ambient or environment light to the light buffer then tone maps it, or shows one of the
+/// G-buffer's channels. Writes the G-buffer's depth so forward drawing afterwards is hidden properly
+pub const DEFERRED_COMPOSITE_FS: &str = r"
+#version 140
+
+in vec2 v_uv;
+
+out vec4 out_color;
+
+uniform sampler2D albedo_buffer;
+uniform sampler2D normal_buffer;
+uniform sampler2D material_buffer;
+uniform sampler2D depth_buffer;
+uniform sampler2D light_buffer;
+uniform mat4 inverse_persp_view;
+uniform vec3 camera_position;
+uniform vec3 ambient;
+// 0 is lit, then albedo, normal, material, depth and light
+uniform int view;
+
+uniform bool has_environment;
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform float prefiltered_levels;
+uniform sampler2D brdf_lut;
+uniform float environment_intensity;
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float rough) {
+    return f0 + (max(vec3(1.0 - rough), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    vec4 normal_sample = texture(normal_buffer, v_uv);
+    // Nothing was drawn here, whatever's already on the screen stays
+    if (normal_sample.w == 0.0) {
+        discard;
+    }
+    float depth = texture(depth_buffer, v_uv).r;
+    gl_FragDepth = depth;
+    vec4 position = inverse_persp_view * vec4(vec3(v_uv, depth) * 2.0 - 1.0, 1.0);
+    position /= position.w;
+    vec3 albedo = texture(albedo_buffer, v_uv).rgb;
+    vec3 normal = normalize(normal_sample.xyz);
+    vec3 material = texture(material_buffer, v_uv).rgb;
+    vec3 light = texture(light_buffer, v_uv).rgb;
+
+    if (view == 1) {
+        out_color = vec4(albedo, 1.0);
+        return;
+    } else if (view == 2) {
+        out_color = vec4(normal * 0.5 + 0.5, 1.0);
+        return;
+    } else if (view == 3) {
+        out_color = vec4(material, 1.0);
+        return;
+    } else if (view == 4) {
+        // Distance from the camera, darker is closer
+        float distance = length(position.xyz - camera_position);
+        out_color = vec4(vec3(distance / (distance + 10.0)), 1.0);
+        return;
+    } else if (view == 5) {
+        light = light / (light + 1.0);
+        out_color = vec4(pow(light, vec3(1.0 / 2.2)), 1.0);
+        return;
+    }
+
+    vec3 base = pow(albedo, vec3(2.2));
+    float metal = material.r;
+    float rough = material.g;
+    vec3 ambient_light;
+    if (has_environment) {
+        vec3 to_camera = normalize(camera_position - position.xyz);
+        float n_dot_v = max(dot(normal, to_camera), 0.0001);
+        vec3 f0 = mix(vec3(0.04), base, metal);
+        vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, rough);
+        vec3 diffuse = texture(irradiance_map, normal).rgb * base * (1.0 - fresnel) * (1.0 - metal);
+        vec3 reflected = reflect(-to_camera, normal);
+        vec3 prefiltered = textureLod(prefiltered_map, reflected, rough * (prefiltered_levels - 1.0)).rgb;
+        vec2 brdf = texture(brdf_lut, vec2(n_dot_v, rough)).rg;
+        vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+        ambient_light = (diffuse + specular) * environment_intensity;
+    } else {
+        ambient_light = ambient * base;
+    }
+    vec3 lit = light + ambient_light * material.b;
+
+    lit = lit / (lit + 1.0);
+    out_color = vec4(pow(lit, vec3(1.0 / 2.2)), 1.0);
+}
+";
+
+/// Draws a quad over one face of a cubemap, `v_direction` is the direction out of the cube at each pixel
+pub const ENVIRONMENT_VS: &str = r"
+#version 140
+
+in vec2 position;
+
+out vec3 v_direction;
+out vec2 v_uv;
+
+uniform int face;
+
+void main() {
+    float s = position.x;
+    float t = position.y;
+    if (face == 0) {
+        v_direction = vec3(1.0, -t, -s);
+    } else if (face == 1) {
+        v_direction = vec3(-1.0, -t, s);
+    } else if (face == 2) {
+        v_direction = vec3(s, 1.0, t);
+    } else if (face == 3) {
+        v_direction = vec3(s, -1.0, -t);
+    } else if (face == 4) {
+        v_direction = vec3(s, -t, 1.0);
+    } else {
+        v_direction = vec3(-s, -t, -1.0);
+    }
+    v_uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+pub const IRRADIANCE_FS: &str = r"
+#version 140
+
+#define PI 3.14159265359
+
+in vec3 v_direction;
+
+out vec4 out_color;
+
+uniform samplerCube sky;
+
+uniform bool srgb;
+
+// Looked up upside down like the skybox shader
+vec3 sky_color(vec3 direction) {
+    vec3 color = texture(sky, vec3(direction.x, -direction.y, direction.z)).rgb;
+    return srgb ? pow(color, vec3(2.2)) : color;
+}
+
+void main() {
+    vec3 normal = normalize(v_direction);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += 0.05) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += 0.05) {
+            vec3 local = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 direction = local.x * right + local.y * up + local.z * normal;
+            irradiance += sky_color(direction) * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+    out_color = vec4(PI * irradiance / samples, 1.0);
+}
+";
+
+pub const PREFILTER_FS: &str = r"
+#version 140
+
+#define PI 3.14159265359
+#define SAMPLES 256u
+
+in vec3 v_direction;
+
+out vec4 out_color;
+
+uniform samplerCube sky;
+uniform float roughness;
+uniform bool srgb;
+
+vec3 sky_color(vec3 direction) {
+    vec3 color = texture(sky, vec3(direction.x, -direction.y, direction.z)).rgb;
+    return srgb ? pow(color, vec3(2.2)) : color;
+}
+
+vec2 hammersley(uint i) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(SAMPLES), float(bits) * 2.3283064365386963e-10);
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float rough) {
+    float a = rough * rough;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 half_direction = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * half_direction.x + bitangent * half_direction.y + normal * half_direction.z);
+}
+
+void main() {
+    // Assumes the view is straight on, so the reflection is the normal
+    vec3 normal = normalize(v_direction);
+    vec3 color = vec3(0.0);
+    float weight = 0.0;
+    for (uint i = 0u; i < SAMPLES; i++) {
+        vec3 half_direction = importance_sample_ggx(hammersley(i), normal, roughness);
+        vec3 to_light = normalize(2.0 * dot(normal, half_direction) * half_direction - normal);
+        float n_dot_l = dot(normal, to_light);
+        if (n_dot_l > 0.0) {
+            color += sky_color(to_light) * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    out_color = vec4(color / max(weight, 0.0001), 1.0);
+}
+";
+
+/// Scale and bias on the fresnel for each view angle (x) and roughness (y), same for every sky
+pub const BRDF_LUT_FS: &str = r"
+#version 140
+
+#define PI 3.14159265359
+#define SAMPLES 512u
+
+in vec2 v_uv;
+
+out vec4 out_color;
+
+vec2 hammersley(uint i) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(SAMPLES), float(bits) * 2.3283064365386963e-10);
+}
+
+float geometry_schlick(float n_dot, float rough) {
+    // k is different for image based lighting
+    float k = rough * rough / 2.0;
+    return n_dot / (n_dot * (1.0 - k) + k);
+}
+
+void main() {
+    float n_dot_v = max(v_uv.x, 0.0001);
+    float rough = v_uv.y;
+    float a = rough * rough;
+    vec3 to_camera = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint i = 0u; i < SAMPLES; i++) {
+        vec2 xi = hammersley(i);
+        float phi = 2.0 * PI * xi.x;
+        float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+        float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        vec3 half_direction = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+        vec3 to_light = normalize(2.0 * dot(to_camera, half_direction) * half_direction - to_camera);
+
+        float n_dot_l = max(to_light.z, 0.0);
+        float n_dot_h = max(half_direction.z, 0.0);
+        float v_dot_h = max(dot(to_camera, half_direction), 0.0);
+        if (n_dot_l > 0.0) {
+            float g = geometry_schlick(n_dot_v, rough) * geometry_schlick(n_dot_l, rough);
+            float visibility = g * v_dot_h / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+    out_color = vec4(scale / float(SAMPLES), bias / float(SAMPLES), 0.0, 1.0);
+}
+";
+
+/// Wraps a panorama around the sky, written upside down like the faces `Skybox::prepare_draw` blits
+pub const EQUIRECTANGULAR_FS: &str = r"
+#version 140
+
+#define PI 3.14159265359
+
+in vec3 v_direction;
+
+out vec4 out_color;
+
+uniform sampler2D panorama;
+uniform bool srgb;
+
+void main() {
+    vec3 direction = normalize(v_direction);
+    direction.y = -direction.y;
+    vec2 uv = vec2(atan(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+    vec3 color = texture(panorama, uv).rgb;
+    out_color = vec4(srgb ? pow(color, vec3(2.2)) : color, 1.0);
+}
+";
+
+/// `ProceduralSky` baked into a cubemap, upside down like `EQUIRECTANGULAR_FS`
+pub const PROCEDURAL_SKY_FS: &str = r"
+#version 140
+
+#define PI 3.14159265359
+#define PRIMARY_STEPS 16
+#define LIGHT_STEPS 8
+
+in vec3 v_direction;
+
+out vec4 out_color;
+
+uniform bool atmosphere;
+uniform vec3 zenith;
+uniform vec3 horizon;
+uniform vec3 ground;
+uniform vec3 to_sun;
+uniform float sun_intensity;
+
+// Where a ray hits a sphere at the origin, x is the near hit and y the far one
+vec2 ray_sphere(vec3 origin, vec3 direction, float radius) {
+    float b = 2.0 * dot(direction, origin);
+    float c = dot(origin, origin) - radius * radius;
+    float d = b * b - 4.0 * c;
+    if (d < 0.0) {
+        return vec2(1e5, -1e5);
+    }
+    return vec2(-b - sqrt(d), -b + sqrt(d)) / 2.0;
+}
+
+// Single scattering through an earth sized atmosphere, Rayleigh for the blue and Mie for the haze
+vec3 scatter(vec3 direction) {
+    const float planet_radius = 6371e3;
+    const float atmosphere_radius = 6471e3;
+    const vec3 rayleigh = vec3(5.5e-6, 13.0e-6, 22.4e-6);
+    const float mie = 21e-6;
+    const float rayleigh_height = 8e3;
+    const float mie_height = 1.2e3;
+    const float g = 0.758;
+    vec3 origin = vec3(0.0, planet_radius + 1e3, 0.0);
+
+    vec2 hit = ray_sphere(origin, direction, atmosphere_radius);
+    hit.y = min(hit.y, ray_sphere(origin, direction, planet_radius).x);
+    if (hit.y < 0.0) {
+        hit.y = ray_sphere(origin, direction, atmosphere_radius).y;
+    }
+    float step_size = hit.y / float(PRIMARY_STEPS);
+
+    float mu = dot(direction, to_sun);
+    float phase_rayleigh = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    float phase_mie = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (mu * mu + 1.0))
+        / (pow(1.0 + g * g - 2.0 * mu * g, 1.5) * (2.0 + g * g));
+
+    vec3 total_rayleigh = vec3(0.0);
+    vec3 total_mie = vec3(0.0);
+    float depth_rayleigh = 0.0;
+    float depth_mie = 0.0;
+    for (int i = 0; i < PRIMARY_STEPS; i++) {
+        vec3 position = origin + direction * (float(i) + 0.5) * step_size;
+        float height = length(position) - planet_radius;
+        float step_rayleigh = exp(-height / rayleigh_height) * step_size;
+        float step_mie = exp(-height / mie_height) * step_size;
+        depth_rayleigh += step_rayleigh;
+        depth_mie += step_mie;
+
+        float light_step = ray_sphere(position, to_sun, atmosphere_radius).y / float(LIGHT_STEPS);
+        float light_rayleigh = 0.0;
+        float light_mie = 0.0;
+        for (int j = 0; j < LIGHT_STEPS; j++) {
+            vec3 light_position = position + to_sun * (float(j) + 0.5) * light_step;
+            float light_height = length(light_position) - planet_radius;
+            light_rayleigh += exp(-light_height / rayleigh_height) * light_step;
+            light_mie += exp(-light_height / mie_height) * light_step;
+        }
+        vec3 attenuation = exp(-(mie * (depth_mie + light_mie) + rayleigh * (depth_rayleigh + light_rayleigh)));
+        total_rayleigh += step_rayleigh * attenuation;
+        total_mie += step_mie * attenuation;
+    }
+    return sun_intensity * (phase_rayleigh * rayleigh * total_rayleigh + phase_mie * mie * total_mie);
+}
+
+void main() {
+    vec3 direction = normalize(v_direction);
+    direction.y = -direction.y;
+    vec3 color;
+    if (atmosphere) {
+        color = scatter(direction);
+    } else if (direction.y >= 0.0) {
+        color = mix(horizon, zenith, sqrt(direction.y));
+    } else {
+        color = mix(horizon, ground, min(-direction.y * 4.0, 1.0));
+    }
+    out_color = vec4(color, 1.0);
+}
+";